@@ -0,0 +1,273 @@
+use std::collections::BTreeSet;
+use std::str::FromStr;
+
+use chrono::NaiveDateTime;
+use galileo_types::geo::impls::GeoPoint2d;
+use galileo_types::geo::NewGeoPoint;
+use serde_json::Value;
+
+use crate::clock::Clock;
+use crate::http;
+use crate::noaa::Station;
+use crate::noaa::StationType;
+use crate::prelude::*;
+use crate::regions;
+use crate::state;
+use crate::Config;
+
+/// What a field of a NOAA response must hold for it to be parsed.
+#[derive(Debug, Copy, Clone)]
+enum Kind {
+    String,
+    Number,
+    Integer,
+    /// A number encoded as a string.
+    NumericString,
+    /// A `%Y-%m-%d %H:%M` time.
+    Time,
+}
+
+impl Kind {
+    fn matches(self, value: &Value) -> bool {
+        match self {
+            Kind::String => value.is_string(),
+            Kind::Number => value.is_number(),
+            Kind::Integer => value.is_u64(),
+            Kind::NumericString => value.as_str().is_some_and(|s| f64::from_str(s).is_ok()),
+            Kind::Time => value
+                .as_str()
+                .is_some_and(|s| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").is_ok()),
+        }
+    }
+}
+
+/// Checks that every object in `items` has `fields` of the expected kinds,
+/// returning a description of each problem. When fields are missing, the
+/// unexpected fields are listed too, since they're likely renames.
+fn check_items(what: &str, items: Option<&Vec<Value>>, fields: &[(&str, Kind)]) -> Vec<String> {
+    let Some(items) = items else {
+        return vec![format!("{what}: missing")];
+    };
+    if items.is_empty() {
+        return vec![format!("{what}: empty")];
+    }
+
+    let mut problems = vec![];
+    let mut missing = false;
+    for (field, kind) in fields {
+        let absent = items
+            .iter()
+            .filter(|item| item.get(field).is_none())
+            .count();
+        let mismatched = items
+            .iter()
+            .filter(|item| item.get(field).is_some_and(|value| !kind.matches(value)))
+            .count();
+        if absent > 0 {
+            missing = true;
+            problems.push(format!(
+                "{what}[].{field}: missing from {absent} of {}",
+                items.len()
+            ));
+        }
+        if mismatched > 0 {
+            problems.push(format!(
+                "{what}[].{field}: not a valid {kind:?} in {mismatched} of {}",
+                items.len()
+            ));
+        }
+    }
+
+    if missing {
+        let unexpected: BTreeSet<_> = items
+            .iter()
+            .filter_map(Value::as_object)
+            .flat_map(|item| item.keys())
+            .filter(|key| !fields.iter().any(|(field, _)| field == key))
+            .collect();
+        if !unexpected.is_empty() {
+            problems.push(format!(
+                "{what}[]: unexpected fields, possibly renamed: {}",
+                unexpected
+                    .into_iter()
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+    }
+    problems
+}
+
+/// Fetches `url`, bypassing the cache, and prints whether its response has
+/// the expected fields. Returns the response, if it could be fetched, and
+/// whether it passed.
+async fn check(
+    name: &str,
+    url: &str,
+    validate: impl FnOnce(&Value) -> Vec<String>,
+) -> (Option<Value>, bool) {
+    let resp = match http::fetch_json_uncached(url).await {
+        Ok(resp) => resp,
+        Err(err) => {
+            println!("FAIL {name}: {err}");
+            return (None, false);
+        },
+    };
+
+    let problems = validate(&resp);
+    if problems.is_empty() {
+        println!("ok   {name}");
+    } else {
+        println!("FAIL {name}");
+        for problem in &problems {
+            println!("       {problem}");
+        }
+    }
+    (Some(resp), problems.is_empty())
+}
+
+/// Fetches the region's tide station and a harmonic current station from the
+/// live NOAA API and checks that their responses still have the fields that
+/// `Station` parses, printing which are missing or have changed type.
+pub async fn check_api(config: &Config) -> Result<()> {
+    let api_proxy = config.api_proxy();
+    let region = config.region.or_else(regions::load).unwrap_or_default();
+    let clock = Clock::new(config.now, region.time_zone);
+    let start_date = state::prediction_start(clock.now().date()).log()?;
+    let hours = state::PREDICTION_HOURS;
+    let mut passed = true;
+
+    let (_, ok) = check(
+        &format!("Metadata for tide station {}", region.tide_station),
+        &Station::metadata_url(region.tide_station, api_proxy.as_ref()),
+        |resp| {
+            check_items(
+                "stations",
+                resp["stations"].as_array(),
+                &[
+                    ("name", Kind::String),
+                    ("lat", Kind::Number),
+                    ("lng", Kind::Number),
+                ],
+            )
+        },
+    )
+    .await;
+    passed &= ok;
+
+    let (_, ok) = check(
+        &format!("Tide predictions for station {}", region.tide_station),
+        &Station::tide_prediction_url(region.tide_station, start_date, hours, api_proxy.as_ref()),
+        |resp| {
+            check_items(
+                "predictions",
+                resp["predictions"].as_array(),
+                &[("t", Kind::Time), ("type", Kind::String)],
+            )
+        },
+    )
+    .await;
+    passed &= ok;
+
+    let (list, ok) = check(
+        "Current prediction station list",
+        &Station::list_url(api_proxy.as_ref()),
+        |resp| {
+            check_items(
+                "stations",
+                resp["stations"].as_array(),
+                &[
+                    ("id", Kind::String),
+                    ("name", Kind::String),
+                    ("lat", Kind::Number),
+                    ("lng", Kind::Number),
+                    ("type", Kind::String),
+                ],
+            )
+        },
+    )
+    .await;
+    passed &= ok;
+
+    let in_region = |s: &&Value| {
+        let (Some(lat), Some(lon)) = (s["lat"].as_f64(), s["lng"].as_f64()) else {
+            return false;
+        };
+        let lat_range = region.lat_range;
+        let lon_range = region.lon_range;
+        lat_range.0.min(lat_range.1) <= lat
+            && lat <= lat_range.0.max(lat_range.1)
+            && lon_range.0.min(lon_range.1) <= lon
+            && lon <= lon_range.0.max(lon_range.1)
+    };
+    let station = list
+        .as_ref()
+        .and_then(|list| list["stations"].as_array())
+        .and_then(|stations| {
+            stations
+                .iter()
+                .filter(in_region)
+                .find(|s| s["type"].as_str() == Some("H"))
+        })
+        .and_then(|s| {
+            Some(Station {
+                id: s["id"].as_str()?.to_string(),
+                name: s["name"].as_str()?.to_string(),
+                loc: GeoPoint2d::latlon(s["lat"].as_f64()?, s["lng"].as_f64()?),
+                type_: StationType::Harmonic,
+                api_proxy: api_proxy.clone(),
+            })
+        });
+    match station {
+        Some(station) => {
+            let (_, ok) = check(
+                &format!("Current predictions for station {}", station.id),
+                &station.current_prediction_url(start_date, hours),
+                |resp| {
+                    check_items(
+                        "current_predictions.cp",
+                        resp["current_predictions"]["cp"].as_array(),
+                        &[
+                            ("Time", Kind::Time),
+                            ("Speed", Kind::NumericString),
+                            ("Direction", Kind::Integer),
+                        ],
+                    )
+                },
+            )
+            .await;
+            passed &= ok;
+        },
+        None => {
+            println!(
+                "FAIL Current predictions: no harmonic station in {} to check",
+                region.name
+            );
+            passed = false;
+        },
+    }
+
+    if passed {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "The NOAA API responses didn't match what KayakNav expects"
+        ))
+    }
+}
+
+#[cfg(all(test, feature = "network", not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::regions::Region;
+
+    #[tokio::test]
+    async fn live_api_matches_parsers() {
+        let config = Config {
+            region: Some(Region::default()),
+            ..Default::default()
+        };
+        check_api(&config).await.unwrap();
+    }
+}
@@ -1,6 +1,20 @@
+use std::path::PathBuf;
+use std::process;
+
 use bpaf::Parser;
+use chrono::NaiveDateTime;
+use kayaknav::check_api;
+use kayaknav::coordinates::CoordinateSystem;
+use kayaknav::dump_urls;
+use kayaknav::regions::Region;
+use kayaknav::regions::REGIONS;
 use kayaknav::run;
+use kayaknav::scheduling::QuantileMethod;
+use kayaknav::scheduling::TideSpeedProfile;
+use kayaknav::state::galileo_state::TILE_SOURCE_MAX_LEVEL;
 use kayaknav::Config;
+use kayaknav::OutputFormat;
+use kayaknav::SpeedInterpolation;
 use winit::event_loop::EventLoop;
 use winit::window::WindowBuilder;
 
@@ -19,10 +33,191 @@ fn parse_args() -> Config {
         .fallback(default_config.api_proxy_url)
         .display_fallback();
 
+    let output = bpaf::long("output")
+        .help("Print output to stdout in the given format when exiting. Supported formats: json (the trip plan), currents-geojson (every station's current at the selected time), stations (the region's stations, for --stations-file), current-field-csv and current-field-parquet (every station's speed and direction over the whole prediction range, one column pair per station, aligned to a common time grid).")
+        .argument::<OutputFormat>("FORMAT")
+        .optional();
+
+    let arrow_update_epsilon = bpaf::long("arrow-update-epsilon")
+        .help("Skip redrawing the current arrows when changing the time unless some station's current changes by more than this many knots. Set to 0 to always redraw.")
+        .argument::<f64>("KNOTS")
+        .fallback(default_config.arrow_update_epsilon)
+        .display_fallback();
+
+    let initial_zoom = bpaf::long("initial-zoom")
+        .help("The zoom level, from 0 to the max tile level, of the initial map view. Defaults to the last viewed map area, or 12.")
+        .argument::<u32>("ZOOM")
+        .optional();
+
+    let max_tile_level = bpaf::long("max-tile-level")
+        .help("The highest zoom level to fetch map tiles at. Lower it to reduce tile requests; zooming in further enlarges the tiles from this level.")
+        .argument::<u32>("LEVEL")
+        .guard(
+            |level| *level <= TILE_SOURCE_MAX_LEVEL,
+            "The tile server only serves zoom levels up to 19",
+        )
+        .fallback(default_config.max_tile_level)
+        .display_fallback();
+
+    let coordinate_system = bpaf::long("coordinate-system")
+        .help("The coordinate system used to display and export coordinates. Supported systems: wgs84 (EPSG:4326), epsg:3857.")
+        .argument::<CoordinateSystem>("CRS")
+        .fallback(default_config.coordinate_system);
+
+    let max_station_distance = bpaf::long("max-station-distance")
+        .help("Trip legs that sample currents from stations farther than this many miles away are marked low confidence.")
+        .argument::<f64>("MILES")
+        .fallback(default_config.max_station_distance)
+        .display_fallback();
+
+    let default_pause_hours = bpaf::long("default-pause-hours")
+        .help("The initial duration, in hours, of pause waypoints. Can be changed in the Controls window.")
+        .argument::<f64>("HOURS")
+        .guard(|hours| *hours >= 0.0, "The pause duration must not be negative")
+        .fallback(default_config.default_pause_hours)
+        .display_fallback();
+
+    let hit_tolerance = bpaf::long("hit-tolerance")
+        .help("How close, in logical pixels, the pointer must be to a waypoint or station to select it.")
+        .argument::<f64>("PIXELS")
+        .fallback(default_config.hit_tolerance)
+        .display_fallback();
+
+    let sharp_turn_warning = bpaf::long("sharp-turn-warning")
+        .help("Flag waypoints where the route turns by more than this many degrees, which often means a waypoint is misplaced. Can also be enabled in the Controls window.")
+        .argument::<f64>("DEGREES")
+        .guard(
+            |degrees| (0.0..=180.0).contains(degrees),
+            "The turn angle must be between 0 and 180 degrees",
+        )
+        .optional();
+
+    let max_path_segment = bpaf::long("max-path-segment")
+        .help("Split integrated leg paths so that drawn points are at most this many miles apart, e.g. to smooth long crossings. Defaults to one 5 minute integration step.")
+        .argument::<f64>("MILES")
+        .guard(|miles| *miles > 0.0, "The path segment length must be positive")
+        .optional();
+
+    let planning_horizon_days = bpaf::long("planning-horizon")
+        .help("Only allow selecting times up to this many days from now, to shorten the time slider. The full two months of predictions are still fetched, and the limit can be lifted in the UI. Defaults to no limit.")
+        .argument::<f64>("DAYS")
+        .guard(|days| *days > 0.0, "The planning horizon must be positive")
+        .optional();
+
+    let max_fps = bpaf::long("max-fps")
+        .help("Most frames drawn per second, to save power while animating. Redraws requested sooner are deferred. Defaults to 60.")
+        .argument::<f64>("FPS")
+        .guard(|fps| *fps > 0.0, "The frame rate cap must be positive")
+        .fallback(default_config.max_fps);
+
+    let presentation_speed = bpaf::long("presentation-speed")
+        .help("Minutes the selected time advances per second in presentation mode, toggled with P, which jumps to now and hides the sidebar to show the currents to others. Defaults to 10.")
+        .argument::<f64>("MINUTES")
+        .guard(|minutes| *minutes > 0.0, "The presentation speed must be positive")
+        .fallback(default_config.presentation_speed);
+
+    let sweep_quantile_method = bpaf::long("sweep-quantile-method")
+        .help("How the departure sweep's fastest 20% cutoff is computed from the trip durations on either side of it. Supported methods: nearest (the nearer duration, so the cutoff is always a real departure's), linear (interpolated between them), midpoint (halfway between them). Defaults to nearest.")
+        .argument::<QuantileMethod>("METHOD")
+        .fallback(default_config.sweep_quantile_method);
+
+    let speed_interpolation = bpaf::long("speed-interpolation")
+        .help("How current speeds are filled in between NOAA's predictions when resampling them. Supported methods: linear (straight lines), sinusoidal (quarter sine waves between slack and max, following the shape of tidal currents more closely, especially for subordinate stations, which only predict slacks and maxes). Defaults to linear.")
+        .argument::<SpeedInterpolation>("METHOD")
+        .fallback(default_config.speed_interpolation);
+
+    let arrow_smoothing = bpaf::long("arrow-smoothing")
+        .help("Draw the current arrows with speeds and directions averaged over this many 30 minute predictions centered on each, so they change more steadily when animated. Trip calculations still use the unsmoothed predictions. Off by default.")
+        .argument::<usize>("PREDICTIONS")
+        .guard(|window| *window > 0, "The smoothing window must be positive")
+        .optional();
+
+    let tide_speed_profile = bpaf::long("tide-speed-profile")
+        .help("Experimental: slow the paddling speed at low tide, for routes through shallows. Given as LOW:HIGH:FACTOR, the speed is multiplied by FACTOR when the tide station's height is at or below LOW feet above MLLW, unchanged at or above HIGH feet, and scaled linearly in between. Off by default; can also be enabled in the UI.")
+        .argument::<TideSpeedProfile>("LOW:HIGH:FACTOR")
+        .optional();
+
+    let region_keys: Vec<_> = REGIONS.iter().map(|region| region.key).collect();
+    let region = bpaf::long("region")
+        .help(
+            format!(
+                "The region to fetch predictions for. Defaults to the region last selected in the UI. Supported regions: {}.",
+                region_keys.join(", ")
+            )
+            .as_str(),
+        )
+        .argument::<Region>("NAME")
+        .optional();
+
+    let stations_file = bpaf::long("stations-file")
+        .help("Read the stations to fetch predictions for from this JSON file instead of NOAA's station list, e.g. to pin a curated set. Write one with --output stations.")
+        .argument::<PathBuf>("PATH")
+        .optional();
+
+    let observed_currents = bpaf::long("observed-currents")
+        .help("Use observed currents instead of predictions for the last 72 hours at stations with a real-time current meter.")
+        .switch();
+
+    let debug_panel = bpaf::long("debug-panel")
+        .help("Show a window with the raw NOAA response and the first parsed predictions of the station selected in the table, for diagnosing arrows that look wrong.")
+        .switch();
+
+    let now = bpaf::long("now")
+        .help("A time in the region's time zone, as YYYY-MM-DD HH:MM, to treat as the current time, e.g. to review a past trip. Predictions are fetched for its month.")
+        .argument::<String>("TIME")
+        .parse(|time| NaiveDateTime::parse_from_str(&time, "%Y-%m-%d %H:%M"))
+        .optional();
+
+    let start_at_now = bpaf::long("start-at-beginning")
+        .help("Open at the start of the predictions, the first of the month, instead of the current time (or --now), e.g. for reproducible launches.")
+        .switch()
+        .map(|start_at_beginning| !start_at_beginning);
+
+    let dump_urls = bpaf::long("dump-urls")
+        .help("Print the NOAA URLs that would be fetched for the other options, in order, and exit without fetching them. Per-station URLs are only printed with --stations-file, since otherwise the stations come from the fetched station list.")
+        .switch();
+
+    let check_api = bpaf::long("check-api")
+        .help("Fetch the region's tide station and a current station from the live NOAA API, bypassing the cache, check that the responses have the fields KayakNav parses, report any that are missing or changed, and exit. Exits with an error if any check fails.")
+        .switch();
+
     bpaf::construct!(Config {
         use_api_proxy,
-        api_proxy_url
+        api_proxy_url,
+        output,
+        arrow_update_epsilon,
+        initial_zoom,
+        max_tile_level,
+        coordinate_system,
+        max_station_distance,
+        default_pause_hours,
+        hit_tolerance,
+        sharp_turn_warning,
+        max_path_segment,
+        planning_horizon_days,
+        max_fps,
+        presentation_speed,
+        sweep_quantile_method,
+        speed_interpolation,
+        arrow_smoothing,
+        tide_speed_profile,
+        region,
+        stations_file,
+        observed_currents,
+        debug_panel,
+        now,
+        start_at_now,
+        dump_urls,
+        check_api
     })
+    .guard(
+        |config| {
+            !config
+                .initial_zoom
+                .is_some_and(|zoom| zoom > config.max_tile_level)
+        },
+        "The initial zoom level must not exceed the max tile level",
+    )
     .to_options()
     .run()
 }
@@ -30,6 +225,21 @@ fn parse_args() -> Config {
 #[tokio::main]
 async fn main() {
     let config = parse_args();
+    if config.dump_urls {
+        if let Err(err) = dump_urls(&config) {
+            eprintln!("{err}");
+            process::exit(1);
+        }
+        return;
+    }
+    if config.check_api {
+        if let Err(err) = check_api(&config).await {
+            eprintln!("{err}");
+            process::exit(1);
+        }
+        return;
+    }
+
     let event_loop = EventLoop::new().unwrap();
     let window = WindowBuilder::new()
         .with_title("KayakNav")
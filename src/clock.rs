@@ -0,0 +1,31 @@
+use chrono::offset::Utc;
+use chrono::NaiveDateTime;
+use chrono_tz::Tz;
+
+/// Source of the current time in the region's time zone, which can be fixed
+/// to plan or review trips as if it were another time.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Clock {
+    fixed: Option<NaiveDateTime>,
+    time_zone: Tz,
+}
+
+impl Clock {
+    /// A clock that always reads `fixed` if set, and the real time in
+    /// `time_zone` otherwise.
+    pub fn new(fixed: Option<NaiveDateTime>, time_zone: Tz) -> Self {
+        Self { fixed, time_zone }
+    }
+
+    /// The current time in the region's time zone, regardless of the
+    /// machine's, to match the stations' local time predictions.
+    pub fn now(&self) -> NaiveDateTime {
+        self.fixed
+            .unwrap_or_else(|| Utc::now().with_timezone(&self.time_zone).naive_local())
+    }
+
+    /// The current time in station local milliseconds, like prediction times.
+    pub fn now_millis(&self) -> i64 {
+        self.now().and_utc().timestamp_millis()
+    }
+}
@@ -0,0 +1,78 @@
+use std::str::FromStr;
+
+use galileo_types::cartesian::Point2d;
+use galileo_types::geo::impls::GeoPoint2d;
+use galileo_types::geo::Crs;
+use galileo_types::geo::GeoPoint;
+use galileo_types::geo::NewGeoPoint;
+use galileo_types::geo::Projection;
+
+use crate::prelude::*;
+
+/// The coordinate system used to display and export coordinates. The map and
+/// all internal computation stay in EPSG:3857 and WGS84.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub enum CoordinateSystem {
+    /// WGS84 latitude and longitude in degrees (EPSG:4326).
+    #[default]
+    Wgs84,
+    /// Web mercator x and y in meters (EPSG:3857).
+    WebMercator,
+}
+
+impl FromStr for CoordinateSystem {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "wgs84" | "epsg:4326" => Ok(Self::Wgs84),
+            "webmercator" | "epsg:3857" => Ok(Self::WebMercator),
+            _ => Err(anyhow!(
+                "Unknown coordinate system {s:?}, expected 'wgs84' or 'epsg:3857'"
+            )),
+        }
+    }
+}
+
+impl CoordinateSystem {
+    /// Names of the first and second coordinate, for column and field names.
+    pub fn axis_names(&self) -> (&'static str, &'static str) {
+        match self {
+            Self::Wgs84 => ("lat", "lon"),
+            Self::WebMercator => ("x", "y"),
+        }
+    }
+
+    pub fn coordinates(&self, point: &impl GeoPoint<Num = f64>) -> (f64, f64) {
+        match self {
+            Self::Wgs84 => (point.lat(), point.lon()),
+            Self::WebMercator => {
+                let proj: Box<dyn Projection<InPoint = GeoPoint2d, OutPoint = Point2d>> =
+                    Crs::EPSG3857.get_projection().unwrap();
+                let point = proj
+                    .project(&GeoPoint2d::latlon(point.lat(), point.lon()))
+                    .unwrap();
+                (point.x, point.y)
+            },
+        }
+    }
+
+    /// Formats the coordinates with `precision` decimal places for latitude
+    /// and longitude, or whole meters for Web Mercator.
+    pub fn format(&self, point: &impl GeoPoint<Num = f64>, precision: usize) -> String {
+        let (a, b) = self.coordinates(point);
+        match self {
+            Self::Wgs84 => format!("Lat: {a:.precision$} Lon: {b:.precision$}"),
+            Self::WebMercator => format!("X: {a:.0} Y: {b:.0}"),
+        }
+    }
+
+    /// Formats the coordinates as a compact pair, e.g. for table rows.
+    pub fn format_pair(&self, point: &impl GeoPoint<Num = f64>, precision: usize) -> String {
+        let (a, b) = self.coordinates(point);
+        match self {
+            Self::Wgs84 => format!("({a:.precision$}, {b:.precision$})"),
+            Self::WebMercator => format!("({a:.0}, {b:.0})"),
+        }
+    }
+}
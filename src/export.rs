@@ -0,0 +1,102 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs;
+
+use polars::prelude::*;
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::JsCast;
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::JsValue;
+
+use crate::prelude::*;
+#[cfg(target_arch = "wasm32")]
+use crate::tasks;
+
+/// Serializes `df` as CSV, with a header row.
+pub fn csv(df: &mut DataFrame) -> Result<String> {
+    let mut buf = Vec::new();
+    CsvWriter::new(&mut buf).finish(df).log()?;
+    Ok(String::from_utf8(buf).log()?)
+}
+
+/// Serializes `df` as Parquet.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn parquet(df: &mut DataFrame) -> Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    ParquetWriter::new(&mut buf).finish(df).log()?;
+    Ok(buf)
+}
+
+/// Writes `contents` to `filename` in the working directory.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save(filename: &str, contents: &str, _mime_type: &str) -> Result<()> {
+    fs::write(filename, contents).log()?;
+    info!("Wrote {filename:?}");
+    Ok(())
+}
+
+/// Offers `contents` to the browser as a download named `filename`.
+#[cfg(target_arch = "wasm32")]
+pub fn save(filename: &str, contents: &str, mime_type: &str) -> Result<()> {
+    let js_err = |err: JsValue| anyhow!("Error downloading {filename:?}: {err:?}");
+
+    let parts = js_sys::Array::new();
+    parts.push(&JsValue::from_str(contents));
+    let blob = web_sys::Blob::new_with_str_sequence_and_options(
+        &parts,
+        web_sys::BlobPropertyBag::new().type_(mime_type),
+    )
+    .map_err(js_err)
+    .log()?;
+    let url = web_sys::Url::create_object_url_with_blob(&blob)
+        .map_err(js_err)
+        .log()?;
+
+    let document = web_sys::window().log()?.document().log()?;
+    let anchor: web_sys::HtmlAnchorElement = document
+        .create_element("a")
+        .map_err(js_err)
+        .log()?
+        .dyn_into()
+        .map_err(|element| js_err(element.into()))
+        .log()?;
+    anchor.set_href(&url);
+    anchor.set_download(filename);
+    anchor.click();
+
+    web_sys::Url::revoke_object_url(&url).map_err(js_err).log()
+}
+
+/// Puts `text` on the clipboard.
+#[cfg(target_arch = "wasm32")]
+pub fn copy_to_clipboard(text: &str) -> Result<()> {
+    let js_err = |err: JsValue| anyhow!("Error copying to the clipboard: {err:?}");
+
+    // web-sys only exposes navigator.clipboard with web_sys_unstable_apis, so
+    // it's looked up dynamically.
+    let navigator = web_sys::window().log()?.navigator();
+    let clipboard = js_sys::Reflect::get(&navigator, &JsValue::from_str("clipboard"))
+        .map_err(js_err)
+        .log()?;
+    let write_text: js_sys::Function =
+        js_sys::Reflect::get(&clipboard, &JsValue::from_str("writeText"))
+            .map_err(js_err)
+            .log()?
+            .dyn_into()
+            .map_err(js_err)
+            .log()?;
+    let promise: js_sys::Promise = write_text
+        .call1(&clipboard, &JsValue::from_str(text))
+        .map_err(js_err)
+        .log()?
+        .dyn_into()
+        .map_err(js_err)
+        .log()?;
+
+    tasks::spawn(async move {
+        let _ = wasm_bindgen_futures::JsFuture::from(promise)
+            .await
+            .map_err(js_err)
+            .log();
+    });
+    Ok(())
+}
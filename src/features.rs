@@ -6,6 +6,8 @@ use galileo::layer::feature_layer::Feature;
 use galileo::layer::feature_layer::FeatureLayer;
 use galileo::render::point_paint::PointPaint;
 use galileo::render::render_bundle::RenderPrimitive;
+use galileo::render::LineCap;
+use galileo::render::LinePaint;
 use galileo::Color;
 use galileo::Map;
 use galileo_types::cartesian::CartesianPoint2d;
@@ -21,13 +23,18 @@ use galileo_types::geometry::Geom;
 use galileo_types::geometry::Geometry;
 use galileo_types::impls::Contour;
 use galileo_types::impls::Polygon;
+use itertools::Itertools;
 use num_traits::AsPrimitive;
+use uom::si::f64::Length;
+use uom::si::f64::Time;
+use uom::si::f64::Velocity;
+use uom::si::length::meter;
 
 use crate::noaa::CurrentPrediction;
 use crate::noaa::StationType;
 use crate::prelude::*;
-use crate::saturating::Saturating;
 use crate::scheduling::Trip;
+use crate::time_idx::TimeIdx;
 
 pub fn clear_features<P, F, S, SP>(layer: Arc<RwLock<FeatureLayer<P, F, S, SP>>>) -> Vec<F>
 where
@@ -50,13 +57,28 @@ where
 #[derive(Debug, Clone, Copy)]
 pub enum WaypointType {
     Move,
-    Pause,
+    /// Stop for the given duration.
+    Pause(Time),
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct Waypoint {
     pub point: Point2d,
     pub type_: WaypointType,
+    pub label: Option<String>,
+}
+
+impl Waypoint {
+    /// A waypoint at the given latitude and longitude.
+    pub fn at(lat: f64, lon: f64, type_: WaypointType) -> Option<Self> {
+        let proj: Box<dyn Projection<InPoint = GeoPoint2d, OutPoint = Point2d>> =
+            Crs::EPSG3857.get_projection()?;
+        Some(Self {
+            point: proj.project(&GeoPoint2d::latlon(lat, lon))?,
+            type_,
+            label: None,
+        })
+    }
 }
 
 impl Feature for Waypoint {
@@ -132,7 +154,86 @@ impl CartesianGeometry2d<Point2d> for Waypoint {
     }
 }
 
-pub struct WaypointSymbol {}
+/// Colors distinguishing routes on the map, as RGB.
+const ROUTE_COLORS: [(u8, u8, u8); 5] = [
+    (0, 0, 0),
+    (220, 0, 0),
+    (0, 160, 0),
+    (128, 0, 255),
+    (255, 0, 255),
+];
+
+pub fn route_color(route_idx: usize) -> (u8, u8, u8) {
+    ROUTE_COLORS[route_idx % ROUTE_COLORS.len()]
+}
+
+/// Fill color of a waypoint, as RGB, in the default or high-contrast palette.
+pub fn waypoint_color(waypoint_type: WaypointType, accessible: bool) -> (u8, u8, u8) {
+    match (waypoint_type, accessible) {
+        (WaypointType::Move, false) => (255, 128, 0),
+        (WaypointType::Move, true) => (255, 64, 0),
+        (WaypointType::Pause(_), false) => (0, 128, 255),
+        (WaypointType::Pause(_), true) => (0, 64, 255),
+    }
+}
+
+/// Color of a station's current arrow, as RGB.
+pub fn station_color(station_type: StationType) -> (u8, u8, u8) {
+    match station_type {
+        StationType::Harmonic => (0, 0, 255),
+        StationType::Subordinate => (255, 0, 0),
+    }
+}
+
+/// How current arrows are colored.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum CurrentColoring {
+    /// By station type, with observed currents set apart.
+    #[default]
+    StationType,
+    /// By speed, see `current_speed_color`.
+    Speed,
+}
+
+/// Speed, in knots, at and above which current arrows colored by speed are
+/// fully red.
+pub const CURRENT_COLOR_MAX_KNOTS: f64 = 3.0;
+
+/// Color of a current arrow by its speed in knots, as RGB, from blue at slack
+/// to red at `CURRENT_COLOR_MAX_KNOTS` or more.
+pub fn current_speed_color(speed: f64) -> (u8, u8, u8) {
+    let t = (speed.abs() / CURRENT_COLOR_MAX_KNOTS).clamp(0.0, 1.0);
+    ((255.0 * t) as u8, 0, (255.0 * (1.0 - t)) as u8)
+}
+
+/// Color of a current arrow drawn from observations rather than predictions,
+/// as RGB.
+pub const OBSERVED_CURRENT_COLOR: (u8, u8, u8) = (0, 160, 160);
+
+pub struct WaypointSymbol {
+    pub outline: Color,
+    /// Whether to draw larger, higher-contrast symbols.
+    pub accessible: Arc<RwLock<bool>>,
+    /// Position of `UiState::selected_waypoint`, which is highlighted. The
+    /// symbol only sees each waypoint, not its index, so waypoints stacked on
+    /// the selected one are highlighted with it.
+    pub selected: Arc<RwLock<Option<Point2d>>>,
+}
+
+impl WaypointSymbol {
+    pub fn for_route(
+        route_idx: usize,
+        accessible: Arc<RwLock<bool>>,
+        selected: Arc<RwLock<Option<Point2d>>>,
+    ) -> Self {
+        let (r, g, b) = route_color(route_idx);
+        Self {
+            outline: Color::rgba(r, g, b, 255),
+            accessible,
+            selected,
+        }
+    }
+}
 
 impl Symbol<Waypoint> for WaypointSymbol {
     fn render<'a, N, P>(
@@ -145,23 +246,37 @@ impl Symbol<Waypoint> for WaypointSymbol {
         N: AsPrimitive<f32>,
         P: CartesianPoint3d<Num = N> + Clone,
     {
-        let size = 10f32;
+        let accessible = *self.accessible.read().unwrap();
+        let (size, outline_width) = if accessible {
+            (16f32, 8.0)
+        } else {
+            (10f32, 4.0)
+        };
         let mut primitives = vec![];
         let Geom::Point(point) = geometry else {
             return primitives;
         };
 
+        let selected =
+            self.selected.read().unwrap().is_some_and(|selected| {
+                selected.x == feature.point.x && selected.y == feature.point.y
+            });
+        if selected {
+            primitives.push(RenderPrimitive::new_point_ref(
+                point,
+                PointPaint::circle(Color::from_hex("#ffff00"), size * 2.0 + 12.0),
+            ));
+        }
+
         primitives.push(RenderPrimitive::new_point_ref(
             point,
-            PointPaint::circle(Color::BLACK, size * 2.0 + 4.0),
+            PointPaint::circle(self.outline, size * 2.0 + outline_width),
         ));
+        let (r, g, b) = waypoint_color(feature.type_, accessible);
         primitives.push(RenderPrimitive::new_point_ref(
             point,
             PointPaint::sector(
-                match feature.type_ {
-                    WaypointType::Move => Color::from_hex("#ff8000"),
-                    WaypointType::Pause => Color::from_hex("#0080ff"),
-                },
+                Color::rgba(r, g, b, 255),
                 size * 2.0,
                 0f32.to_radians(),
                 360f32.to_radians(),
@@ -172,6 +287,265 @@ impl Symbol<Waypoint> for WaypointSymbol {
     }
 }
 
+/// Color of a leg, as RGB, by its net speed relative to the paddling speed:
+/// red at half of it or less, yellow at it, and green at one and a half times
+/// it or more.
+pub fn leg_speed_color(speed: Velocity, paddling_speed: Velocity) -> (u8, u8, u8) {
+    let t = ((speed / paddling_speed).value - 0.5).clamp(0.0, 1.0);
+    if t < 0.5 {
+        (255, (510.0 * t) as u8, 0)
+    } else {
+        ((510.0 * (1.0 - t)) as u8, 255, 0)
+    }
+}
+
+/// A trip leg between consecutive waypoints, drawn in `color` through
+/// `points`, which are the two waypoints unless the leg follows its
+/// integrated path.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Leg {
+    pub points: Vec<Point2d>,
+    pub color: (u8, u8, u8),
+}
+
+/// Mean radius of the Earth, in meters.
+const EARTH_RADIUS_METERS: f64 = 6_371_008.8;
+
+fn to_nvector((lat, lon): (f64, f64)) -> [f64; 3] {
+    let (lat, lon) = (lat.to_radians(), lon.to_radians());
+    [lat.cos() * lon.cos(), lat.cos() * lon.sin(), lat.sin()]
+}
+
+fn from_nvector([x, y, z]: [f64; 3]) -> (f64, f64) {
+    (z.atan2(x.hypot(y)).to_degrees(), y.atan2(x).to_degrees())
+}
+
+/// Adds points along the great circle between consecutive latitude and
+/// longitude pairs of `path` so that none are more than `max_segment` apart.
+pub fn resample_path(path: &[(f64, f64)], max_segment: Length) -> Vec<(f64, f64)> {
+    let max_segment = max_segment.get::<meter>();
+    if max_segment <= 0.0 {
+        return path.to_vec();
+    }
+
+    let mut resampled = vec![];
+    for (&a, &b) in path.iter().tuple_windows() {
+        resampled.push(a);
+        let (a, b) = (to_nvector(a), to_nvector(b));
+        let cos_angle = (a[0] * b[0] + a[1] * b[1] + a[2] * b[2]).clamp(-1.0, 1.0);
+        let angle = cos_angle.acos();
+        let segments = (angle * EARTH_RADIUS_METERS / max_segment).ceil() as usize;
+        for i in 1..segments {
+            let t = i as f64 / segments as f64;
+            let wa = ((1.0 - t) * angle).sin() / angle.sin();
+            let wb = (t * angle).sin() / angle.sin();
+            resampled.push(from_nvector([
+                wa * a[0] + wb * b[0],
+                wa * a[1] + wb * b[1],
+                wa * a[2] + wb * b[2],
+            ]));
+        }
+    }
+    resampled.extend(path.last());
+    resampled
+}
+
+impl Leg {
+    /// A leg through the latitude and longitude pairs of `path`.
+    pub fn along(path: &[(f64, f64)], color: (u8, u8, u8)) -> Option<Self> {
+        let proj: Box<dyn Projection<InPoint = GeoPoint2d, OutPoint = Point2d>> =
+            Crs::EPSG3857.get_projection()?;
+        let points = path
+            .iter()
+            .map(|&(lat, lon)| proj.project(&GeoPoint2d::latlon(lat, lon)))
+            .collect::<Option<_>>()?;
+        Some(Self { points, color })
+    }
+}
+
+impl Feature for Leg {
+    type Geom = Self;
+
+    fn geometry(&self) -> &Self::Geom {
+        self
+    }
+}
+
+impl Geometry for Leg {
+    type Point = Point2d;
+
+    fn project<P: Projection<InPoint = Self::Point> + ?Sized>(
+        &self,
+        projection: &P,
+    ) -> Option<Geom<P::OutPoint>> {
+        Some(Geom::Contour(Contour::open(
+            self.points
+                .iter()
+                .map(|point| projection.project(point))
+                .collect::<Option<_>>()?,
+        )))
+    }
+}
+
+impl CartesianGeometry2d<Point2d> for Leg {
+    fn is_point_inside<
+        Other: galileo_types::cartesian::CartesianPoint2d<
+            Num = <Point2d as galileo_types::cartesian::CartesianPoint2d>::Num,
+        >,
+    >(
+        &self,
+        _point: &Other,
+        _tolerance: <Point2d as galileo_types::cartesian::CartesianPoint2d>::Num,
+    ) -> bool {
+        false
+    }
+
+    fn bounding_rectangle(
+        &self,
+    ) -> Option<
+        galileo_types::cartesian::Rect<
+            <Point2d as galileo_types::cartesian::CartesianPoint2d>::Num,
+        >,
+    > {
+        None
+    }
+}
+
+pub struct LegSymbol {
+    /// Whether to draw wider lines.
+    pub accessible: Arc<RwLock<bool>>,
+}
+
+impl Symbol<Leg> for LegSymbol {
+    fn render<'a, N, P>(
+        &self,
+        feature: &Leg,
+        geometry: &'a Geom<P>,
+        _min_resolution: f64,
+    ) -> Vec<RenderPrimitive<'a, N, P, Contour<P>, Polygon<P>>>
+    where
+        N: AsPrimitive<f32>,
+        P: CartesianPoint3d<Num = N> + Clone,
+    {
+        let Geom::Contour(contour) = geometry else {
+            return vec![];
+        };
+
+        let (r, g, b) = feature.color;
+        vec![RenderPrimitive::new_contour_ref(
+            contour,
+            LinePaint {
+                color: Color::rgba(r, g, b, 255),
+                width: if *self.accessible.read().unwrap() {
+                    8.0
+                } else {
+                    5.0
+                },
+                offset: 0.0,
+                line_cap: LineCap::Round,
+            },
+        )]
+    }
+}
+
+/// The boat's position during a trip replay.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Boat {
+    pub point: Point2d,
+}
+
+impl Boat {
+    /// A boat at the given latitude and longitude.
+    pub fn at(lat: f64, lon: f64) -> Option<Self> {
+        let proj: Box<dyn Projection<InPoint = GeoPoint2d, OutPoint = Point2d>> =
+            Crs::EPSG3857.get_projection()?;
+        Some(Self {
+            point: proj.project(&GeoPoint2d::latlon(lat, lon))?,
+        })
+    }
+}
+
+impl Feature for Boat {
+    type Geom = Self;
+
+    fn geometry(&self) -> &Self::Geom {
+        self
+    }
+}
+
+impl Geometry for Boat {
+    type Point = Point2d;
+
+    fn project<P: Projection<InPoint = Self::Point> + ?Sized>(
+        &self,
+        projection: &P,
+    ) -> Option<Geom<P::OutPoint>> {
+        self.point.project(projection)
+    }
+}
+
+impl CartesianGeometry2d<Point2d> for Boat {
+    fn is_point_inside<
+        Other: galileo_types::cartesian::CartesianPoint2d<
+            Num = <Point2d as galileo_types::cartesian::CartesianPoint2d>::Num,
+        >,
+    >(
+        &self,
+        _point: &Other,
+        _tolerance: <Point2d as galileo_types::cartesian::CartesianPoint2d>::Num,
+    ) -> bool {
+        false
+    }
+
+    fn bounding_rectangle(
+        &self,
+    ) -> Option<
+        galileo_types::cartesian::Rect<
+            <Point2d as galileo_types::cartesian::CartesianPoint2d>::Num,
+        >,
+    > {
+        None
+    }
+}
+
+pub struct BoatSymbol {
+    /// Whether to draw a larger marker.
+    pub accessible: Arc<RwLock<bool>>,
+}
+
+impl Symbol<Boat> for BoatSymbol {
+    fn render<'a, N, P>(
+        &self,
+        _feature: &Boat,
+        geometry: &'a Geom<P>,
+        _min_resolution: f64,
+    ) -> Vec<RenderPrimitive<'a, N, P, Contour<P>, Polygon<P>>>
+    where
+        N: AsPrimitive<f32>,
+        P: CartesianPoint3d<Num = N> + Clone,
+    {
+        let size = if *self.accessible.read().unwrap() {
+            18f32
+        } else {
+            12f32
+        };
+        let Geom::Point(point) = geometry else {
+            return vec![];
+        };
+
+        vec![
+            RenderPrimitive::new_point_ref(
+                point,
+                PointPaint::circle(Color::rgba(0, 0, 0, 255), size * 2.0 + 6.0),
+            ),
+            RenderPrimitive::new_point_ref(
+                point,
+                PointPaint::circle(Color::rgba(255, 255, 255, 255), size * 2.0),
+            ),
+        ]
+    }
+}
+
 pub fn add_waypoint(
     map: &mut Map,
     trip: Arc<RwLock<Trip>>,
@@ -183,13 +557,23 @@ pub fn add_waypoint(
     trip.write().unwrap().add_waypoint(Waypoint {
         point: Point2d::new(map_pos.x, map_pos.y),
         type_: waypoint_type,
+        label: None,
     });
 
     map.redraw();
     Ok(())
 }
 
-pub fn remove_waypoints(map: &mut Map, trip: Arc<RwLock<Trip>>, pos: Point2d) -> Result<()> {
+/// Removes the waypoint nearest to `pos` within `tolerance` screen pixels
+/// of it, or every waypoint within `tolerance` if `remove_all`. Of stacked
+/// waypoints, the last placed is removed first.
+pub fn remove_waypoints(
+    map: &mut Map,
+    trip: Arc<RwLock<Trip>>,
+    pos: Point2d,
+    tolerance: f64,
+    remove_all: bool,
+) -> Result<()> {
     let view = map.view().clone();
     let map_pos = view.screen_to_map(pos).log()?;
 
@@ -197,7 +581,7 @@ pub fn remove_waypoints(map: &mut Map, trip: Arc<RwLock<Trip>>, pos: Point2d) ->
     let mut indices_to_remove = {
         let layer = trip.waypoint_layer.read().unwrap();
         let matching_features: Vec<_> = layer
-            .get_features_at(&map_pos, map.view().resolution() * 10.0)
+            .get_features_at(&map_pos, view.resolution() * tolerance)
             .collect();
 
         let mut indices_to_remove = vec![];
@@ -208,6 +592,18 @@ pub fn remove_waypoints(map: &mut Map, trip: Arc<RwLock<Trip>>, pos: Point2d) ->
         indices_to_remove
     };
 
+    if !remove_all {
+        let distance = |index: usize| {
+            let point = trip.waypoints[index].point;
+            (point.x - map_pos.x).hypot(point.y - map_pos.y)
+        };
+        indices_to_remove = indices_to_remove
+            .into_iter()
+            .min_by(|&a, &b| distance(a).total_cmp(&distance(b)).then(b.cmp(&a)))
+            .into_iter()
+            .collect();
+    }
+
     indices_to_remove.sort();
     indices_to_remove.reverse();
 
@@ -268,9 +664,18 @@ fn heading_degrees_to_polar_degrees(heading: f32) -> f32 {
 }
 
 pub struct CurrentPredictionSymbol {
-    pub time_idx: Arc<RwLock<Saturating<usize>>>,
+    pub time_idx: Arc<RwLock<TimeIdx>>,
+    /// Whether to draw larger, wider arrows.
+    pub accessible: Arc<RwLock<bool>>,
+    pub coloring: Arc<RwLock<CurrentColoring>>,
+    /// Offset, in 30 minute rows, of a second time whose currents are drawn
+    /// faded behind the selected time's, if comparing times.
+    pub compare_rows: Arc<RwLock<Option<f64>>>,
 }
 
+/// Opacity of arrows for the compared time.
+const COMPARE_ALPHA: u8 = 96;
+
 impl<const R: u8> Symbol<CurrentPrediction<R>> for CurrentPredictionSymbol {
     fn render<'a, N, P>(
         &self,
@@ -282,45 +687,62 @@ impl<const R: u8> Symbol<CurrentPrediction<R>> for CurrentPredictionSymbol {
         N: AsPrimitive<f32>,
         P: CartesianPoint3d<Num = N> + Clone,
     {
-        let base_size = 30f32;
+        let (base_size, half_width) = if *self.accessible.read().unwrap() {
+            (40f32, 22.5)
+        } else {
+            (30f32, 15.0)
+        };
         let mut primitives = vec![];
         let Geom::Point(point) = geometry else {
             return primitives;
         };
 
-        let mut rev_heading = heading_degrees_to_polar_degrees(
-            (feature.df["direction"]
-             .f64()
-             .unwrap()
-             .get(self.time_idx.read().unwrap().val())
-             .unwrap()) as f32)
-        // Reverse it so the the sector "arrow" points in the right
-        // direction.
-            + 180.0;
-
-        let mut speed = (feature.df["speed"]
-            .f64()
+        let row_pos = self.time_idx.read().unwrap().row_pos();
+        // The compared time is drawn first, so the selected time's arrow is on
+        // top.
+        let samples = self
+            .compare_rows
+            .read()
             .unwrap()
-            .get(self.time_idx.read().unwrap().val())
-            .unwrap()) as f32;
-
-        if speed < 0.0 {
-            speed *= -1.0;
-            rev_heading += 180.0;
-        }
-
-        primitives.push(RenderPrimitive::new_point_ref(
-            point,
-            PointPaint::sector(
-                match feature.station.type_ {
-                    StationType::Harmonic => Color::BLUE,
-                    StationType::Subordinate => Color::RED,
+            .map(|rows| (row_pos + rows, COMPARE_ALPHA))
+            .into_iter()
+            .chain([(row_pos, 255)]);
+        for (pos, alpha) in samples {
+            // Interpolated so that arrows drawn between the prediction's rows
+            // don't snap to them.
+            let Some((speed, direction)) = feature.interpolated(pos) else {
+                continue;
+            };
+
+            let mut rev_heading = heading_degrees_to_polar_degrees(direction as f32)
+            // Reverse it so the the sector "arrow" points in the right
+            // direction.
+                + 180.0;
+
+            let mut speed = speed as f32;
+
+            if speed < 0.0 {
+                speed *= -1.0;
+                rev_heading += 180.0;
+            }
+
+            let (r, g, b) = match *self.coloring.read().unwrap() {
+                CurrentColoring::Speed => current_speed_color(speed as f64),
+                CurrentColoring::StationType if feature.is_observed(pos as usize) => {
+                    OBSERVED_CURRENT_COLOR
                 },
-                base_size * speed,
-                (rev_heading - 15.0).to_radians(),
-                (rev_heading + 15.0).to_radians(),
-            ),
-        ));
+                CurrentColoring::StationType => station_color(feature.station.type_),
+            };
+            primitives.push(RenderPrimitive::new_point_ref(
+                point,
+                PointPaint::sector(
+                    Color::rgba(r, g, b, alpha),
+                    base_size * speed,
+                    (rev_heading - half_width).to_radians(),
+                    (rev_heading + half_width).to_radians(),
+                ),
+            ));
+        }
 
         primitives
     }
@@ -0,0 +1,48 @@
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::closure::Closure;
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::JsCast;
+
+/// Latitude and longitude of the device, or why they couldn't be determined.
+pub type Location = Result<(f64, f64), String>;
+
+/// Asks the browser for the device's location and calls `on_located` with the
+/// result, possibly after the user answers a permission prompt.
+#[cfg(target_arch = "wasm32")]
+pub fn locate(on_located: impl Fn(Location) + Clone + 'static) {
+    let Some(geolocation) =
+        web_sys::window().and_then(|window| window.navigator().geolocation().ok())
+    else {
+        on_located(Err("This browser doesn't support location".to_string()));
+        return;
+    };
+
+    let on_success = {
+        let on_located = on_located.clone();
+        Closure::once_into_js(move |position: web_sys::Position| {
+            let coords = position.coords();
+            on_located(Ok((coords.latitude(), coords.longitude())));
+        })
+    };
+    let on_error = {
+        let on_located = on_located.clone();
+        Closure::once_into_js(move |err: web_sys::PositionError| {
+            on_located(Err(format!(
+                "Couldn't get your location: {}",
+                err.message()
+            )));
+        })
+    };
+
+    if let Err(err) = geolocation.get_current_position_with_error_callback(
+        on_success.unchecked_ref(),
+        Some(on_error.unchecked_ref()),
+    ) {
+        on_located(Err(format!("Couldn't get your location: {err:?}")));
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn locate(on_located: impl Fn(Location) + Clone + 'static) {
+    on_located(Err("Location is only available in the browser".to_string()));
+}
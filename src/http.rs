@@ -1,4 +1,16 @@
 #[cfg(not(target_arch = "wasm32"))]
+use std::sync::Arc;
+use std::sync::RwLock;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::SystemTime;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::UNIX_EPOCH;
+
+use chrono::DateTime;
+use chrono::Utc;
+#[cfg(not(target_arch = "wasm32"))]
 use http_cache_reqwest::CACacheManager;
 #[cfg(not(target_arch = "wasm32"))]
 use http_cache_reqwest::Cache;
@@ -8,6 +20,8 @@ use http_cache_reqwest::CacheMode;
 use http_cache_reqwest::HttpCache;
 #[cfg(not(target_arch = "wasm32"))]
 use http_cache_reqwest::HttpCacheOptions;
+#[cfg(not(target_arch = "wasm32"))]
+use http_cache_reqwest::Parts;
 use once_cell::sync::Lazy;
 use reqwest::Client;
 use reqwest::Response;
@@ -22,19 +36,53 @@ use reqwest_retry::RetryTransientMiddleware;
 use serde_json::Value;
 
 use crate::prelude::*;
+#[cfg(target_arch = "wasm32")]
+use crate::tasks;
+
+const MAX_RETRIES: u32 = 3;
+
+/// How long responses are reused before being refetched. Station metadata
+/// rarely changes, while predictions are refetched daily.
+#[cfg(not(target_arch = "wasm32"))]
+fn cache_ttl(uri: &str) -> Duration {
+    if uri.contains("mdapi") {
+        Duration::from_secs(30 * 24 * 60 * 60)
+    } else {
+        Duration::from_secs(24 * 60 * 60)
+    }
+}
+
+/// Responses are cached regardless of their headers, so entries are expired by
+/// bucketing the cache key by the endpoint's TTL.
+#[cfg(not(target_arch = "wasm32"))]
+fn cache_key(parts: &Parts) -> String {
+    let uri = parts.uri.to_string();
+    let ttl = cache_ttl(&uri);
+    let bucket = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+        / ttl.as_secs();
+    let key = format!("{}:{uri}:{bucket}", parts.method);
+    debug!("Using cache key {key:?} with TTL {ttl:?}");
+    key
+}
 
 #[cfg(not(target_arch = "wasm32"))]
 pub static CLIENT: Lazy<ClientWithMiddleware> = Lazy::new(|| {
     ClientBuilder::new(Client::new())
         .with(RetryTransientMiddleware::new_with_policy(
-            ExponentialBackoff::builder().build_with_max_retries(3),
+            ExponentialBackoff::builder().build_with_max_retries(MAX_RETRIES),
         ))
         .with(Cache(HttpCache {
             mode: CacheMode::IgnoreRules,
             manager: CACacheManager {
                 path: "/tmp/kayaknav_cache".into(),
             },
-            options: HttpCacheOptions::default(),
+            options: HttpCacheOptions {
+                cache_key: Some(Arc::new(cache_key)),
+                ..HttpCacheOptions::default()
+            },
         }))
         .build()
 });
@@ -42,6 +90,35 @@ pub static CLIENT: Lazy<ClientWithMiddleware> = Lazy::new(|| {
 #[cfg(target_arch = "wasm32")]
 pub static CLIENT: Lazy<Client> = Lazy::new(Client::new);
 
+/// The `Date` of the oldest prediction response fetched so far. For cached
+/// responses, this is when they were originally fetched.
+static OLDEST_PREDICTION: Lazy<RwLock<Option<DateTime<Utc>>>> = Lazy::new(|| RwLock::new(None));
+
+pub fn oldest_prediction_date() -> Option<DateTime<Utc>> {
+    *OLDEST_PREDICTION.read().unwrap()
+}
+
+fn record_response_date(url: &str, resp: &Response) {
+    // Station metadata is cached much longer than predictions.
+    if url.contains("mdapi") {
+        return;
+    }
+    let Some(date) = resp
+        .headers()
+        .get("date")
+        .and_then(|date| date.to_str().ok())
+        .and_then(|date| DateTime::parse_from_rfc2822(date).ok())
+    else {
+        return;
+    };
+    let date = date.with_timezone(&Utc);
+
+    let mut oldest = OLDEST_PREDICTION.write().unwrap();
+    if oldest.map_or(true, |oldest| date < oldest) {
+        *oldest = Some(date);
+    }
+}
+
 pub async fn error_for_status(resp: Response) -> Result<Response> {
     let status = resp.status();
     let url = resp.url().clone();
@@ -52,22 +129,55 @@ pub async fn error_for_status(resp: Response) -> Result<Response> {
     }
 }
 
+// Retries are handled by the client middleware.
+#[cfg(not(target_arch = "wasm32"))]
+async fn get(url: &str) -> Result<Response> {
+    error_for_status(CLIENT.get(url).send().await.log()?).await
+}
+
+#[cfg(target_arch = "wasm32")]
+async fn get(url: &str) -> Result<Response> {
+    let mut delay_ms = 500;
+    for _ in 0..MAX_RETRIES {
+        match CLIENT.get(url).send().await {
+            Ok(resp) if !resp.status().is_server_error() => return error_for_status(resp).await,
+            Ok(resp) => warn!("Retrying {url:?} in {delay_ms}ms after {}", resp.status()),
+            Err(err) => warn!("Retrying {url:?} in {delay_ms}ms after {err}"),
+        }
+        tasks::sleep(delay_ms).await;
+        delay_ms *= 2;
+    }
+    error_for_status(CLIENT.get(url).send().await.log()?).await
+}
+
 pub async fn fetch_json(url: &str) -> Result<Value> {
     info!("Fetching url {url:?}");
 
-    let bytes = error_for_status(CLIENT.get(url).send().await.log()?)
-        .await
-        .log()?
-        .bytes()
+    let resp = get(url).await.log()?;
+    if let Some(cache_status) = resp.headers().get("x-cache") {
+        info!("Cache {cache_status:?} for {url:?}");
+    }
+    record_response_date(url, &resp);
+    decode_json(url, resp).await
+}
+
+/// Like `fetch_json`, but always fetches `url`, without reading or updating
+/// the response cache.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn fetch_json_uncached(url: &str) -> Result<Value> {
+    info!("Fetching url {url:?} without the cache");
+
+    let resp = CLIENT
+        .get(url)
+        .with_extension(CacheMode::NoStore)
+        .send()
         .await
         .log()?;
+    decode_json(url, error_for_status(resp).await.log()?).await
+}
 
-    // TODO: add retires (e.g., 504 Gateway timeout)
-    // let resp = { || async { http::CLIENT.get(url.clone()).send().await } }
-    //     .retry(&ExponentialBuilder::default())
-    //     .await
-    //     .log()?;
-    // // .retry(&ExponentialBuilder::default())
+async fn decode_json(url: &str, resp: Response) -> Result<Value> {
+    let bytes = resp.bytes().await.log()?;
 
     debug!("Got response from {url:?}: {bytes:?}");
 
@@ -1,7 +1,15 @@
 use std::io;
+use std::mem;
 use std::panic;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
 
+use chrono::NaiveDateTime;
 use tracing_subscriber::filter::LevelFilter;
 use tracing_subscriber::fmt::format::FmtSpan;
 use tracing_subscriber::fmt::format::Pretty;
@@ -13,6 +21,12 @@ use tracing_web::performance_layer;
 #[cfg(target_arch = "wasm32")]
 use tracing_web::MakeWebConsoleWriter;
 #[cfg(target_arch = "wasm32")]
+use wasm_bindgen::closure::Closure;
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::JsCast;
+#[cfg(target_arch = "wasm32")]
+use web_time::Instant;
+#[cfg(target_arch = "wasm32")]
 use winit::dpi::PhysicalSize;
 use winit::event::Event;
 use winit::event::KeyEvent;
@@ -25,16 +39,40 @@ use winit::window::Window;
 #[cfg(target_arch = "wasm32")]
 use winit::window::WindowBuilder;
 
+#[cfg(not(target_arch = "wasm32"))]
+mod api_check;
+mod clock;
+pub mod coordinates;
 mod error_utils;
+mod export;
 mod features;
+mod geolocation;
 mod http;
 mod noaa;
+mod polyline;
 pub mod prelude;
+pub mod regions;
 mod run_ui;
 mod saturating;
 pub mod scheduling;
+mod share;
 pub mod state;
+mod storage;
+mod tasks;
+mod time_idx;
 
+#[cfg(not(target_arch = "wasm32"))]
+pub use crate::api_check::check_api;
+use crate::clock::Clock;
+use crate::coordinates::CoordinateSystem;
+use crate::http::ApiProxy;
+pub use crate::noaa::SpeedInterpolation;
+use crate::noaa::Station;
+use crate::prelude::*;
+use crate::regions;
+use crate::regions::Region;
+use crate::scheduling::QuantileMethod;
+use crate::scheduling::TideSpeedProfile;
 use crate::state::State;
 
 #[cfg(target_arch = "wasm32")]
@@ -74,10 +112,116 @@ fn configure_tracing() {
     tracing_subscriber::registry().with(layers).init();
 }
 
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum OutputFormat {
+    Json,
+    /// Every station's current at the selected time, as GeoJSON.
+    CurrentsGeoJson,
+    /// The region's stations' definitions, as read by `--stations-file`.
+    Stations,
+    /// Every station's predictions on the common time grid, as CSV.
+    CurrentFieldCsv,
+    /// The same as `CurrentFieldCsv`, as Parquet.
+    #[cfg(not(target_arch = "wasm32"))]
+    CurrentFieldParquet,
+}
+
+impl FromStr for OutputFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "json" => Ok(Self::Json),
+            "currents-geojson" => Ok(Self::CurrentsGeoJson),
+            "stations" => Ok(Self::Stations),
+            "current-field-csv" => Ok(Self::CurrentFieldCsv),
+            #[cfg(not(target_arch = "wasm32"))]
+            "current-field-parquet" => Ok(Self::CurrentFieldParquet),
+            _ => Err(anyhow!(
+                "Unknown output format {s:?}, expected 'json', 'currents-geojson', 'stations', \
+                 'current-field-csv', or 'current-field-parquet'"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Config {
     pub use_api_proxy: bool,
     pub api_proxy_url: String,
+    pub output: Option<OutputFormat>,
+    /// Smallest change in any station's current, in knots, that rebuilds the
+    /// current arrows when the time changes.
+    pub arrow_update_epsilon: f64,
+    /// Web mercator zoom level of the initial map view. Overrides the saved
+    /// view when set.
+    pub initial_zoom: Option<u32>,
+    /// Highest zoom level to fetch tiles at, at most
+    /// `galileo_state::TILE_SOURCE_MAX_LEVEL`. Zooming in further enlarges the
+    /// tiles from this level.
+    pub max_tile_level: u32,
+    /// Coordinate system used to display and export coordinates.
+    pub coordinate_system: CoordinateSystem,
+    /// Distance, in miles, beyond which a station's current predictions are
+    /// considered unreliable.
+    pub max_station_distance: f64,
+    /// Duration, in hours, of newly placed pause waypoints.
+    pub default_pause_hours: f64,
+    /// Distance, in logical pixels, within which a click selects a waypoint
+    /// and the pointer hovers a station.
+    pub hit_tolerance: f64,
+    /// Change in bearing, in degrees, between consecutive legs above which a
+    /// waypoint is flagged as a possibly misplaced sharp turn. Off if unset.
+    pub sharp_turn_warning: Option<f64>,
+    /// Longest distance, in miles, between drawn points of an integrated leg
+    /// path. Longer segments are split along the great circle. Defaults to
+    /// the distance covered in one 5 minute integration step.
+    pub max_path_segment: Option<f64>,
+    /// Only allow selecting times up to this many days from now, though the
+    /// full range is still fetched. Defaults to every time with predictions.
+    pub planning_horizon_days: Option<f64>,
+    /// Most frames drawn per second. Redraws requested sooner, e.g. while
+    /// animating, are deferred.
+    pub max_fps: f64,
+    /// Minutes the selected time advances per second in presentation mode.
+    pub presentation_speed: f64,
+    /// How the sweep's fastest 20% duration cutoff is interpolated.
+    pub sweep_quantile_method: QuantileMethod,
+    /// How current speeds are interpolated between predictions when they're
+    /// resampled to finer intervals.
+    pub speed_interpolation: SpeedInterpolation,
+    /// Number of 30 minute predictions the drawn arrows are averaged over, to
+    /// steady them when animated. Trips use the unsmoothed predictions. Off
+    /// by default.
+    pub arrow_smoothing: Option<usize>,
+    /// Experimental: scale the paddling speed by the tide station's height.
+    /// Off by default.
+    pub tide_speed_profile: Option<TideSpeedProfile>,
+    /// Region to fetch predictions for. Defaults to the region last selected
+    /// in the UI.
+    pub region: Option<Region>,
+    /// JSON file of stations to use instead of fetching the region's station
+    /// list from NOAA. Native only.
+    pub stations_file: Option<PathBuf>,
+    /// Whether to replace predictions with observed currents where stations
+    /// have recent measurements.
+    pub observed_currents: bool,
+    /// Whether to show the inspected station's raw NOAA response and parsed
+    /// predictions, for diagnosing parsing issues.
+    pub debug_panel: bool,
+    /// Time in the region's time zone to treat as the current time, e.g. to
+    /// review a past trip. Defaults to the real time.
+    pub now: Option<NaiveDateTime>,
+    /// Whether to open at the current time rather than the start of the
+    /// predictions, unless a shared plan sets the departure.
+    pub start_at_now: bool,
+    /// Print the NOAA URLs that would be fetched, in order, instead of
+    /// running.
+    pub dump_urls: bool,
+    /// Fetch a known station from the live NOAA API, bypassing the cache,
+    /// and check that the responses have the fields KayakNav parses, instead
+    /// of running.
+    pub check_api: bool,
 }
 
 impl Default for Config {
@@ -85,8 +229,124 @@ impl Default for Config {
         Self {
             use_api_proxy: false,
             api_proxy_url: "https://kayaknav.com/proxy".to_string(),
+            output: None,
+            arrow_update_epsilon: 0.05,
+            initial_zoom: None,
+            max_tile_level: 18,
+            coordinate_system: CoordinateSystem::default(),
+            max_station_distance: 2.0,
+            default_pause_hours: 0.5,
+            hit_tolerance: 8.0,
+            sharp_turn_warning: None,
+            max_path_segment: None,
+            planning_horizon_days: None,
+            max_fps: 60.0,
+            presentation_speed: 10.0,
+            sweep_quantile_method: QuantileMethod::default(),
+            speed_interpolation: SpeedInterpolation::default(),
+            arrow_smoothing: None,
+            tide_speed_profile: None,
+            region: None,
+            stations_file: None,
+            observed_currents: false,
+            debug_panel: false,
+            now: None,
+            start_at_now: true,
+            dump_urls: false,
+            check_api: false,
+        }
+    }
+}
+
+impl Config {
+    pub(crate) fn api_proxy(&self) -> Option<ApiProxy> {
+        self.use_api_proxy.then(|| ApiProxy {
+            url: self.api_proxy_url.clone(),
+        })
+    }
+}
+
+/// Prints the NOAA URLs that `run` would fetch for `config`, in the order
+/// they'd be requested, without fetching them. The current stations come from
+/// the fetched station list, so their URLs are only printed with
+/// `stations_file`.
+pub fn dump_urls(config: &Config) -> Result<()> {
+    let api_proxy = config.api_proxy();
+    let region = config.region.or_else(regions::load).unwrap_or_default();
+    let clock = Clock::new(config.now, region.time_zone);
+    let start_date = state::prediction_start(clock.now().date()).log()?;
+    let hours = state::PREDICTION_HOURS;
+
+    println!(
+        "{}",
+        Station::metadata_url(region.tide_station, api_proxy.as_ref())
+    );
+    println!(
+        "{}",
+        Station::tide_prediction_url(region.tide_station, start_date, hours, api_proxy.as_ref())
+    );
+    println!(
+        "{}",
+        Station::tide_height_url(region.tide_station, start_date, hours, api_proxy.as_ref())
+    );
+
+    let Some(stations_file) = &config.stations_file else {
+        println!("{}", Station::list_url(api_proxy.as_ref()));
+        eprintln!("Pass --stations-file to also print each station's current prediction URLs.");
+        return Ok(());
+    };
+    let mut stations: Vec<_> = Station::from_file(stations_file, api_proxy)?
+        .into_iter()
+        .collect();
+    stations.sort_unstable_by(|a, b| a.id.cmp(&b.id));
+    for station in stations {
+        println!("{}", station.current_prediction_url(start_date, hours));
+        if config.observed_currents {
+            println!("{}", station.observed_current_url());
+        }
+    }
+    Ok(())
+}
+
+/// Caps the frame rate by deferring redraws requested too soon after the last
+/// frame until the next frame is due.
+struct FrameLimiter {
+    interval_ms: i64,
+    last_frame: Option<Instant>,
+    wake_pending: Arc<AtomicBool>,
+}
+
+impl FrameLimiter {
+    fn new(max_fps: f64) -> Self {
+        Self {
+            interval_ms: (1000.0 / max_fps) as i64,
+            last_frame: None,
+            wake_pending: Arc::new(AtomicBool::new(false)),
         }
     }
+
+    /// Whether a frame can be drawn now. If not, a redraw of `window` is
+    /// requested once it can.
+    fn ready(&mut self, window: &Arc<Window>) -> bool {
+        let now = Instant::now();
+        if let Some(last_frame) = self.last_frame {
+            let remaining_ms = self.interval_ms - (now - last_frame).as_millis() as i64;
+            if remaining_ms > 0 {
+                if !self.wake_pending.swap(true, Ordering::Relaxed) {
+                    let wake_pending = self.wake_pending.clone();
+                    let window = window.clone();
+                    tasks::spawn(async move {
+                        tasks::sleep(remaining_ms as i32).await;
+                        wake_pending.store(false, Ordering::Relaxed);
+                        window.request_redraw();
+                    });
+                }
+                return false;
+            }
+        }
+        self.last_frame = Some(now);
+        true
+    }
 }
 
 pub async fn run(window: Window, event_loop: EventLoop<()>, config: Config) {
@@ -97,8 +357,16 @@ pub async fn run(window: Window, event_loop: EventLoop<()>, config: Config) {
 
     let window = Arc::new(window);
 
+    let mut frame_limiter = FrameLimiter::new(config.max_fps);
     let mut state = State::new(Arc::clone(&window), config).await.unwrap();
 
+    // Rendering is paused while the page is hidden, and the surface is
+    // reconfigured once it's shown again.
+    let hidden = Arc::new(AtomicBool::new(false));
+    #[cfg(target_arch = "wasm32")]
+    watch_visibility(Arc::clone(&window), Arc::clone(&hidden));
+    let mut was_hidden = false;
+
     let _ = event_loop.run(move |event, ewlt| {
         ewlt.set_control_flow(ControlFlow::Wait);
 
@@ -117,17 +385,34 @@ pub async fn run(window: Window, event_loop: EventLoop<()>, config: Config) {
                                 ..
                             },
                         ..
-                    } => ewlt.exit(),
+                    } => {
+                        state.save_view();
+                        state.write_output();
+                        ewlt.exit()
+                    },
                     WindowEvent::Resized(physical_size) => {
                         state.resize(*physical_size);
                     },
-                    WindowEvent::RedrawRequested => match state.render() {
-                        Ok(_) => {},
-                        Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
-                            state.resize(state.size)
-                        },
-                        Err(wgpu::SurfaceError::OutOfMemory) => ewlt.exit(),
-                        Err(wgpu::SurfaceError::Timeout) => {},
+                    WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                        state.set_scale_factor(*scale_factor);
+                    },
+                    WindowEvent::RedrawRequested if hidden.load(Ordering::Relaxed) => {
+                        was_hidden = true;
+                        return;
+                    },
+                    WindowEvent::RedrawRequested if !frame_limiter.ready(&window) => return,
+                    WindowEvent::RedrawRequested => {
+                        if mem::take(&mut was_hidden) {
+                            state.resume();
+                        }
+                        match state.render() {
+                            Ok(_) => {},
+                            Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                                state.resize(state.size)
+                            },
+                            Err(wgpu::SurfaceError::OutOfMemory) => ewlt.exit(),
+                            Err(wgpu::SurfaceError::Timeout) => {},
+                        }
                     },
                     other => {
                         state.handle_event(other);
@@ -143,6 +428,33 @@ pub async fn run(window: Window, event_loop: EventLoop<()>, config: Config) {
     });
 }
 
+/// Keeps `hidden` up to date with whether the page is hidden, e.g. in a
+/// background tab, and requests a redraw when it's shown again.
+#[cfg(target_arch = "wasm32")]
+fn watch_visibility(window: Arc<Window>, hidden: Arc<AtomicBool>) {
+    let Some(document) = web_sys::window().and_then(|win| win.document()) else {
+        return;
+    };
+
+    let on_change = {
+        let document = document.clone();
+        Closure::<dyn FnMut()>::new(move || {
+            let is_hidden = document.hidden();
+            hidden.store(is_hidden, Ordering::Relaxed);
+            if !is_hidden {
+                window.request_redraw();
+            }
+        })
+    };
+    if let Err(err) = document
+        .add_event_listener_with_callback("visibilitychange", on_change.as_ref().unchecked_ref())
+    {
+        warn!("Couldn't watch the page's visibility: {err:?}");
+    }
+    // The listener lives as long as the page.
+    on_change.forget();
+}
+
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::wasm_bindgen;
 
@@ -171,25 +483,11 @@ pub async fn set_up() -> (Window, EventLoop<()>) {
         web_window.inner_height().unwrap().as_f64().unwrap() * scale,
     ));
 
-    sleep(10).await;
+    tasks::sleep(10).await;
 
     (window, event_loop)
 }
 
-#[cfg(target_arch = "wasm32")]
-async fn sleep(duration: i32) {
-    let mut cb = |resolve: js_sys::Function, _reject: js_sys::Function| {
-        web_sys::window()
-            .unwrap()
-            .set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, duration)
-            .unwrap();
-    };
-
-    let p = js_sys::Promise::new(&mut cb);
-
-    wasm_bindgen_futures::JsFuture::from(p).await.unwrap();
-}
-
 #[cfg(target_arch = "wasm32")]
 #[wasm_bindgen(start)]
 pub async fn init() {
@@ -203,6 +501,8 @@ pub async fn init() {
         api_proxy_url: option_env!("KAYAKNAV_API_PROXY_URL")
             .map(str::to_string)
             .unwrap_or_else(|| Config::default().api_proxy_url),
+        output: None,
+        ..Config::default()
     };
     let (window, event_loop) = set_up().await;
     run(window, event_loop, config).await;
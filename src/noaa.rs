@@ -1,12 +1,21 @@
+use std::collections::HashMap;
 use std::collections::HashSet;
+use std::f64::consts::FRAC_PI_2;
+use std::f64::consts::PI;
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs;
 use std::hash::Hash;
 use std::hash::Hasher;
+use std::path::Path;
 use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::RwLock;
 
 // use backon::ExponentialBuilder;
 // use backon::Retryable;
 use chrono::NaiveDate;
 use chrono::NaiveDateTime;
+use chrono::Timelike;
 use galileo_types::geo::impls::GeoPoint2d;
 use galileo_types::geo::GeoPoint;
 use galileo_types::geo::NewGeoPoint;
@@ -22,10 +31,25 @@ use rstar::PointDistance;
 use rstar::RTreeObject;
 use rstar::AABB;
 use serde_json::json;
+use serde_json::Value;
 
 use crate::http;
 use crate::http::ApiProxy;
 use crate::prelude::*;
+use crate::tasks;
+use crate::time_idx;
+
+const STATION_LIST_URL: &str = concat!(
+    "https://api.tidesandcurrents.noaa.gov/mdapi/prod/webapi/stations.json",
+    "?type=currentpredictions",
+);
+
+fn proxied(url: String, api_proxy: Option<&ApiProxy>) -> String {
+    match api_proxy {
+        Some(api_proxy) => api_proxy.proxied_url(&url),
+        None => url,
+    }
+}
 
 fn metadata_url(station_id: &str) -> String {
     format!("https://api.tidesandcurrents.noaa.gov/mdapi/prod/webapi/stations/{station_id}.json")
@@ -58,6 +82,11 @@ fn current_prediction_url(
     )
 }
 
+fn observed_current_url(station_id: &str) -> String {
+    // The last 72 hours, every 6 minutes.
+    format!("{URL_BASE}&station={station_id}&date=recent&product=currents")
+}
+
 fn tide_prediction_url(station_id: &str, begin_date: NaiveDate, hours: u32) -> String {
     format!(
         "{}&product=predictions&interval=hilo&datum=MLLW",
@@ -65,6 +94,13 @@ fn tide_prediction_url(station_id: &str, begin_date: NaiveDate, hours: u32) -> S
     )
 }
 
+fn tide_height_url(station_id: &str, begin_date: NaiveDate, hours: u32) -> String {
+    format!(
+        "{}&product=predictions&interval=30&datum=MLLW",
+        common_url(station_id, begin_date, hours)
+    )
+}
+
 #[derive(Debug, Copy, Clone)]
 pub enum StationType {
     Harmonic,
@@ -95,13 +131,64 @@ impl Hash for Station {
 }
 
 impl Station {
+    /// URL of station `id`'s metadata, as fetched by `new`.
+    pub fn metadata_url(id: &str, api_proxy: Option<&ApiProxy>) -> String {
+        proxied(metadata_url(id), api_proxy)
+    }
+
+    /// URL of the list of current prediction stations, as fetched by
+    /// `in_area`.
+    pub fn list_url(api_proxy: Option<&ApiProxy>) -> String {
+        proxied(STATION_LIST_URL.to_string(), api_proxy)
+    }
+
+    /// URL of the station's current predictions, as fetched by
+    /// `current_prediction`.
+    pub fn current_prediction_url(&self, start: NaiveDate, hours: u32) -> String {
+        let (interval, vel_type) = match self.type_ {
+            StationType::Harmonic => ("h", "speed_dir"),
+            StationType::Subordinate => ("max_slack", "default"),
+        };
+        proxied(
+            current_prediction_url(&self.id, start, hours, interval, vel_type),
+            self.api_proxy.as_ref(),
+        )
+    }
+
+    /// URL of the station's observed currents, as fetched by
+    /// `observed_currents`.
+    pub fn observed_current_url(&self) -> String {
+        proxied(observed_current_url(&self.id), self.api_proxy.as_ref())
+    }
+
+    /// URL of station `id`'s tide predictions, as fetched by
+    /// `tide_prediction`.
+    pub fn tide_prediction_url(
+        id: &str,
+        start: NaiveDate,
+        hours: u32,
+        api_proxy: Option<&ApiProxy>,
+    ) -> String {
+        proxied(tide_prediction_url(id, start, hours), api_proxy)
+    }
+
+    /// URL of station `id`'s tide heights, as fetched by `tide_heights`.
+    pub fn tide_height_url(
+        id: &str,
+        start: NaiveDate,
+        hours: u32,
+        api_proxy: Option<&ApiProxy>,
+    ) -> String {
+        proxied(tide_height_url(id, start, hours), api_proxy)
+    }
+
     pub async fn new(id: &str, api_proxy: Option<ApiProxy>) -> Result<Self> {
-        let mut url = metadata_url(id);
-        if let Some(api_proxy) = &api_proxy {
-            url = api_proxy.proxied_url(&url);
-        }
+        let url = Self::metadata_url(id, api_proxy.as_ref());
 
-        let resp = http::fetch_json(&url).await.log()?;
+        let resp = http::fetch_json(&url)
+            .await
+            .map_err(|err| anyhow!("Error fetching metadata for station {id:?}: {err}"))
+            .log()?;
 
         let station_obj = &resp["stations"][0];
         Ok(Self {
@@ -132,12 +219,12 @@ impl Station {
         let lat = (f64::min(lat.0, lat.1), f64::max(lat.0, lat.1));
         let lon = (f64::min(lon.0, lon.1), f64::max(lon.0, lon.1));
 
-        let mut url = "https://api.tidesandcurrents.noaa.gov/mdapi/prod/webapi/stations.json?type=currentpredictions".to_string();
-        if let Some(api_proxy) = &api_proxy {
-            url = api_proxy.proxied_url(&url);
-        }
+        let url = Self::list_url(api_proxy.as_ref());
 
-        let resp = http::fetch_json(&url).await.log()?;
+        let resp = http::fetch_json(&url)
+            .await
+            .map_err(|err| anyhow!("Error fetching the current prediction station list: {err}"))
+            .log()?;
 
         resp["stations"]
             .as_array()
@@ -171,21 +258,115 @@ impl Station {
             .collect()
     }
 
+    /// The station's definition, as read by `from_json`.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "id": self.id,
+            "name": self.name,
+            "lat": self.loc.lat(),
+            "lon": self.loc.lon(),
+            "type": match self.type_ {
+                StationType::Harmonic => "harmonic",
+                StationType::Subordinate => "subordinate",
+            },
+        })
+    }
+
+    /// Reads a station definition written by `to_json`, without fetching its
+    /// metadata.
+    pub fn from_json(station: &Value, api_proxy: Option<ApiProxy>) -> Result<Self> {
+        let field = |key: &str| {
+            station
+                .get(key)
+                .ok_or_else(|| anyhow!("Station {station} is missing {key:?}"))
+        };
+        let id = field("id")?
+            .as_str()
+            .filter(|id| !id.is_empty())
+            .ok_or_else(|| anyhow!("Station {station} has an invalid id"))?;
+        let name = field("name")?
+            .as_str()
+            .ok_or_else(|| anyhow!("Station {station} has an invalid name"))?;
+        let lat = field("lat")?
+            .as_f64()
+            .filter(|lat| (-90.0..=90.0).contains(lat))
+            .ok_or_else(|| anyhow!("Station {station} has an invalid lat"))?;
+        let lon = field("lon")?
+            .as_f64()
+            .filter(|lon| (-180.0..=180.0).contains(lon))
+            .ok_or_else(|| anyhow!("Station {station} has an invalid lon"))?;
+        let type_ = match field("type")?.as_str() {
+            Some("harmonic") => StationType::Harmonic,
+            Some("subordinate") => StationType::Subordinate,
+            _ => {
+                return Err(anyhow!(
+                    "Station {station} has an invalid type, expected 'harmonic' or 'subordinate'"
+                ))
+            },
+        };
+
+        Ok(Self {
+            id: id.to_string(),
+            name: name.to_string(),
+            loc: GeoPoint2d::latlon(lat, lon),
+            type_,
+            api_proxy,
+        })
+    }
+
+    /// Reads a JSON array of station definitions, as written by
+    /// `--output stations`. Malformed stations are skipped with a warning.
+    pub fn list_from_json(json: &str, api_proxy: Option<ApiProxy>) -> Result<HashSet<Self>> {
+        let stations: Value = serde_json::from_str(json)
+            .map_err(|err| anyhow!("Error parsing the station list: {err}"))
+            .log()?;
+        let stations = stations
+            .as_array()
+            .ok_or_else(|| anyhow!("The station list is not a JSON array"))
+            .log()?;
+
+        Ok(stations
+            .iter()
+            .filter_map(|station| {
+                Self::from_json(station, api_proxy.clone())
+                    .map_err(|err| warn!("Skipping station: {err}"))
+                    .ok()
+            })
+            .collect())
+    }
+
+    /// Reads the stations in the JSON file at `path`, in place of
+    /// `in_area`'s station list fetched from NOAA.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn from_file(path: &Path, api_proxy: Option<ApiProxy>) -> Result<HashSet<Self>> {
+        let json = fs::read_to_string(path)
+            .map_err(|err| anyhow!("Error reading stations from {path:?}: {err}"))
+            .log()?;
+        Self::list_from_json(&json, api_proxy)
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn from_file(path: &Path, _api_proxy: Option<ApiProxy>) -> Result<HashSet<Self>> {
+        Err(anyhow!(
+            "Can't read stations from {path:?}, files are only available natively"
+        ))
+    }
+
+    /// Definitions of `stations`, sorted by id, as read by `list_from_json`.
+    pub fn list_to_json<'a>(stations: impl IntoIterator<Item = &'a Self>) -> Value {
+        let mut stations: Vec<_> = stations.into_iter().collect();
+        stations.sort_unstable_by(|a, b| a.id.cmp(&b.id));
+        Value::Array(stations.into_iter().map(Self::to_json).collect())
+    }
+
     #[instrument(level = "debug")]
     pub async fn current_prediction(
         &self,
         start: NaiveDate,
         hours: u32,
+        speed_interpolation: SpeedInterpolation,
     ) -> Result<CurrentPrediction<30>> {
-        let (interval, vel_type) = match self.type_ {
-            StationType::Harmonic => ("h", "speed_dir"),
-            StationType::Subordinate => ("max_slack", "default"),
-        };
-
-        let mut url = current_prediction_url(&self.id, start, hours, interval, vel_type);
-        if let Some(api_proxy) = &self.api_proxy {
-            url = api_proxy.proxied_url(&url);
-        }
+        let url = self.current_prediction_url(start, hours);
 
         let resp = http::fetch_json(&url).await.log()?;
 
@@ -248,7 +429,7 @@ impl Station {
 
                 let df = DataFrame::new(vec![time, direction, speed]).log()?;
 
-                df.sort(["time"], Default::default())
+                sort_unique_times(df)
                     .log()?
                     .upsample::<[String; 0]>(
                         [],
@@ -258,7 +439,7 @@ impl Station {
                     )
                     .log()?
                     .lazy()
-                    .with_column(col("speed").interpolate(InterpolationMethod::Linear))
+                    .with_column(speed_interpolation.interpolate(col("speed")))
                     .collect()
                     .log()?
                     .fill_null(FillNullStrategy::Forward(None))
@@ -304,8 +485,7 @@ impl Station {
                     .collect()
                     .log()?;
 
-                df = df
-                    .sort(["time"], Default::default())
+                df = sort_unique_times(df)
                     .log()?
                     .upsample::<[String; 0]>(
                         [],
@@ -315,7 +495,7 @@ impl Station {
                     )
                     .log()?
                     .lazy()
-                    .with_column(col("speed").interpolate(InterpolationMethod::Linear))
+                    .with_column(speed_interpolation.interpolate(col("speed")))
                     .collect()
                     .log()?;
 
@@ -378,11 +558,18 @@ impl Station {
         })
     }
 
+    /// Currents measured over the last 72 hours, on the 30 minute prediction
+    /// grid. Only stations with a real-time current meter have observations.
+    #[instrument(level = "debug")]
+    pub async fn observed_currents(&self) -> Result<DataFrame> {
+        let url = self.observed_current_url();
+
+        let resp = http::fetch_json(&url).await?;
+        parse_observed_currents(&resp)
+    }
+
     pub async fn tide_prediction(&self, start: NaiveDate, hours: u32) -> Result<DataFrame> {
-        let mut url = tide_prediction_url(&self.id, start, hours);
-        if let Some(api_proxy) = &self.api_proxy {
-            url = api_proxy.proxied_url(&url);
-        }
+        let url = Self::tide_prediction_url(&self.id, start, hours, self.api_proxy.as_ref());
 
         let resp = http::fetch_json(&url).await.log()?;
 
@@ -439,8 +626,7 @@ impl Station {
             .collect()
             .log()?;
 
-        df = df
-            .sort(["time"], Default::default())
+        df = sort_unique_times(df)
             .log()?
             .upsample::<[String; 0]>([], "time", Duration::parse("30m"), Duration::parse("0"))
             .log()?;
@@ -474,6 +660,343 @@ impl Station {
 
         Ok(df)
     }
+
+    /// Tide heights every 30 minutes, unlike `tide_prediction`'s highs and
+    /// lows.
+    pub async fn tide_heights(&self, start: NaiveDate, hours: u32) -> Result<TideHeights> {
+        let url = Self::tide_height_url(&self.id, start, hours, self.api_proxy.as_ref());
+
+        let resp = http::fetch_json(&url).await.log()?;
+        parse_tide_heights(&resp)
+    }
+}
+
+/// Sorts `df` by time and drops rows whose time repeats an earlier row's,
+/// keeping the first in response order. NOAA occasionally returns duplicate
+/// or out of order times, and rounding to the 30 minute grid can merge
+/// nearby ones, but upsampling needs unique times.
+fn sort_unique_times(df: DataFrame) -> Result<DataFrame> {
+    let height = df.height();
+    let df = df
+        .sort(
+            ["time"],
+            SortMultipleOptions::default().with_maintain_order(true),
+        )?
+        .unique_stable(Some(&["time".to_string()]), UniqueKeepStrategy::First, None)?;
+    if df.height() < height {
+        warn!("Dropped {} rows with duplicate times", height - df.height());
+    }
+    Ok(df)
+}
+
+/// Predicted tide heights, in feet above MLLW, every 30 minutes.
+#[derive(Debug, Clone)]
+pub struct TideHeights {
+    /// Time of the first height, in milliseconds since the epoch.
+    start: i64,
+    heights: Vec<f64>,
+}
+
+impl TideHeights {
+    const INTERVAL_MILLIS: i64 = time_idx::ROW_MILLIS;
+
+    /// The height at `time`, in milliseconds since the epoch, interpolated
+    /// between the surrounding predictions.
+    pub fn at(&self, time: i64) -> Option<f64> {
+        let offset = time - self.start;
+        if offset < 0 {
+            return None;
+        }
+        let idx = (offset / Self::INTERVAL_MILLIS) as usize;
+        let frac = (offset % Self::INTERVAL_MILLIS) as f64 / Self::INTERVAL_MILLIS as f64;
+        let before = *self.heights.get(idx)?;
+        if frac == 0.0 {
+            return Some(before);
+        }
+        let after = *self.heights.get(idx + 1)?;
+        Some(before + (after - before) * frac)
+    }
+}
+
+/// Parses a 30 minute `predictions` product response, whose heights are
+/// strings.
+fn parse_tide_heights(resp: &Value) -> Result<TideHeights> {
+    let predictions = resp["predictions"]
+        .as_array()
+        .filter(|predictions| !predictions.is_empty())
+        .ok_or_else(|| anyhow!("Missing tide heights in response: {resp:?}"))?;
+
+    let time = |prediction: &Value| -> Result<i64> {
+        let t = prediction["t"].as_str().log()?;
+        Ok(NaiveDateTime::parse_from_str(t, "%Y-%m-%d %H:%M")
+            .log()?
+            .and_utc()
+            .timestamp_millis())
+    };
+    let start = time(&predictions[0])?;
+
+    let heights = predictions
+        .iter()
+        .enumerate()
+        .fallible()
+        .map(|(i, prediction)| {
+            if time(prediction)? != start + i as i64 * TideHeights::INTERVAL_MILLIS {
+                Err(anyhow!(
+                    "Tide heights aren't every 30 minutes: {prediction:?}"
+                ))?
+            }
+            Ok(f64::from_str(prediction["v"].as_str().log()?).log()?)
+        })
+        .collect::<Vec<f64>>()
+        .log()?;
+
+    Ok(TideHeights { start, heights })
+}
+
+/// Parses a `currents` product response into time, speed, and direction
+/// columns, keeping only observations on the 30 minute prediction grid.
+fn parse_observed_currents(resp: &Value) -> Result<DataFrame> {
+    let observations = resp["data"]
+        .as_array()
+        .ok_or_else(|| anyhow!("Missing observed currents in response: {resp:?}"))?;
+
+    // Values are usually strings, and are empty when the meter reported
+    // nothing.
+    let number = |value: &Value| value.as_f64().or_else(|| value.as_str()?.parse().ok());
+
+    let mut time = vec![];
+    let mut speed = vec![];
+    let mut direction = vec![];
+    for observation in observations {
+        let t = NaiveDateTime::parse_from_str(observation["t"].as_str().log()?, "%Y-%m-%d %H:%M")
+            .log()?;
+        let (Some(s), Some(d)) = (number(&observation["s"]), number(&observation["d"])) else {
+            continue;
+        };
+        if t.minute() % 30 == 0 {
+            time.push(t);
+            speed.push(s);
+            direction.push(d);
+        }
+    }
+
+    if time.is_empty() {
+        return Err(anyhow!("No observed currents in response"));
+    }
+
+    sort_unique_times(DataFrame::new(vec![
+        Series::new("time", time),
+        Series::new("speed", speed),
+        Series::new("direction", direction),
+    ])?)
+}
+
+pub type TideResult = std::result::Result<DataFrame, String>;
+
+/// Tide predictions for arbitrary stations, fetched on demand and cached since
+/// most stations are never inspected.
+#[derive(Debug, Clone)]
+pub struct TideCache {
+    start: NaiveDate,
+    hours: u32,
+    tides: Arc<RwLock<HashMap<String, Option<TideResult>>>>,
+}
+
+impl TideCache {
+    pub fn new(start: NaiveDate, hours: u32) -> Self {
+        Self {
+            start,
+            hours,
+            tides: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// Returns the station's tide predictions, or None while they are being
+    /// fetched. If this call starts a fetch, `on_fetched` is called once it
+    /// completes.
+    pub fn get(
+        &self,
+        station: &Station,
+        on_fetched: impl FnOnce() + Send + 'static,
+    ) -> Option<TideResult> {
+        if let Some(tides) = self.tides.read().unwrap().get(&station.id) {
+            return tides.clone();
+        }
+
+        self.tides.write().unwrap().insert(station.id.clone(), None);
+
+        let tides = self.tides.clone();
+        let station = station.clone();
+        let start = self.start;
+        let hours = self.hours;
+        tasks::spawn(async move {
+            let result = station
+                .tide_prediction(start, hours)
+                .await
+                .map_err(|err| err.to_string());
+            tides.write().unwrap().insert(station.id, Some(result));
+            on_fetched();
+        });
+
+        None
+    }
+}
+
+pub type RawResponse = std::result::Result<String, String>;
+
+/// Pretty-printed current prediction responses, for inspecting what was parsed.
+/// Refetched on demand, usually from the response cache, rather than retained
+/// for every station.
+#[derive(Debug, Clone)]
+pub struct RawResponseCache {
+    start: NaiveDate,
+    hours: u32,
+    responses: Arc<RwLock<HashMap<String, Option<RawResponse>>>>,
+}
+
+impl RawResponseCache {
+    pub fn new(start: NaiveDate, hours: u32) -> Self {
+        Self {
+            start,
+            hours,
+            responses: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// Returns the station's current prediction response, or None while it is
+    /// being fetched. If this call starts a fetch, `on_fetched` is called once
+    /// it completes.
+    pub fn get(
+        &self,
+        station: &Station,
+        on_fetched: impl FnOnce() + Send + 'static,
+    ) -> Option<RawResponse> {
+        if let Some(response) = self.responses.read().unwrap().get(&station.id) {
+            return response.clone();
+        }
+
+        self.responses
+            .write()
+            .unwrap()
+            .insert(station.id.clone(), None);
+
+        let responses = self.responses.clone();
+        let id = station.id.clone();
+        let url = station.current_prediction_url(self.start, self.hours);
+        tasks::spawn(async move {
+            let result = match http::fetch_json(&url).await {
+                Ok(json) => serde_json::to_string_pretty(&json).map_err(|err| err.to_string()),
+                Err(err) => Err(err.to_string()),
+            };
+            responses.write().unwrap().insert(id, Some(result));
+            on_fetched();
+        });
+
+        None
+    }
+}
+
+/// Interpolates linearly between the currents `a` and `b`, as (speed,
+/// direction in degrees), as velocity vectors rather than separately, so that
+/// a reversal slows through slack instead of swinging around at full speed.
+fn interpolate_velocity(a: (f64, f64), b: (f64, f64), t: f64) -> (f64, f64) {
+    let (east_a, north_a) = velocity(a);
+    let (east_b, north_b) = velocity(b);
+    let east = east_a + (east_b - east_a) * t;
+    let north = north_a + (north_b - north_a) * t;
+    (
+        east.hypot(north),
+        (east.atan2(north).to_degrees() + 360.0) % 360.0,
+    )
+}
+
+/// The east and north components of a current's (speed, direction in
+/// degrees).
+fn velocity((speed, direction): (f64, f64)) -> (f64, f64) {
+    let radians = direction.to_radians();
+    (speed * radians.sin(), speed * radians.cos())
+}
+
+/// How speeds are filled in between a station's predictions when they're
+/// resampled to a finer interval.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum SpeedInterpolation {
+    /// Straight lines between predictions.
+    #[default]
+    Linear,
+    /// Quarter sine waves between slack and max, and half cosine waves between
+    /// opposite maxes. Tidal currents roughly follow a sinusoid, so this is
+    /// more physically accurate for subordinate stations, whose predictions
+    /// are only their slacks and maxes: a straight line underestimates the
+    /// current between them.
+    Sinusoidal,
+}
+
+impl FromStr for SpeedInterpolation {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "linear" => Ok(Self::Linear),
+            "sinusoidal" => Ok(Self::Sinusoidal),
+            _ => Err(anyhow!(
+                "Unknown interpolation method {s:?}, expected 'linear' or 'sinusoidal'"
+            )),
+        }
+    }
+}
+
+impl SpeedInterpolation {
+    /// Fills the nulls of `speed` between known values. Leading and trailing
+    /// nulls are left, as with `Expr::interpolate`.
+    fn interpolate(self, speed: Expr) -> Expr {
+        match self {
+            Self::Linear => speed.interpolate(InterpolationMethod::Linear),
+            Self::Sinusoidal => speed.map(
+                |s| {
+                    let speeds: Vec<Option<f64>> = s.f64().log()?.into_iter().collect();
+                    Ok(Some(Series::new(s.name(), sinusoidal_fill(&speeds))))
+                },
+                GetOutput::from_type(DataType::Float64),
+            ),
+        }
+    }
+}
+
+/// Fills the gaps between the known `speeds` with `interpolate_sinusoidal`.
+fn sinusoidal_fill(speeds: &[Option<f64>]) -> Vec<Option<f64>> {
+    let known: Vec<(usize, f64)> = speeds
+        .iter()
+        .enumerate()
+        .filter_map(|(i, speed)| Some((i, (*speed)?)))
+        .collect();
+
+    let mut filled = speeds.to_vec();
+    for pair in known.windows(2) {
+        let ((i, a), (j, b)) = (pair[0], pair[1]);
+        for (k, speed) in filled.iter_mut().enumerate().take(j).skip(i + 1) {
+            *speed = Some(interpolate_sinusoidal(
+                a,
+                b,
+                (k - i) as f64 / (j - i) as f64,
+            ));
+        }
+    }
+    filled
+}
+
+/// Interpolates between the speeds `a` and `b` along a sinusoid: changing
+/// fastest near slack and flattening out toward a max.
+fn interpolate_sinusoidal(a: f64, b: f64, t: f64) -> f64 {
+    let shape = if a * b < 0.0 {
+        // From one max through slack to the opposite max.
+        (1.0 - (PI * t).cos()) / 2.0
+    } else if b.abs() >= a.abs() {
+        (FRAC_PI_2 * t).sin()
+    } else {
+        1.0 - (FRAC_PI_2 * t).cos()
+    };
+    a + (b - a) * shape
 }
 
 #[derive(Debug, Clone)]
@@ -491,7 +1014,134 @@ impl<const R: u8> CurrentPrediction<R> {
         Duration::parse(&format!("{}m", Self::resolution_minutes()))
     }
 
-    pub fn resampled<const R2: u8>(&self) -> Result<CurrentPrediction<R2>> {
+    /// Row of the prediction at `time`, in milliseconds since the epoch, or
+    /// `None` if the prediction doesn't cover it.
+    pub fn time_idx(&self, time: i64) -> Option<usize> {
+        let times = self.df["time"].datetime().ok()?;
+        let step = Self::resolution_minutes() as i64 * 60 * 1000;
+        let offset = time - times.get(0)?;
+        if offset < 0 || offset % step != 0 {
+            return None;
+        }
+        let idx = (offset / step) as usize;
+        // `get` panics rather than returning `None` when out of bounds.
+        (idx < times.len() && times.get(idx)? == time).then_some(idx)
+    }
+
+    /// A copy with speeds and directions smoothed by a rolling mean over
+    /// `window` predictions centered on each, for drawing arrows that don't
+    /// twitch when animated. Directions are averaged as unit vectors so they
+    /// don't jump when crossing north.
+    pub fn smoothed(&self, window: usize) -> Result<Self> {
+        let options = RollingOptionsFixedWindow {
+            window_size: window,
+            min_periods: 1,
+            center: true,
+            ..Default::default()
+        };
+        let radians = col("direction").radians();
+        // Rows without predictions, e.g. outside the station's time range,
+        // stay empty rather than taking their neighbors' mean.
+        let present = col("speed").is_not_null();
+        let df = self
+            .df
+            .clone()
+            .lazy()
+            .with_columns([
+                when(present.clone())
+                    .then(col("speed").rolling_mean(options.clone()))
+                    .otherwise(lit(NULL))
+                    .alias("speed"),
+                when(present)
+                    .then(
+                        ((radians.clone().sin().rolling_mean(options.clone()))
+                            .arctan2(radians.cos().rolling_mean(options))
+                            .degrees()
+                            + lit(360.0))
+                            % lit(360.0),
+                    )
+                    .otherwise(lit(NULL))
+                    .alias("direction"),
+            ])
+            .collect()
+            .log()?;
+        Ok(Self {
+            station: self.station.clone(),
+            df,
+        })
+    }
+
+    /// Speed and direction at the fractional row `pos`, interpolated linearly
+    /// between the velocity vectors of the rows around it. `None` if `pos` is
+    /// outside the prediction.
+    pub fn interpolated(&self, pos: f64) -> Option<(f64, f64)> {
+        let speed = self.df["speed"].f64().ok()?;
+        let direction = self.df["direction"].f64().ok()?;
+        let last = self.df.height().checked_sub(1)?;
+        if !(0.0..=last as f64).contains(&pos) {
+            return None;
+        }
+
+        let idx = pos.floor() as usize;
+        let next = (idx + 1).min(last);
+        let t = pos - idx as f64;
+        let a = (speed.get(idx)?, direction.get(idx)?);
+        let b = (speed.get(next)?, direction.get(next)?);
+        Some(interpolate_velocity(a, b, t))
+    }
+
+    /// Replaces the predictions with `observed` currents, as returned by
+    /// `Station::observed_currents`, where they overlap, marking those rows in
+    /// an `observed` column.
+    pub fn add_observations(&mut self, observed: DataFrame) -> Result<()> {
+        let observed = observed.lazy().select([
+            col("time"),
+            col("speed").alias("observed_speed"),
+            col("direction").alias("observed_direction"),
+        ]);
+        self.df = self
+            .df
+            .clone()
+            .lazy()
+            .join(
+                observed,
+                [col("time")],
+                [col("time")],
+                JoinArgs::new(JoinType::Left),
+            )
+            .with_columns([
+                col("observed_speed").is_not_null().alias("observed"),
+                when(col("observed_speed").is_not_null())
+                    .then(col("observed_speed"))
+                    .otherwise(col("speed"))
+                    .alias("speed"),
+                when(col("observed_direction").is_not_null())
+                    .then(col("observed_direction"))
+                    .otherwise(col("direction"))
+                    .alias("direction"),
+            ])
+            .select([col("*").exclude(["observed_speed", "observed_direction"])])
+            .collect()
+            .log()?;
+        Ok(())
+    }
+
+    /// Whether the current at row `idx` was observed rather than predicted.
+    pub fn is_observed(&self, idx: usize) -> bool {
+        // `get` panics rather than returning `None` when out of bounds.
+        idx < self.df.height()
+            && self
+                .df
+                .column("observed")
+                .ok()
+                .and_then(|observed| observed.bool().ok()?.get(idx))
+                .unwrap_or(false)
+    }
+
+    pub fn resampled<const R2: u8>(
+        &self,
+        speed_interpolation: SpeedInterpolation,
+    ) -> Result<CurrentPrediction<R2>> {
         let df = self
             .df
             .clone()
@@ -505,11 +1155,13 @@ impl<const R: u8> CurrentPrediction<R> {
             )
             .log()?
             .lazy()
-            .with_columns([col("speed").interpolate(InterpolationMethod::Linear)])
+            .with_columns([speed_interpolation.interpolate(col("speed"))])
             .with_columns([col("direction").interpolate(InterpolationMethod::Linear)])
+            // Speeds and directions are left null past the ends of the
+            // predictions, rather than extended, since those rows may be
+            // padding from aligning them to a common time grid.
+            .with_columns([all().exclude(["time", "speed", "direction"]).forward_fill(None)])
             .collect()
-            .log()?
-            .fill_null(FillNullStrategy::Forward(None))
             .log()?;
 
         Ok(CurrentPrediction::<R2> {
@@ -548,3 +1200,217 @@ impl PointDistance for Station {
             .as_metres()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeDelta;
+
+    use super::*;
+
+    #[test]
+    fn sort_unique_times_keeps_the_first_of_each_time() {
+        let time = |hour, minute| {
+            NaiveDate::from_ymd_opt(2024, 6, 1)
+                .unwrap()
+                .and_hms_opt(hour, minute, 0)
+                .unwrap()
+        };
+        let df = df!(
+            "time" => [time(10, 30), time(10, 0), time(10, 30), time(11, 0), time(10, 0)],
+            "speed" => [1.0, 2.0, 3.0, 4.0, 5.0],
+        )
+        .unwrap();
+
+        let df = sort_unique_times(df).unwrap();
+
+        let times: Vec<_> = df["time"].datetime().unwrap().into_iter().collect();
+        let expected_times: Vec<_> = [time(10, 0), time(10, 30), time(11, 0)]
+            .iter()
+            .map(|time| Some(time.and_utc().timestamp_millis()))
+            .collect();
+        assert_eq!(times, expected_times);
+        let speeds: Vec<_> = df["speed"].f64().unwrap().into_iter().collect();
+        assert_eq!(speeds, [Some(2.0), Some(1.0), Some(4.0)]);
+    }
+
+    fn assert_speed_eq(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "{actual} != {expected}");
+    }
+
+    #[test]
+    fn sinusoidal_slack_to_max() {
+        assert_speed_eq(interpolate_sinusoidal(0.0, 2.0, 0.0), 0.0);
+        // Rises fastest out of slack, so ahead of a straight line.
+        assert_speed_eq(interpolate_sinusoidal(0.0, 2.0, 0.5), 2.0_f64.sqrt());
+        assert_speed_eq(interpolate_sinusoidal(0.0, 2.0, 1.0), 2.0);
+        assert_speed_eq(interpolate_sinusoidal(0.0, -2.0, 0.5), -(2.0_f64.sqrt()));
+    }
+
+    #[test]
+    fn sinusoidal_max_to_slack() {
+        assert_speed_eq(interpolate_sinusoidal(2.0, 0.0, 0.0), 2.0);
+        // Falls slowest off the max, so ahead of a straight line.
+        assert_speed_eq(interpolate_sinusoidal(2.0, 0.0, 0.5), 2.0_f64.sqrt());
+        assert_speed_eq(interpolate_sinusoidal(2.0, 0.0, 1.0), 0.0);
+    }
+
+    #[test]
+    fn sinusoidal_max_to_opposite_max() {
+        assert_speed_eq(interpolate_sinusoidal(2.0, -2.0, 0.0), 2.0);
+        assert_speed_eq(interpolate_sinusoidal(2.0, -2.0, 0.25), 2.0_f64.sqrt());
+        assert_speed_eq(interpolate_sinusoidal(2.0, -2.0, 0.5), 0.0);
+        assert_speed_eq(interpolate_sinusoidal(2.0, -2.0, 0.75), -(2.0_f64.sqrt()));
+        assert_speed_eq(interpolate_sinusoidal(2.0, -2.0, 1.0), -2.0);
+    }
+
+    #[test]
+    fn sinusoidal_fill_leaves_leading_and_trailing_nulls() {
+        let filled = sinusoidal_fill(&[None, Some(0.0), None, Some(2.0), None, None]);
+
+        assert_eq!(filled[..2], [None, Some(0.0)]);
+        assert_speed_eq(filled[2].unwrap(), 2.0_f64.sqrt());
+        assert_eq!(filled[3..], [Some(2.0), None, None]);
+        assert_eq!(sinusoidal_fill(&[None, None]), [None, None]);
+        assert_eq!(sinusoidal_fill(&[]), []);
+    }
+
+    #[test]
+    fn sinusoidal_resampling_leads_linear_between_slack_and_max() {
+        let start = NaiveDate::from_ymd_opt(2024, 6, 1)
+            .unwrap()
+            .and_hms_opt(8, 0, 0)
+            .unwrap();
+        // Slack, max, then slack again, half an hour apart.
+        let prediction = CurrentPrediction::<30> {
+            station: Station {
+                id: "a".to_string(),
+                name: "a".to_string(),
+                loc: GeoPoint2d::latlon(40.70, -74.02),
+                type_: StationType::Subordinate,
+                api_proxy: None,
+            },
+            df: df!(
+                "time" => [0, 30, 60].map(|minutes| start + TimeDelta::minutes(minutes)),
+                "speed" => [0.0, 2.0, 0.0],
+                "direction" => [0.0, 0.0, 0.0],
+            )
+            .unwrap(),
+        };
+        let speeds = |speed_interpolation| -> Vec<f64> {
+            let resampled = prediction.resampled::<5>(speed_interpolation).unwrap();
+            resampled.df["speed"]
+                .f64()
+                .unwrap()
+                .into_iter()
+                .map(Option::unwrap)
+                .collect()
+        };
+
+        let linear = speeds(SpeedInterpolation::Linear);
+        let sinusoidal = speeds(SpeedInterpolation::Sinusoidal);
+
+        assert_eq!(linear.len(), 13);
+        assert_eq!(sinusoidal.len(), 13);
+        for (idx, speed) in [(0, 0.0), (6, 2.0), (12, 0.0)] {
+            assert_speed_eq(linear[idx], speed);
+            assert_speed_eq(sinusoidal[idx], speed);
+        }
+        for idx in (1..6).chain(7..12) {
+            assert!(
+                sinusoidal[idx] > linear[idx],
+                "{idx}: {} <= {}",
+                sinusoidal[idx],
+                linear[idx]
+            );
+        }
+    }
+
+    #[test]
+    fn station_list_json_round_trip() {
+        let station = |id: &str, lat, lon, type_| Station {
+            id: id.to_string(),
+            name: format!("Station {id}"),
+            loc: GeoPoint2d::latlon(lat, lon),
+            type_,
+            api_proxy: None,
+        };
+        let stations = [
+            station("NYH1927", 40.7783, -73.9383, StationType::Harmonic),
+            station("ACT3296", 40.7, -74.0167, StationType::Subordinate),
+        ];
+
+        let json = serde_json::to_string(&Station::list_to_json(&stations)).unwrap();
+        let ids: Vec<_> = serde_json::from_str::<Value>(&json)
+            .unwrap()
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|station| station["id"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(ids, ["ACT3296", "NYH1927"]);
+
+        let read = Station::list_from_json(&json, None).unwrap();
+        assert_eq!(read.len(), stations.len());
+        for expected in &stations {
+            let station = read.get(expected).unwrap();
+            assert_eq!(station.name, expected.name);
+            assert_eq!(station.loc.lat(), expected.loc.lat());
+            assert_eq!(station.loc.lon(), expected.loc.lon());
+            assert_eq!(station.to_json(), expected.to_json());
+        }
+    }
+
+    #[test]
+    fn station_list_from_json_skips_malformed_stations() {
+        let json = r#"[
+            {"id": "NYH1927", "name": "A", "lat": 40.7783, "lon": -73.9383, "type": "harmonic"},
+            {"id": "ACT3296", "name": "B", "lat": 40.7, "lon": -74.0167, "type": "tidal"},
+            {"id": "", "name": "C", "lat": 40.7, "lon": -74.0, "type": "harmonic"}
+        ]"#;
+
+        let read = Station::list_from_json(json, None).unwrap();
+        let ids: Vec<_> = read.iter().map(|station| station.id.as_str()).collect();
+        assert_eq!(ids, ["NYH1927"]);
+        assert!(Station::list_from_json("{}", None).is_err());
+        assert!(Station::list_from_json("[", None).is_err());
+    }
+
+    fn assert_current_eq(actual: (f64, f64), expected: (f64, f64)) {
+        let direction_error = (actual.1 - expected.1 + 540.0).rem_euclid(360.0) - 180.0;
+        assert!(
+            (actual.0 - expected.0).abs() < 1e-9 && direction_error.abs() < 1e-9,
+            "{actual:?} != {expected:?}"
+        );
+    }
+
+    #[test]
+    fn interpolate_velocity_same_direction() {
+        let current = interpolate_velocity((1.0, 90.0), (3.0, 90.0), 0.5);
+        assert_current_eq(current, (2.0, 90.0));
+    }
+
+    #[test]
+    fn interpolate_velocity_endpoints() {
+        let a = (1.0, 350.0);
+        let b = (2.0, 20.0);
+        assert_current_eq(interpolate_velocity(a, b, 0.0), a);
+        assert_current_eq(interpolate_velocity(a, b, 1.0), b);
+    }
+
+    #[test]
+    fn interpolate_velocity_across_north() {
+        // Equal speeds 30 degrees apart either side of north.
+        let (_, direction) = interpolate_velocity((1.0, 345.0), (1.0, 15.0), 0.5);
+        assert_current_eq((0.0, direction), (0.0, 0.0));
+    }
+
+    #[test]
+    fn interpolate_velocity_reversal_passes_through_slack() {
+        let flood = (2.0, 45.0);
+        let ebb = (2.0, 225.0);
+        let (speed, _) = interpolate_velocity(flood, ebb, 0.5);
+        assert!(speed.abs() < 1e-9, "speed {speed} at slack");
+        assert_current_eq(interpolate_velocity(flood, ebb, 0.25), (1.0, 45.0));
+        assert_current_eq(interpolate_velocity(flood, ebb, 0.75), (1.0, 225.0));
+    }
+}
@@ -0,0 +1,138 @@
+use crate::prelude::*;
+
+/// Coordinates are rounded to 5 decimal places, about a meter.
+const PRECISION: f64 = 1e5;
+
+/// Encodes latitude and longitude pairs in Google's encoded polyline format,
+/// which stores each coordinate as the difference from the previous one.
+pub fn encode(coordinates: &[(f64, f64)]) -> String {
+    let mut encoded = String::new();
+    let mut prev = (0, 0);
+    for (lat, lon) in coordinates {
+        let cur = (
+            (lat * PRECISION).round() as i64,
+            (lon * PRECISION).round() as i64,
+        );
+        encode_value(cur.0 - prev.0, &mut encoded);
+        encode_value(cur.1 - prev.1, &mut encoded);
+        prev = cur;
+    }
+    encoded
+}
+
+fn encode_value(value: i64, encoded: &mut String) {
+    let mut value = if value < 0 { !(value << 1) } else { value << 1 };
+    while value >= 0x20 {
+        encoded.push(char::from((0x20 | (value & 0x1f)) as u8 + 63));
+        value >>= 5;
+    }
+    encoded.push(char::from(value as u8 + 63));
+}
+
+/// Decodes latitude and longitude pairs from Google's encoded polyline format.
+pub fn decode(polyline: &str) -> Result<Vec<(f64, f64)>> {
+    let mut bytes = polyline.bytes();
+    let mut coordinates = vec![];
+    let mut cur = (0, 0);
+    while let Some(lat) = decode_value(&mut bytes)? {
+        let lon = decode_value(&mut bytes)?
+            .ok_or_else(|| anyhow!("Polyline {polyline:?} ends with a lone latitude"))?;
+        cur = (cur.0 + lat, cur.1 + lon);
+        coordinates.push((cur.0 as f64 / PRECISION, cur.1 as f64 / PRECISION));
+    }
+    Ok(coordinates)
+}
+
+/// Decodes the next value, or returns `None` at the end of the polyline.
+fn decode_value(bytes: &mut impl Iterator<Item = u8>) -> Result<Option<i64>> {
+    let mut value = 0i64;
+    let mut shift = 0;
+    loop {
+        let Some(byte) = bytes.next() else {
+            return if shift == 0 {
+                Ok(None)
+            } else {
+                Err(anyhow!("Polyline ends in the middle of a value"))
+            };
+        };
+        if !(63..=126).contains(&byte) || shift > 60 {
+            return Err(anyhow!("Invalid polyline character {:?}", char::from(byte)));
+        }
+        let chunk = i64::from(byte - 63);
+        value |= (chunk & 0x1f) << shift;
+        shift += 5;
+        if chunk < 0x20 {
+            break;
+        }
+    }
+    Ok(Some(if value & 1 == 1 {
+        !(value >> 1)
+    } else {
+        value >> 1
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: &[(f64, f64)], expected: &[(f64, f64)]) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected) {
+            assert!(
+                (a.0 - e.0).abs() <= 0.5 / PRECISION && (a.1 - e.1).abs() <= 0.5 / PRECISION,
+                "{a:?} != {e:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn google_example() {
+        let coordinates = [(38.5, -120.2), (40.7, -120.95), (43.252, -126.453)];
+        let encoded = "_p~iF~ps|U_ulLnnqC_mqNvxq`@";
+        assert_eq!(encode(&coordinates), encoded);
+        assert_close(&decode(encoded).unwrap(), &coordinates);
+    }
+
+    #[test]
+    fn round_trip_new_york() {
+        // The Battery, the Statue of Liberty and Hell Gate, with negative
+        // longitudes.
+        let coordinates = [
+            (40.70037, -74.01485),
+            (40.68925, -74.0445),
+            (40.77958, -73.92986),
+        ];
+        assert_eq!(decode(&encode(&coordinates)).unwrap(), coordinates);
+    }
+
+    #[test]
+    fn round_trip_rounds_to_precision() {
+        let coordinates = [(40.700374912, -74.014853177), (40.689250499, -74.044499501)];
+        let decoded = decode(&encode(&coordinates)).unwrap();
+        assert_close(&decoded, &coordinates);
+        assert_eq!(decoded, [(40.70037, -74.01485), (40.68925, -74.0445)]);
+    }
+
+    #[test]
+    fn empty() {
+        assert_eq!(encode(&[]), "");
+        assert_eq!(decode("").unwrap(), []);
+    }
+
+    #[test]
+    fn rejects_truncated() {
+        let encoded = encode(&[(40.70037, -74.01485)]);
+        // Mid-value, and after the latitude's 5 characters.
+        assert!(decode(&encoded[..encoded.len() - 1]).is_err());
+        assert!(decode(&encoded[..5]).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_characters() {
+        assert!(decode("_p~iF ~ps|U").is_err());
+        assert!(decode("_p~iF~ps|U\u{e9}").is_err());
+        // A value longer than fits in an i64.
+        assert!(decode(&"~".repeat(20)).is_err());
+    }
+}
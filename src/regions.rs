@@ -0,0 +1,93 @@
+use std::str::FromStr;
+
+use chrono_tz::Tz;
+
+use crate::prelude::*;
+use crate::storage;
+
+const REGION_KEY: &str = "region";
+
+/// A preset area to fetch current predictions for.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Region {
+    /// Identifier used on the command line and in saved settings.
+    pub key: &'static str,
+    pub name: &'static str,
+    /// Tide station whose predictions set the time range and are shown
+    /// alongside the selected time.
+    pub tide_station: &'static str,
+    /// Current stations within these latitude and longitude ranges are
+    /// fetched.
+    pub lat_range: (f64, f64),
+    pub lon_range: (f64, f64),
+    /// Latitude and longitude of the default map center.
+    pub center: (f64, f64),
+    /// Time zone of the region's stations, whose predictions are in local
+    /// time.
+    pub time_zone: Tz,
+}
+
+pub const REGIONS: &[Region] = &[
+    Region {
+        key: "nyc",
+        name: "NYC Harbor",
+        // The Battery
+        tide_station: "8518750",
+        lat_range: (39.0, 42.0),
+        lon_range: (-75.0, -73.0),
+        center: (40.7127, -74.0059),
+        time_zone: Tz::America__New_York,
+    },
+    Region {
+        key: "puget-sound",
+        name: "Puget Sound",
+        // Seattle
+        tide_station: "9447130",
+        lat_range: (47.0, 48.5),
+        lon_range: (-123.2, -122.0),
+        center: (47.6062, -122.3321),
+        time_zone: Tz::America__Los_Angeles,
+    },
+];
+
+impl Default for Region {
+    fn default() -> Self {
+        REGIONS[0]
+    }
+}
+
+impl FromStr for Region {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        REGIONS
+            .iter()
+            .find(|region| region.key.eq_ignore_ascii_case(s))
+            .copied()
+            .ok_or_else(|| {
+                let keys: Vec<_> = REGIONS.iter().map(|region| region.key).collect();
+                anyhow!("Unknown region {s:?}, expected one of: {}", keys.join(", "))
+            })
+    }
+}
+
+/// Loads the region last selected in the UI.
+pub fn load() -> Option<Region> {
+    storage::load(REGION_KEY)?.parse().log().ok()
+}
+
+pub fn store(region: Region) -> Result<()> {
+    storage::store(REGION_KEY, region.key)
+}
+
+/// Reloads the page so that a newly selected region is fetched. Returns false
+/// where that isn't possible, i.e. when running natively.
+#[cfg(target_arch = "wasm32")]
+pub fn reload() -> bool {
+    web_sys::window().is_some_and(|window| window.location().reload().is_ok())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn reload() -> bool {
+    false
+}
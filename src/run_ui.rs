@@ -1,38 +1,101 @@
 use std::collections::HashMap;
+use std::mem;
+use std::ops::RangeInclusive;
 use std::rc::Rc;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::sync::RwLock;
 
 use bitflags::bitflags;
 use chrono::offset::Local;
 use chrono::DateTime;
+use chrono::NaiveDateTime;
+use chrono::TimeDelta;
+use chrono::TimeZone;
+use chrono::Timelike;
+use chrono::Utc;
+use chrono_tz::Tz;
+use egui::pos2;
+use egui::show_tooltip_at_pointer;
+use egui::vec2;
 use egui::Align;
 use egui::Align2;
+use egui::Area;
+use egui::Button;
+use egui::CollapsingHeader;
+use egui::Color32;
+use egui::ComboBox;
 use egui::Context;
+use egui::DragValue;
+use egui::FontId;
+use egui::Frame;
 use egui::Hyperlink;
+use egui::Id;
 use egui::Layout;
+use egui::Pos2;
+use egui::Rect;
 use egui::ScrollArea;
+use egui::Sense;
+use egui::Shape;
 use egui::SidePanel;
 use egui::Slider;
+use egui::Stroke;
+use egui::Style;
+use egui::Ui;
+use egui::Vec2;
 use egui::Window;
 use egui_extras::Column;
 use egui_extras::TableBuilder;
+use galileo_types::cartesian::Point2d;
 use galileo_types::geo::impls::GeoPoint2d;
 use galileo_types::geo::GeoPoint;
 use ordered_float::OrderedFloat;
 use polars::prelude::*;
 use uom::fmt::DisplayStyle::Abbreviation;
-use uom::si::f64::Ratio;
+use uom::si::f64::Length;
 use uom::si::f64::Time;
+use uom::si::f64::Velocity;
+use uom::si::length::meter;
 use uom::si::length::mile;
 use uom::si::time::hour;
-use uom::si::time::minute;
+use uom::si::time::second;
 use uom::si::velocity::knot;
 
-use crate::saturating::Saturating;
+use crate::clock::Clock;
+use crate::coordinates::CoordinateSystem;
+use crate::export;
+use crate::features;
+use crate::features::Boat;
+use crate::features::CurrentColoring;
+use crate::features::Leg;
+use crate::features::Waypoint;
+use crate::features::WaypointSymbol;
+use crate::features::WaypointType;
+use crate::geolocation;
+use crate::geolocation::Location;
+use crate::noaa::RawResponseCache;
+use crate::noaa::Station;
+use crate::noaa::StationType;
+use crate::noaa::TideCache;
+use crate::prelude::*;
+use crate::regions;
+use crate::regions::Region;
+use crate::regions::REGIONS;
+use crate::scheduling::CurrentExtremum;
+use crate::scheduling::TideSpeedProfile;
 use crate::scheduling::Trip;
+use crate::scheduling::TripError;
+use crate::scheduling::TripResult;
+#[cfg(target_arch = "wasm32")]
+use crate::share::SharedPlan;
 use crate::state::galileo_state::GalileoState;
+use crate::state::no_stations_message;
 use crate::state::WaypointClickAction;
+use crate::storage;
+use crate::tasks;
+use crate::time_idx;
+use crate::time_idx::TimeIdx;
 
 bitflags! {
     #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -86,40 +149,577 @@ impl From<Weekdays> for WeekdayFlags {
     }
 }
 
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SweepView {
+    Table,
+    Heatmap,
+}
+
+/// Why a station's current predictions couldn't be loaded.
+#[derive(Clone, Debug)]
+pub enum StationFailure {
+    /// NOAA returned no predictions for the station.
+    NoPredictions,
+    /// The predictions couldn't be fetched or parsed.
+    Error(String),
+}
+
+impl StationFailure {
+    /// Classifies an error from fetching a station's predictions by the
+    /// messages of `Station::current_prediction`.
+    pub fn of(err: String) -> Self {
+        if err.starts_with("Missing current predictions")
+            || err.starts_with("Current predictions were empty")
+        {
+            StationFailure::NoPredictions
+        } else {
+            StationFailure::Error(err)
+        }
+    }
+}
+
 #[derive(Clone)]
+/// A replay of the trip departing at the 5 minute `departure`, which
+/// advances the selected time while `playing`.
+pub struct Replay {
+    pub departure: usize,
+    pub playing: bool,
+    /// When, in egui seconds, the time last advanced.
+    last_step: f64,
+    /// The trip's `track` from `departure`, as of the trip's `revision`.
+    track: Vec<(f64, f64)>,
+    trip_revision: u64,
+}
+
+/// Presentation mode, which hides the sidebar and advances the selected time
+/// at `UiState::presentation_speed`.
+pub struct Presentation {
+    /// When, in egui seconds, the time last advanced, or `None` before the
+    /// first frame.
+    last_step: Option<f64>,
+}
+
 pub struct UiState {
     pub pointer_position: Option<GeoPoint2d>,
-    pub time_idx: Arc<RwLock<Saturating<usize>>>,
+    pub time_idx: Arc<RwLock<TimeIdx>>,
+    /// The region's tide station, whose predictions set the time range.
+    pub battery: Station,
     pub battery_tide_predictions: DataFrame,
+    /// Rows of the high and low tides in `battery_tide_predictions`.
+    tide_events: Vec<(usize, Tide)>,
+    pub tide_cache: TideCache,
+    pub inspected_station: Option<Station>,
+    /// Set to show the inspected station's raw NOAA response for debugging.
+    pub raw_responses: Option<RawResponseCache>,
     pub galileo_state: Rc<RwLock<GalileoState>>,
     pub waypoint_mode: Arc<RwLock<WaypointClickAction>>,
+    /// Duration of newly placed pause waypoints.
+    pub pause_duration: Arc<RwLock<Time>>,
+    /// Whether removing waypoints removes every waypoint under the cursor
+    /// rather than just the nearest.
+    pub remove_all_waypoints: Arc<RwLock<bool>>,
+    /// Whether the arrow keys and time slider are disabled, so the arrow keys
+    /// move the selected waypoint without Shift.
+    pub lock_time: bool,
+    /// Whether times more than `planning_horizon_days` from now are hidden
+    /// from the time slider.
+    pub limit_planning_horizon: bool,
+    pub planning_horizon_days: f64,
     pub sweep_weekdays: Weekdays,
+    /// Whether the sweep only considers departures today, ignoring
+    /// `sweep_weekdays`.
+    pub sweep_today: bool,
     // TODO: get actual sunrise and sunset
     // TODO: make customizable
     // leave before 8am, arrive before 9pm
     pub daytime: bool,
+    pub limit_trip_duration: bool,
+    pub max_trip_hours: f64,
+    /// Whether to flag waypoints where the route turns by more than
+    /// `sharp_turn_degrees`.
+    pub warn_sharp_turns: bool,
+    pub sharp_turn_degrees: f64,
+    /// The tide speed profile being edited, kept while it's disabled.
+    pub tide_speed_profile: TideSpeedProfile,
+    pub sweep_view: SweepView,
+    pub confirm_clear_waypoints: bool,
+    pub coordinate_system: CoordinateSystem,
+    pub precision: Precision,
+    pub time_display: TimeDisplay,
+    /// Every route, where the active route's entry is a placeholder for the
+    /// one being edited in `trip`.
+    pub routes: Vec<Trip>,
+    pub active_route: usize,
+    pub show_north_arrow: bool,
+    pub show_legend: bool,
+    pub color_legs_by_speed: bool,
+    /// Whether legs follow the path integrated for the selected departure
+    /// rather than straight lines between waypoints.
+    pub show_integrated_path: bool,
+    /// Longest distance between drawn points of an integrated path, or `None`
+    /// to draw one point per integration step.
+    pub max_path_segment: Option<Length>,
+    shown_legs: Vec<Leg>,
+    /// Larger text and symbols and a high-contrast palette.
+    pub accessible: Arc<RwLock<bool>>,
+    applied_accessibility: Option<bool>,
+    /// How the current arrows are colored, shared with their symbol.
+    pub current_coloring: Arc<RwLock<CurrentColoring>>,
+    /// Index of the active route's waypoint selected with Tab, which Shift
+    /// and the arrow keys move and Delete removes.
+    pub selected_waypoint: Option<usize>,
+    /// Position of the selected waypoint, shared with the waypoint symbols,
+    /// which highlight it.
+    waypoint_highlight: Arc<RwLock<Option<Point2d>>>,
+    /// Whether to also draw the currents `compare_hours` from the selected
+    /// time, faded.
+    pub compare_times: bool,
+    pub compare_hours: f64,
+    /// How far ahead of now, in hours, the quick suggestion looks.
+    pub quick_suggestion_hours: f64,
+    /// The departure time index and trip duration found by the last quick
+    /// suggestion, or `None` if no departure was feasible.
+    pub quick_suggestion: Option<Option<(usize, Time)>>,
+    /// When the loaded predictions were fetched from NOAA.
+    pub data_fetched_at: DateTime<Utc>,
+    pub data_from_cache: bool,
+    /// Number of stations whose current predictions have been fetched, out of
+    /// the total, while fetches are outstanding.
+    pub current_predictions_loading: Option<(usize, usize)>,
+    /// Stations whose current predictions failed to load, and whether the UI
+    /// asked to fetch them again.
+    pub failed_stations: Vec<(Station, StationFailure)>,
+    pub retry_failed_stations: bool,
+    /// The display's scale factor, if it's far enough from 1 that the map's
+    /// symbols, which are sized in physical pixels, look tiny or huge next to
+    /// the controls.
+    pub unusual_scale_factor: Option<f64>,
+    /// The region's stations, including any whose predictions failed to load.
+    pub stations: Vec<Station>,
+    /// The region predictions were fetched for, and the one selected in the
+    /// UI, which is only fetched after a reload.
+    pub region: Region,
+    pub selected_region: Region,
+    /// Departure time typed in by the user, and why it couldn't be used.
+    pub time_input: String,
+    pub time_input_error: Option<String>,
+    /// Whether observed currents were fetched to replace predictions.
+    pub observed_currents: bool,
+    pub clock: Clock,
+    /// Latitude, longitude, and label of a waypoint to place by coordinates,
+    /// and why it couldn't be placed.
+    pub coordinate_input: (String, String),
+    pub coordinate_label: String,
+    pub coordinate_pause: bool,
+    pub coordinate_error: Option<String>,
+    /// The device location, set by the browser once a request completes.
+    location: Arc<RwLock<Option<Location>>>,
+    locating: bool,
+    pub locate_adds_waypoint: bool,
+    pub location_error: Option<String>,
+    /// Where and when, in egui seconds, the pointer came to rest, and whether
+    /// a redraw is scheduled for when the station tooltip is due.
+    hover_start: Option<(Pos2, f64)>,
+    hover_wake_pending: Arc<AtomicBool>,
+    /// The last shareable link copied, or why it couldn't be.
+    pub share_link: Option<Result<String, String>>,
+    pub replay: Option<Replay>,
+    replay_wake_pending: Arc<AtomicBool>,
+    pub presentation: Option<Presentation>,
+    /// Minutes the selected time advances per second while presenting.
+    pub presentation_speed: f64,
+    presentation_wake_pending: Arc<AtomicBool>,
+    /// Latitude and longitude of the drawn replay boat.
+    shown_boat: Option<(f64, f64)>,
+    /// The settings as configured at launch, restored by Reset Settings.
+    pub default_settings: Option<Settings>,
     trip: Arc<RwLock<Trip>>,
+    window: Arc<winit::window::Window>,
 }
 
 impl UiState {
     pub fn new(
-        time_idx: Arc<RwLock<Saturating<usize>>>,
+        time_idx: Arc<RwLock<TimeIdx>>,
+        battery: Station,
         battery_tide_predictions: DataFrame,
+        tide_cache: TideCache,
         waypoint_mode: Arc<RwLock<WaypointClickAction>>,
+        pause_duration: Arc<RwLock<Time>>,
+        remove_all_waypoints: Arc<RwLock<bool>>,
         trip: Arc<RwLock<Trip>>,
         galileo_state: Rc<RwLock<GalileoState>>,
+        window: Arc<winit::window::Window>,
+        coordinate_system: CoordinateSystem,
+        data_fetched_at: DateTime<Utc>,
+        data_from_cache: bool,
+        accessible: Arc<RwLock<bool>>,
+        current_coloring: Arc<RwLock<CurrentColoring>>,
+        waypoint_highlight: Arc<RwLock<Option<Point2d>>>,
+        stations: Vec<Station>,
+        region: Region,
+        observed_currents: bool,
+        clock: Clock,
     ) -> Self {
         Self {
             pointer_position: None,
             time_idx,
+            battery,
+            tide_events: tide_event_rows(&battery_tide_predictions),
             battery_tide_predictions,
+            tide_cache,
+            inspected_station: None,
+            raw_responses: None,
             galileo_state,
             waypoint_mode,
+            pause_duration,
+            remove_all_waypoints,
+            lock_time: false,
+            limit_planning_horizon: false,
+            planning_horizon_days: 14.0,
             sweep_weekdays: Weekdays::default(),
+            sweep_today: false,
             daytime: true,
+            limit_trip_duration: false,
+            max_trip_hours: 8.0,
+            warn_sharp_turns: false,
+            sharp_turn_degrees: 120.0,
+            tide_speed_profile: TideSpeedProfile::default(),
+            sweep_view: SweepView::Table,
+            confirm_clear_waypoints: false,
+            coordinate_system,
+            precision: Precision::default(),
+            time_display: TimeDisplay::new(region.time_zone),
+            show_north_arrow: true,
+            show_legend: true,
+            color_legs_by_speed: false,
+            show_integrated_path: false,
+            max_path_segment: None,
+            shown_legs: Vec::new(),
+            accessible,
+            applied_accessibility: None,
+            current_coloring,
+            selected_waypoint: None,
+            waypoint_highlight,
+            compare_times: false,
+            compare_hours: 3.0,
+            quick_suggestion_hours: 6.0,
+            quick_suggestion: None,
+            data_fetched_at,
+            data_from_cache,
+            current_predictions_loading: (!stations.is_empty()).then_some((0, stations.len())),
+            failed_stations: Vec::new(),
+            unusual_scale_factor: None,
+            retry_failed_stations: false,
+            stations,
+            region,
+            selected_region: region,
+            time_input: String::new(),
+            time_input_error: None,
+            observed_currents,
+            clock,
+            coordinate_input: (String::new(), String::new()),
+            coordinate_label: String::new(),
+            coordinate_pause: false,
+            coordinate_error: None,
+            location: Arc::new(RwLock::new(None)),
+            locating: false,
+            locate_adds_waypoint: false,
+            location_error: None,
+            hover_start: None,
+            hover_wake_pending: Arc::new(AtomicBool::new(false)),
+            share_link: None,
+            replay: None,
+            replay_wake_pending: Arc::new(AtomicBool::new(false)),
+            presentation: None,
+            presentation_speed: 10.0,
+            presentation_wake_pending: Arc::new(AtomicBool::new(false)),
+            shown_boat: None,
+            default_settings: None,
+            routes: vec![trip.read().unwrap().clone()],
+            active_route: 0,
             trip,
+            window,
+        }
+    }
+
+    /// Records the display's scale factor, warning if it's outside
+    /// `USUAL_SCALE_FACTORS`.
+    pub fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.unusual_scale_factor =
+            (!USUAL_SCALE_FACTORS.contains(&scale_factor)).then_some(scale_factor);
+        if self.unusual_scale_factor.is_some() {
+            warn!("Unusual display scale factor {scale_factor}; map symbols may be mis-sized");
+        }
+    }
+
+    /// Clears the selected waypoint if it was removed, and moves the highlight
+    /// to wherever the selected waypoint is now.
+    pub fn sync_selected_waypoint(&mut self) {
+        let trip = self.trip.read().unwrap();
+        let point = self
+            .selected_waypoint
+            .and_then(|idx| trip.waypoints.get(idx))
+            .map(|waypoint| waypoint.point);
+        if point.is_none() {
+            self.selected_waypoint = None;
+        }
+
+        let mut highlight = self.waypoint_highlight.write().unwrap();
+        if highlight.map(|p| (p.x, p.y)) != point.map(|p| (p.x, p.y)) {
+            *highlight = point;
+            // Waypoints are only re-rendered when they're inserted.
+            trip.reload_waypoint_layer();
+            self.galileo_state.read().unwrap().redraw();
+        }
+    }
+
+    /// Enters presentation mode, jumping to now, or leaves it.
+    pub fn toggle_presentation(&mut self) {
+        if self.presentation.take().is_some() {
+            return;
+        }
+        if let Ok(times) = self.battery_tide_predictions["time"].datetime() {
+            let time_vec = times.to_vec_null_aware().unwrap_left();
+            let now = self.clock.now_millis();
+            if let Some(idx) = time_idx::nearest_step(&time_vec, now) {
+                if self.time_idx.write().unwrap().set(idx) {
+                    self.galileo_state.read().unwrap().redraw_map();
+                }
+            }
+        }
+        self.presentation = Some(Presentation { last_step: None });
+    }
+}
+
+/// Decimal places shown for each kind of quantity.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Precision {
+    /// Distances, in miles.
+    pub distance: usize,
+    /// Durations, in hours.
+    pub time: usize,
+    /// Paddling and trip speeds, in knots.
+    pub speed: usize,
+    /// Station current speeds, in knots.
+    pub current: usize,
+    /// Latitudes and longitudes, in degrees.
+    pub coordinates: usize,
+}
+
+impl Default for Precision {
+    fn default() -> Self {
+        Self {
+            distance: 2,
+            time: 1,
+            speed: 1,
+            current: 2,
+            coordinates: 4,
+        }
+    }
+}
+
+impl Precision {
+    pub fn distance(&self, distance: Length) -> String {
+        format!(
+            "{:.*}",
+            self.distance,
+            distance.into_format_args(mile, Abbreviation)
+        )
+    }
+
+    pub fn time(&self, time: Time) -> String {
+        format!(
+            "{:.*}",
+            self.time,
+            time.into_format_args(hour, Abbreviation)
+        )
+    }
+
+    /// Formats a duration in seconds as compact hours, e.g. for table cells.
+    pub fn hours(&self, seconds: f64) -> String {
+        format!("{:.*}h", self.time, seconds / 3600.0)
+    }
+
+    pub fn speed(&self, speed: Velocity) -> String {
+        format!(
+            "{:.*}",
+            self.speed,
+            speed.into_format_args(knot, Abbreviation)
+        )
+    }
+
+    /// Formats a station's current speed in knots, without the unit.
+    pub fn current(&self, speed: f64) -> String {
+        format!("{:.*}", self.current, speed)
+    }
+}
+
+/// How times, which are in the region's local time like the predictions, are
+/// shown.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TimeDisplay {
+    /// Whether to show times in UTC rather than local time.
+    pub utc: bool,
+    time_zone: Tz,
+}
+
+impl TimeDisplay {
+    pub fn new(time_zone: Tz) -> Self {
+        Self {
+            utc: false,
+            time_zone,
         }
     }
+
+    /// Formats the local `time`, converted to UTC and marked as such if `utc`
+    /// is set. Times repeated when daylight saving time ends are taken as the
+    /// first.
+    pub fn format(&self, time: NaiveDateTime, format: &str) -> String {
+        let utc_time = self
+            .utc
+            .then(|| self.time_zone.from_local_datetime(&time).earliest())
+            .flatten();
+        match utc_time {
+            Some(utc_time) => format!("{} UTC", utc_time.naive_utc().format(format)),
+            None => time.format(format).to_string(),
+        }
+    }
+
+    /// Like `format`, for prediction times in milliseconds since the epoch.
+    pub fn format_millis(&self, millis: i64, format: &str) -> String {
+        self.format(
+            DateTime::from_timestamp_millis(millis).unwrap().naive_utc(),
+            format,
+        )
+    }
+}
+
+/// Settings of the UI and the trip, excluding the waypoints, region, and
+/// accessibility.
+#[derive(Clone)]
+pub struct Settings {
+    speed: Velocity,
+    snap_to_stations: bool,
+    subordinate_stations: bool,
+    tide_speed_profile: Option<TideSpeedProfile>,
+    edited_tide_speed_profile: TideSpeedProfile,
+    pause_duration: Time,
+    remove_all_waypoints: bool,
+    limit_planning_horizon: bool,
+    planning_horizon_days: f64,
+    sweep_weekdays: Weekdays,
+    sweep_today: bool,
+    daytime: bool,
+    limit_trip_duration: bool,
+    max_trip_hours: f64,
+    warn_sharp_turns: bool,
+    sharp_turn_degrees: f64,
+    sweep_view: SweepView,
+    coordinate_system: CoordinateSystem,
+    precision: Precision,
+    utc_times: bool,
+    show_north_arrow: bool,
+    show_legend: bool,
+    color_legs_by_speed: bool,
+    current_coloring: CurrentColoring,
+    compare_times: bool,
+    compare_hours: f64,
+    show_integrated_path: bool,
+    max_path_segment: Option<Length>,
+    quick_suggestion_hours: f64,
+    presentation_speed: f64,
+    locate_adds_waypoint: bool,
+}
+
+impl Settings {
+    pub fn of(state: &UiState) -> Self {
+        let trip = state.trip.read().unwrap();
+        Self {
+            speed: trip.speed,
+            snap_to_stations: trip.snap_to_stations,
+            subordinate_stations: trip.subordinate_stations,
+            tide_speed_profile: trip.tide_speed_profile,
+            edited_tide_speed_profile: state.tide_speed_profile,
+            pause_duration: *state.pause_duration.read().unwrap(),
+            remove_all_waypoints: *state.remove_all_waypoints.read().unwrap(),
+            limit_planning_horizon: state.limit_planning_horizon,
+            planning_horizon_days: state.planning_horizon_days,
+            sweep_weekdays: state.sweep_weekdays,
+            sweep_today: state.sweep_today,
+            daytime: state.daytime,
+            limit_trip_duration: state.limit_trip_duration,
+            max_trip_hours: state.max_trip_hours,
+            warn_sharp_turns: state.warn_sharp_turns,
+            sharp_turn_degrees: state.sharp_turn_degrees,
+            sweep_view: state.sweep_view,
+            coordinate_system: state.coordinate_system,
+            precision: state.precision,
+            utc_times: state.time_display.utc,
+            show_north_arrow: state.show_north_arrow,
+            show_legend: state.show_legend,
+            color_legs_by_speed: state.color_legs_by_speed,
+            current_coloring: *state.current_coloring.read().unwrap(),
+            compare_times: state.compare_times,
+            compare_hours: state.compare_hours,
+            show_integrated_path: state.show_integrated_path,
+            max_path_segment: state.max_path_segment,
+            quick_suggestion_hours: state.quick_suggestion_hours,
+            presentation_speed: state.presentation_speed,
+            locate_adds_waypoint: state.locate_adds_waypoint,
+        }
+    }
+
+    /// Applies the settings to `state` and its trip, whose cached results are
+    /// cleared as needed. The other routes copy the trip's settings when
+    /// they're next calculated.
+    pub fn restore(&self, state: &mut UiState) {
+        {
+            let mut trip = state.trip.write().unwrap();
+            if trip.speed != self.speed {
+                trip.set_speed(self.speed);
+            }
+            trip.snap_to_stations = self.snap_to_stations;
+            trip.set_subordinate_stations(self.subordinate_stations);
+            trip.set_tide_speed_profile(self.tide_speed_profile);
+            trip.set_weekdays(self.sweep_weekdays.into());
+            trip.set_sweep_date(self.sweep_today.then(|| state.clock.now().date()));
+            trip.set_daytime(self.daytime);
+            trip.set_max_trip_duration(
+                self.limit_trip_duration
+                    .then(|| Time::new::<hour>(self.max_trip_hours)),
+            );
+        }
+        state.tide_speed_profile = self.edited_tide_speed_profile;
+        *state.pause_duration.write().unwrap() = self.pause_duration;
+        *state.remove_all_waypoints.write().unwrap() = self.remove_all_waypoints;
+        state.limit_planning_horizon = self.limit_planning_horizon;
+        state.planning_horizon_days = self.planning_horizon_days;
+        state.sweep_weekdays = self.sweep_weekdays;
+        state.sweep_today = self.sweep_today;
+        state.daytime = self.daytime;
+        state.limit_trip_duration = self.limit_trip_duration;
+        state.max_trip_hours = self.max_trip_hours;
+        state.warn_sharp_turns = self.warn_sharp_turns;
+        state.sharp_turn_degrees = self.sharp_turn_degrees;
+        state.sweep_view = self.sweep_view;
+        state.coordinate_system = self.coordinate_system;
+        state.precision = self.precision;
+        state.time_display.utc = self.utc_times;
+        state.show_north_arrow = self.show_north_arrow;
+        state.show_legend = self.show_legend;
+        state.color_legs_by_speed = self.color_legs_by_speed;
+        set_current_coloring(state, self.current_coloring);
+        state.compare_times = self.compare_times;
+        state.compare_hours = self.compare_hours;
+        state.show_integrated_path = self.show_integrated_path;
+        state.max_path_segment = self.max_path_segment;
+        state.quick_suggestion_hours = self.quick_suggestion_hours;
+        state.presentation_speed = self.presentation_speed;
+        state.locate_adds_waypoint = self.locate_adds_waypoint;
+        // Found with the old settings.
+        state.quick_suggestion = None;
+        state.galileo_state.read().unwrap().redraw();
+    }
 }
 
 fn degree_to_cardinal_direction(heading: f64) -> String {
@@ -145,7 +745,527 @@ fn degree_to_cardinal_direction(heading: f64) -> String {
     mapping[&rounded].to_string()
 }
 
+/// Scale factors at which the map's symbols and the controls are both usable.
+/// Phones commonly use up to 3.
+const USUAL_SCALE_FACTORS: RangeInclusive<f64> = 0.75..=3.0;
+
+/// Age past which the loaded predictions are shown as stale.
+const STALE_DATA_HOURS: i64 = 24;
+
+/// Parses decimal degree latitude and longitude, or returns an error if they
+/// are outside `region`.
+fn parse_coordinate_input(lat: &str, lon: &str, region: &Region) -> Result<(f64, f64)> {
+    let parse = |input: &str, name: &str| {
+        input
+            .trim()
+            .parse::<f64>()
+            .map_err(|_| anyhow!("Expected a {name} in decimal degrees, like 40.7"))
+    };
+    let (lat, lon) = (parse(lat, "latitude")?, parse(lon, "longitude")?);
+
+    let within = |value: f64, (a, b): (f64, f64)| a.min(b) <= value && value <= a.max(b);
+    if !within(lat, region.lat_range) || !within(lon, region.lon_range) {
+        return Err(anyhow!(
+            "({lat}, {lon}) is outside {}, which spans latitudes {} to {} and longitudes {} to {}",
+            region.name,
+            region.lat_range.0,
+            region.lat_range.1,
+            region.lon_range.0,
+            region.lon_range.1,
+        ));
+    }
+
+    Ok((lat, lon))
+}
+
+/// Parses a "YYYY-MM-DD HH:MM" time and returns the index of the nearest 5
+/// minute step, or an error if it is outside `time_vec`'s range.
+fn parse_time_input(input: &str, time_vec: &[i64]) -> Result<usize> {
+    let time = NaiveDateTime::parse_from_str(input.trim(), "%Y-%m-%d %H:%M")
+        .map_err(|err| anyhow!("Expected a time like 2024-07-14 06:30: {err}"))?
+        .and_utc()
+        .timestamp_millis();
+
+    let (Some(first), Some(last)) = (time_vec.first(), time_vec.last()) else {
+        return Err(anyhow!("No predictions are loaded"));
+    };
+    // Same range check as `Trip::plan_at`, so a time accepted here plans.
+    time_idx::nearest_step_in_range(time_vec, time).ok_or_else(|| {
+        let format = |ts: i64| {
+            DateTime::from_timestamp_millis(ts)
+                .unwrap()
+                .naive_utc()
+                .format("%Y-%m-%d %H:%M")
+        };
+        anyhow!(
+            "{} is outside the loaded predictions, {} to {}",
+            input.trim(),
+            format(*first),
+            format(*last)
+        )
+    })
+}
+
+/// Index of the first time in `time_vec` that isn't before `clock`'s current
+/// time, or `time_vec.len()` if they all are.
+fn now_time_idx(time_vec: &[i64], clock: &Clock) -> usize {
+    let now = clock.now_millis();
+    time_vec.partition_point(|t| *t < now)
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Tide {
+    High,
+    Low,
+}
+
+/// Rows of the high and low tides in `tide_predictions`, whose `high_low` is
+/// "H" or "L" on the 30 minute row each tide was rounded to.
+fn tide_event_rows(tide_predictions: &DataFrame) -> Vec<(usize, Tide)> {
+    let Ok(high_low) = tide_predictions["high_low"].str() else {
+        return Vec::new();
+    };
+    high_low
+        .into_iter()
+        .enumerate()
+        .filter_map(|(row, high_low)| match high_low? {
+            "H" => Some((row, Tide::High)),
+            "L" => Some((row, Tide::Low)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The 5 minute step of the first `tide` after `idx`, or the last one before
+/// it.
+fn adjacent_tide(events: &[(usize, Tide)], idx: usize, tide: Tide, forward: bool) -> Option<usize> {
+    let mut steps = events
+        .iter()
+        .filter(|(_, t)| *t == tide)
+        .map(|(row, _)| time_idx::row_to_step(*row));
+    if forward {
+        steps.find(|step| *step > idx)
+    } else {
+        steps.filter(|step| *step < idx).last()
+    }
+}
+
+/// The 5 minute step of the high or low tide nearest `idx`, preferring the
+/// earlier one on a tie.
+fn nearest_tide(events: &[(usize, Tide)], idx: usize) -> Option<usize> {
+    events
+        .iter()
+        .map(|(row, _)| time_idx::row_to_step(*row))
+        .min_by_key(|step| step.abs_diff(idx))
+}
+
+const HEATMAP_CELL_SIZE: Vec2 = Vec2::new(6.0, 10.0);
+const HEATMAP_LABEL_WIDTH: f32 = 72.0;
+const HEATMAP_CELLS_PER_DAY: usize = 48;
+
+/// Draws the sweep results as a grid of days by time of day, colored from green
+/// (fastest) to red (slowest) by trip duration, with departures filtered out of
+/// the sweep greyed. Returns the time index of the clicked cell, if any.
+fn departure_heatmap(
+    ui: &mut Ui,
+    time_vec: &[i64],
+    sweep_idx_vec: &[u64],
+    sweep_duration_vec: &[f64],
+    precision: Precision,
+    time_display: TimeDisplay,
+) -> Option<usize> {
+    let times: Vec<NaiveDateTime> = time_vec
+        .iter()
+        .map(|ts| DateTime::from_timestamp_millis(*ts).unwrap().naive_utc())
+        .collect();
+    let first_date = times.first()?.date();
+    let num_days = (times.last()?.date() - first_date).num_days() as usize + 1;
+
+    let durations: HashMap<usize, f64> = sweep_idx_vec
+        .iter()
+        .map(|idx| *idx as usize)
+        .zip(sweep_duration_vec.iter().copied())
+        .collect();
+    let (min_duration, max_duration) = sweep_duration_vec.iter().fold(
+        (f64::INFINITY, f64::NEG_INFINITY),
+        |(min, max), duration| (min.min(*duration), max.max(*duration)),
+    );
+
+    let cell = |dt: &NaiveDateTime| {
+        (
+            (dt.date() - first_date).num_days() as usize,
+            (dt.hour() * 2 + dt.minute() / 30) as usize,
+        )
+    };
+
+    let (rect, response) = ui.allocate_exact_size(
+        vec2(
+            HEATMAP_LABEL_WIDTH + HEATMAP_CELL_SIZE.x * HEATMAP_CELLS_PER_DAY as f32,
+            HEATMAP_CELL_SIZE.y * num_days as f32,
+        ),
+        Sense::click(),
+    );
+    let painter = ui.painter_at(rect);
+
+    for (row, date) in first_date.iter_days().take(num_days).enumerate() {
+        painter.text(
+            rect.min + vec2(0.0, (row as f32 + 0.5) * HEATMAP_CELL_SIZE.y),
+            Align2::LEFT_CENTER,
+            date.format("%a %m-%d").to_string(),
+            FontId::monospace(HEATMAP_CELL_SIZE.y - 1.0),
+            ui.visuals().text_color(),
+        );
+    }
+
+    for (idx, dt) in times.iter().enumerate() {
+        let (row, col) = cell(dt);
+        let color = match durations.get(&idx) {
+            Some(duration) if max_duration > min_duration => {
+                let t = ((duration - min_duration) / (max_duration - min_duration)) as f32;
+                Color32::from_rgb((255.0 * t) as u8, (255.0 * (1.0 - t)) as u8, 0)
+            },
+            Some(_) => Color32::GREEN,
+            None => Color32::DARK_GRAY,
+        };
+        let cell_rect = Rect::from_min_size(
+            rect.min
+                + vec2(
+                    HEATMAP_LABEL_WIDTH + col as f32 * HEATMAP_CELL_SIZE.x,
+                    row as f32 * HEATMAP_CELL_SIZE.y,
+                ),
+            HEATMAP_CELL_SIZE,
+        );
+        painter.rect_filled(cell_rect.shrink(0.5), 0.0, color);
+    }
+
+    let pointer_idx = |pos: Pos2| {
+        let x = pos.x - rect.min.x - HEATMAP_LABEL_WIDTH;
+        if x < 0.0 {
+            return None;
+        }
+        let row = ((pos.y - rect.min.y) / HEATMAP_CELL_SIZE.y) as usize;
+        let col = (x / HEATMAP_CELL_SIZE.x) as usize;
+        let time = (first_date + TimeDelta::days(row as i64))
+            .and_hms_opt(0, 0, 0)?
+            .and_utc()
+            .timestamp_millis()
+            + col as i64 * time_idx::ROW_MILLIS;
+        time_idx::nearest_step(time_vec, time)
+            .map(|step| step / time_idx::STEPS_PER_ROW)
+            .filter(|idx| cell(&times[*idx]) == (row, col))
+    };
+
+    let clicked_idx = if response.clicked() {
+        response.interact_pointer_pos().and_then(pointer_idx)
+    } else {
+        None
+    };
+
+    if let Some(idx) = response.hover_pos().and_then(pointer_idx) {
+        let time_str = time_display.format(times[idx], "%a %Y-%m-%d %H:%M");
+        response.on_hover_text(match durations.get(&idx) {
+            Some(duration) => format!("{}: {}", time_str, precision.hours(*duration)),
+            None => time_str,
+        });
+    }
+
+    clicked_idx
+}
+
+/// Draws the trip's totals, arrival time, and feasibility on one line.
+fn summary_bar(
+    ui: &mut Ui,
+    trip_result: &Result<TripResult, TripError>,
+    start_time: Option<NaiveDateTime>,
+    precision: Precision,
+    time_display: TimeDisplay,
+) {
+    ui.horizontal(|ui| match trip_result {
+        Ok(trip_result) => {
+            ui.colored_label(Color32::GREEN, "Feasible");
+            ui.label(format!(
+                "{}, {} moving, {} total",
+                precision.distance(trip_result.distance()),
+                precision.time(trip_result.moving_time()),
+                precision.time(trip_result.time()),
+            ));
+            if let Some(average_speed) = trip_result.average_speed() {
+                ui.label(format!("avg {}", precision.speed(average_speed)));
+            }
+            if let Some(start_time) = start_time {
+                let duration = TimeDelta::seconds(trip_result.time().get::<second>() as i64);
+                ui.label(format!(
+                    "ETA {}",
+                    time_display.format(start_time + duration, "%a %H:%M")
+                ));
+            }
+        },
+        Err(err) => {
+            ui.colored_label(Color32::RED, format!("Infeasible: {err}"));
+        },
+    });
+}
+
+const SCALE_BAR_MAX_WIDTH: f32 = 120.0;
+
+/// Draws a scale bar for a map scale of `meters_per_point`, sized to the
+/// longest 1, 2, or 5 times a power of ten miles that fits in
+/// `SCALE_BAR_MAX_WIDTH`.
+fn scale_bar(ui: &mut Ui, meters_per_point: f64) {
+    let max_miles =
+        Length::new::<meter>(meters_per_point * SCALE_BAR_MAX_WIDTH as f64).get::<mile>();
+    let magnitude = 10f64.powf(max_miles.log10().floor());
+    let miles = [5.0, 2.0, 1.0]
+        .into_iter()
+        .map(|m| m * magnitude)
+        .find(|m| *m <= max_miles)
+        .unwrap_or(magnitude);
+    let width = (Length::new::<mile>(miles).get::<meter>() / meters_per_point) as f32;
+
+    let (rect, _) = ui.allocate_exact_size(vec2(width, 6.0), Sense::hover());
+    let painter = ui.painter();
+    let stroke = Stroke::new(2.0, ui.visuals().text_color());
+    painter.line_segment([rect.left_bottom(), rect.right_bottom()], stroke);
+    painter.line_segment([rect.left_top(), rect.left_bottom()], stroke);
+    painter.line_segment([rect.right_top(), rect.right_bottom()], stroke);
+    ui.label(format!("{miles} mi"));
+}
+
+const NORTH_ARROW_SIZE: f32 = 32.0;
+
+/// Draws an arrow pointing north on a map rotated by `rotation` radians.
+/// Returns whether it was clicked.
+fn north_arrow(ui: &mut Ui, rotation: f64) -> bool {
+    let (rect, response) = ui.allocate_exact_size(Vec2::splat(NORTH_ARROW_SIZE), Sense::click());
+    let painter = ui.painter();
+    let center = rect.center();
+    let radius = NORTH_ARROW_SIZE / 2.0;
+    let rotate = |v: Vec2| Vec2::angled(v.angle() + rotation as f32) * v.length();
+    let text_color = ui.visuals().text_color();
+
+    painter.add(Shape::convex_polygon(
+        vec![
+            center + rotate(vec2(0.0, -radius * 0.3)),
+            center + rotate(vec2(radius * 0.35, radius * 0.9)),
+            center + rotate(vec2(-radius * 0.35, radius * 0.9)),
+        ],
+        Color32::RED,
+        Stroke::new(1.0, text_color),
+    ));
+    painter.text(
+        center + rotate(vec2(0.0, -radius * 0.7)),
+        Align2::CENTER_CENTER,
+        "N",
+        FontId::proportional(10.0),
+        text_color,
+    );
+
+    response.on_hover_text("Reset to north-up").clicked()
+}
+
+const LEGEND_ICON_SIZE: f32 = 14.0;
+const LEGEND_GRADIENT_WIDTH: f32 = 96.0;
+
+/// Draws sample waypoint and current arrow symbols in the map's colors.
+fn legend(
+    ui: &mut Ui,
+    accessible: bool,
+    color_legs_by_speed: bool,
+    current_coloring: CurrentColoring,
+    compare_hours: Option<f64>,
+    observed_currents: bool,
+) {
+    let to_color32 = |(r, g, b)| Color32::from_rgb(r, g, b);
+
+    for (waypoint_type, label) in [
+        (WaypointType::Move, "Move waypoint"),
+        (WaypointType::Pause(Time::default()), "Pause waypoint"),
+    ] {
+        ui.horizontal(|ui| {
+            let (rect, _) = ui.allocate_exact_size(Vec2::splat(LEGEND_ICON_SIZE), Sense::hover());
+            ui.painter().circle(
+                rect.center(),
+                LEGEND_ICON_SIZE / 2.0 - 1.0,
+                to_color32(features::waypoint_color(waypoint_type, accessible)),
+                Stroke::new(2.0, Color32::BLACK),
+            );
+            ui.label(label);
+        });
+    }
+
+    let mut current_colors = vec![];
+    if current_coloring == CurrentColoring::StationType {
+        current_colors.push((
+            features::station_color(StationType::Harmonic),
+            "Harmonic station current",
+        ));
+        current_colors.push((
+            features::station_color(StationType::Subordinate),
+            "Subordinate station current",
+        ));
+        if observed_currents {
+            current_colors.push((features::OBSERVED_CURRENT_COLOR, "Observed current"));
+        }
+    }
+    for (color, label) in current_colors {
+        ui.horizontal(|ui| {
+            let (rect, _) = ui.allocate_exact_size(Vec2::splat(LEGEND_ICON_SIZE), Sense::hover());
+            ui.painter().add(Shape::convex_polygon(
+                vec![rect.center_top(), rect.right_bottom(), rect.left_bottom()],
+                to_color32(color),
+                Stroke::NONE,
+            ));
+            ui.label(label);
+        });
+    }
+
+    ui.label("Arrow length is proportional to speed.");
+    if let Some(compare_hours) = compare_hours {
+        ui.label(format!(
+            "Faded arrows are the currents {compare_hours:+} h from the selected time."
+        ));
+    }
+
+    if current_coloring == CurrentColoring::Speed {
+        ui.label("Current speed:");
+        ui.horizontal(|ui| {
+            ui.label("0 kt");
+            let (rect, _) = ui.allocate_exact_size(
+                vec2(LEGEND_GRADIENT_WIDTH, LEGEND_ICON_SIZE),
+                Sense::hover(),
+            );
+            let steps = LEGEND_GRADIENT_WIDTH as usize / 2;
+            for i in 0..steps {
+                let t = i as f64 / (steps - 1) as f64;
+                let color = features::current_speed_color(t * features::CURRENT_COLOR_MAX_KNOTS);
+                let x = rect.min.x + i as f32 * 2.0;
+                ui.painter().rect_filled(
+                    Rect::from_min_max(pos2(x, rect.min.y), pos2(x + 2.0, rect.max.y)),
+                    0.0,
+                    to_color32(color),
+                );
+            }
+            ui.label(format!("{} kt+", features::CURRENT_COLOR_MAX_KNOTS));
+        });
+    }
+
+    if color_legs_by_speed {
+        ui.label("Leg speed, relative to paddling speed:");
+        ui.horizontal(|ui| {
+            ui.label("50%");
+            let (rect, _) = ui.allocate_exact_size(
+                vec2(LEGEND_GRADIENT_WIDTH, LEGEND_ICON_SIZE),
+                Sense::hover(),
+            );
+            let steps = LEGEND_GRADIENT_WIDTH as usize / 2;
+            for i in 0..steps {
+                let t = i as f64 / (steps - 1) as f64;
+                let speed = Velocity::new::<knot>(0.5 + t);
+                let color = features::leg_speed_color(speed, Velocity::new::<knot>(1.0));
+                let x = rect.min.x + i as f32 * 2.0;
+                ui.painter().rect_filled(
+                    Rect::from_min_max(pos2(x, rect.min.y), pos2(x + 2.0, rect.max.y)),
+                    0.0,
+                    to_color32(color),
+                );
+            }
+            ui.label("150%");
+        });
+    }
+}
+
+/// Sets how the current arrows are colored, redrawing them if it changed.
+fn set_current_coloring(state: &UiState, current_coloring: CurrentColoring) {
+    let mut coloring = state.current_coloring.write().unwrap();
+    if *coloring != current_coloring {
+        *coloring = current_coloring;
+        drop(coloring);
+        // Symbols are only re-rendered when their features are re-inserted.
+        state.galileo_state.read().unwrap().rebuild_arrows();
+    }
+}
+
+const ACCESSIBILITY_KEY: &str = "accessibility";
+const ACCESSIBLE_TEXT_SCALE: f32 = 1.4;
+
+#[cfg(target_arch = "wasm32")]
+fn prefers_high_contrast() -> bool {
+    web_sys::window()
+        .and_then(|window| window.match_media("(prefers-contrast: more)").ok()?)
+        .is_some_and(|query| query.matches())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn prefers_high_contrast() -> bool {
+    false
+}
+
+/// Loads the saved accessibility setting, defaulting to the browser's contrast
+/// preference.
+pub fn load_accessibility() -> bool {
+    storage::load(ACCESSIBILITY_KEY)
+        .and_then(|accessible| accessible.parse().ok())
+        .unwrap_or_else(prefers_high_contrast)
+}
+
+/// Enlarges text and switches to a high-contrast palette, or restores the
+/// default style.
+fn apply_accessibility(ctx: &Context, accessible: bool) {
+    let mut style = Style::default();
+    if accessible {
+        for font_id in style.text_styles.values_mut() {
+            font_id.size *= ACCESSIBLE_TEXT_SCALE;
+        }
+        style.visuals.override_text_color = Some(Color32::WHITE);
+        style.visuals.panel_fill = Color32::BLACK;
+        style.visuals.window_fill = Color32::BLACK;
+        style.visuals.widgets.noninteractive.bg_stroke.color = Color32::WHITE;
+    }
+    ctx.set_style(style);
+}
+
 pub fn run_ui(state: &mut UiState, ui: &Context) {
+    let accessible = *state.accessible.read().unwrap();
+    if state.applied_accessibility != Some(accessible) {
+        apply_accessibility(ui, accessible);
+        // Symbols are only re-rendered when their features are re-inserted.
+        if state.applied_accessibility.is_some() {
+            let _ = storage::store(ACCESSIBILITY_KEY, &accessible.to_string());
+            state.trip.read().unwrap().reload_waypoint_layer();
+            for (i, route) in state.routes.iter().enumerate() {
+                if i != state.active_route {
+                    route.reload_waypoint_layer();
+                }
+            }
+            state.galileo_state.read().unwrap().rebuild_arrows();
+        }
+        state.applied_accessibility = Some(accessible);
+    }
+
+    // 30 minute rows.
+    state
+        .galileo_state
+        .read()
+        .unwrap()
+        .set_compare_rows(state.compare_times.then_some(state.compare_hours * 2.0));
+
+    let location = state.location.write().unwrap().take();
+    if let Some(location) = location {
+        state.locating = false;
+        match location {
+            Ok((lat, lon)) => {
+                state.galileo_state.write().unwrap().center_on(lat, lon);
+                if state.locate_adds_waypoint {
+                    if let Some(waypoint) = Waypoint::at(lat, lon, WaypointType::Move) {
+                        state.trip.write().unwrap().add_waypoint(waypoint);
+                    }
+                }
+                state.galileo_state.read().unwrap().redraw();
+            },
+            Err(err) => state.location_error = Some(err),
+        }
+    }
+
     // TODO: is this too long?
     let time_vec = state.battery_tide_predictions["time"]
         .datetime()
@@ -160,13 +1280,42 @@ pub fn run_ui(state: &mut UiState, ui: &Context) {
             ui.spacing_mut().button_padding = (30.0, 10.00).into();
             ui.label("Time");
             ui.with_layout(Layout::left_to_right(Align::Min), |ui| {
-                if ui.button("⬅").clicked() && state.time_idx.write().unwrap().dec() {
-                    state.galileo_state.read().unwrap().redraw_map();
-                }
-                if ui.button("➡").clicked() && state.time_idx.write().unwrap().inc() {
-                    state.galileo_state.read().unwrap().redraw_map();
+                let by_row = ui.input(|i| i.modifiers.ctrl);
+                let locked = state.lock_time;
+                for (label, forward) in [("⬅", false), ("➡", true)] {
+                    if ui
+                        .add_enabled(!locked, Button::new(label))
+                        .on_hover_text("Steps 5 minutes, or 30 with Ctrl held.")
+                        .clicked()
+                    {
+                        let mut time_idx = state.time_idx.write().unwrap();
+                        let changed = if by_row {
+                            time_idx.step_row(forward)
+                        } else {
+                            time_idx.step(forward)
+                        };
+                        drop(time_idx);
+                        if changed {
+                            state.galileo_state.read().unwrap().redraw_map();
+                        }
+                    }
                 }
+                let lock_label = if locked { "🔒 Locked" } else { "🔓" };
+                ui.toggle_value(&mut state.lock_time, lock_label)
+                    .on_hover_text("Locks the time while editing waypoints. The arrow keys then move the selected waypoint.");
             });
+            let present_label = if state.presentation.is_some() {
+                "Stop Presenting (P)"
+            } else {
+                "Present (P)"
+            };
+            if ui
+                .button(present_label)
+                .on_hover_text("Jumps to now and advances the time, hiding the sidebar, to show the currents to others.")
+                .clicked()
+            {
+                state.toggle_presentation();
+            }
 
             ui.separator();
 
@@ -176,25 +1325,370 @@ pub fn run_ui(state: &mut UiState, ui: &Context) {
                 ui.selectable_value(
                     &mut *waypoint_mode,
                     WaypointClickAction::Move,
-                    "Move"
+                    "Move (1)"
                 );
                 ui.selectable_value(
                     &mut *waypoint_mode,
                     WaypointClickAction::Pause,
-                    "Pause"
+                    "Pause (2)"
                 );
                 ui.selectable_value(
                     &mut *waypoint_mode,
                     WaypointClickAction::Remove,
-                    "Remove"
+                    "Remove (3)"
                 );
             });
+            ui.with_layout(Layout::left_to_right(Align::Min), |ui| {
+                ui.label("Pause duration");
+                let mut hours = state.pause_duration.read().unwrap().get::<hour>();
+                if ui
+                    .add(
+                        DragValue::new(&mut hours)
+                            .clamp_range(0.0..=12.0)
+                            .speed(0.25)
+                            .suffix("h"),
+                    )
+                    .changed()
+                {
+                    *state.pause_duration.write().unwrap() = Time::new::<hour>(hours);
+                }
+            });
+            ui.checkbox(
+                &mut state.remove_all_waypoints.write().unwrap(),
+                "Remove every waypoint under the cursor",
+            )
+            .on_hover_text("By default, only the nearest waypoint under the cursor is removed, e.g. one of several stacked pauses.");
 
             ui.separator();
 
+            {
+                let mut trip = state.trip.write().unwrap();
+                let label = format!(
+                    "Snap new waypoints to within {:.1} of a current station",
+                    trip.max_station_distance
+                        .into_format_args(mile, Abbreviation)
+                );
+                ui.checkbox(&mut trip.snap_to_stations, label);
+                let mut subordinate_stations = trip.subordinate_stations;
+                if ui
+                    .checkbox(&mut subordinate_stations, "Use currents from subordinate stations")
+                    .on_hover_text("Subordinate stations' predictions are less accurate. When unchecked, trips sample currents only from harmonic stations, though subordinate stations' arrows are still shown.")
+                    .changed()
+                {
+                    trip.set_subordinate_stations(subordinate_stations);
+                }
+                ui.collapsing("Tide height speed (experimental)", |ui| {
+                    if trip.tide_heights.is_none() {
+                        ui.label("Tide heights are unavailable.");
+                        return;
+                    }
+                    let profile = &mut state.tide_speed_profile;
+                    let mut enabled = trip.tide_speed_profile.is_some();
+                    let mut changed = ui
+                        .checkbox(&mut enabled, "Slow down at low tide")
+                        .on_hover_text("Experimental. Scales the paddling speed by the height at the region's tide station, for routes through shallows. The tide station may be far from the shallows, so treat the results as rough.")
+                        .changed();
+                    ui.add_enabled_ui(enabled, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label("Slowest at or below");
+                            changed |= ui
+                                .add(
+                                    DragValue::new(&mut profile.low)
+                                        .clamp_range(-5.0..=profile.high - 0.1)
+                                        .speed(0.1)
+                                        .suffix(" ft"),
+                                )
+                                .changed();
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Full speed at or above");
+                            changed |= ui
+                                .add(
+                                    DragValue::new(&mut profile.high)
+                                        .clamp_range(profile.low + 0.1..=20.0)
+                                        .speed(0.1)
+                                        .suffix(" ft"),
+                                )
+                                .changed();
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Slowest speed");
+                            let mut percent = profile.min_factor * 100.0;
+                            if ui
+                                .add(
+                                    DragValue::new(&mut percent)
+                                        .clamp_range(0.0..=100.0)
+                                        .speed(1.0)
+                                        .suffix("%"),
+                                )
+                                .changed()
+                            {
+                                profile.min_factor = percent / 100.0;
+                                changed = true;
+                            }
+                        });
+                    });
+                    if changed {
+                        trip.set_tide_speed_profile(enabled.then_some(*profile));
+                    }
+                });
+                if let Some(warning) = &trip.waypoint_warning {
+                    ui.colored_label(Color32::YELLOW, warning);
+                }
+            }
+
+            ui.label("Place a waypoint at:");
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut state.coordinate_input.0)
+                        .hint_text("Latitude")
+                        .desired_width(80.0),
+                );
+                ui.add(
+                    egui::TextEdit::singleline(&mut state.coordinate_input.1)
+                        .hint_text("Longitude")
+                        .desired_width(80.0),
+                );
+            });
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut state.coordinate_label)
+                        .hint_text("Label (optional)")
+                        .desired_width(120.0),
+                );
+                ui.checkbox(&mut state.coordinate_pause, "Pause");
+                if ui.button("Place").clicked() {
+                    let (lat, lon) = &state.coordinate_input;
+                    let waypoint_type = if state.coordinate_pause {
+                        WaypointType::Pause(*state.pause_duration.read().unwrap())
+                    } else {
+                        WaypointType::Move
+                    };
+                    let waypoint = parse_coordinate_input(lat, lon, &state.region).and_then(
+                        |(lat, lon)| {
+                            Waypoint::at(lat, lon, waypoint_type)
+                                .ok_or_else(|| anyhow!("Couldn't project ({lat}, {lon})"))
+                        },
+                    );
+                    state.coordinate_error = match waypoint {
+                        Ok(mut waypoint) => {
+                            let label = state.coordinate_label.trim();
+                            waypoint.label = (!label.is_empty()).then(|| label.to_string());
+                            state.trip.write().unwrap().add_waypoint(waypoint);
+                            state.galileo_state.read().unwrap().redraw();
+                            None
+                        },
+                        Err(err) => Some(err.to_string()),
+                    };
+                }
+            });
+            if let Some(err) = &state.coordinate_error {
+                ui.colored_label(Color32::RED, err);
+            }
+
+            let extendable = state.trip.read().unwrap().waypoints.len() > 1;
+            if ui
+                .add_enabled(extendable, Button::new("Extend Route"))
+                .on_hover_text("Repeat the last leg's bearing and distance (E)")
+                .clicked()
+                && state.trip.write().unwrap().extend_route()
+            {
+                state.galileo_state.read().unwrap().redraw();
+            }
+
             if ui.button("Clear Waypoints").clicked() {
-                state.trip.write().unwrap().clear_waypoints();
+                state.confirm_clear_waypoints = true;
+            }
+
+            if let Some(settings) = state.default_settings.clone() {
+                if ui
+                    .button("Reset Settings")
+                    .on_hover_text("Restores the settings this session started with, keeping the waypoints.")
+                    .clicked()
+                {
+                    settings.restore(state);
+                }
+            }
+
+            if ui.button("Export Plan (JSON)").clicked() {
+                let time_idx = state.time_idx.read().unwrap().val();
+                let plan = state
+                    .trip
+                    .write()
+                    .unwrap()
+                    .to_plan_json(time_idx, state.coordinate_system);
+                let _ = export::save(
+                    "kayaknav_plan.json",
+                    &format!("{plan:#}"),
+                    "application/json",
+                );
+            }
+
+            if ui.button("Export Currents (GeoJSON)").clicked() {
+                let time_idx = state.time_idx.read().unwrap().row();
+                let currents = state.trip.read().unwrap().currents_geojson(time_idx);
+                let _ = export::save(
+                    "kayaknav_currents.geojson",
+                    &format!("{currents:#}"),
+                    "application/geo+json",
+                );
+            }
+
+            if ui
+                .button("Export Current Field (CSV)")
+                .on_hover_text("Save every station's speed and direction over the whole prediction range, one column pair per station, aligned to a common time grid.")
+                .clicked()
+            {
+                let field = state.trip.read().unwrap().current_field_dataframe();
+                if let Ok(csv) = field.and_then(|mut field| export::csv(&mut field)).log() {
+                    let _ = export::save("kayaknav_current_field.csv", &csv, "text/csv");
+                }
+            }
+
+            if ui
+                .button("Export Stations (JSON)")
+                .on_hover_text("Save the region's stations, e.g. to curate and load with --stations-file.")
+                .clicked()
+            {
+                let stations = Station::list_to_json(&state.stations);
+                let _ = export::save(
+                    "kayaknav_stations.json",
+                    &format!("{stations:#}"),
+                    "application/json",
+                );
+            }
+
+            #[cfg(target_arch = "wasm32")]
+            if ui
+                .button("Copy Link")
+                .on_hover_text("Copy a link that opens this plan.")
+                .clicked()
+            {
+                let trip = state.trip.read().unwrap();
+                let departure = trip.time_at_5m(state.time_idx.read().unwrap().val());
+                let link = SharedPlan::new(&trip, state.region, departure).link();
+                drop(trip);
+                state.share_link = Some(
+                    link.and_then(|link| {
+                        export::copy_to_clipboard(&link)?;
+                        Ok(link)
+                    })
+                    .map_err(|err| err.to_string()),
+                );
             }
+            match &state.share_link {
+                Some(Ok(link)) => {
+                    ui.label("Copied:");
+                    let mut link = link.as_str();
+                    ui.add(egui::TextEdit::singleline(&mut link));
+                },
+                Some(Err(err)) => {
+                    ui.colored_label(Color32::RED, err);
+                },
+                None => {},
+            }
+
+            ui.separator();
+
+            let previous_region = state.selected_region;
+            ComboBox::from_label("Region")
+                .selected_text(state.selected_region.name)
+                .show_ui(ui, |ui| {
+                    for region in REGIONS {
+                        ui.selectable_value(&mut state.selected_region, *region, region.name);
+                    }
+                });
+            if state.selected_region != previous_region {
+                let _ = regions::store(state.selected_region);
+                if state.selected_region != state.region {
+                    regions::reload();
+                }
+            }
+            if state.selected_region != state.region {
+                ui.label(format!(
+                    "Restart KayakNav to load {}.",
+                    state.selected_region.name
+                ));
+            }
+
+            if ui.button("Reset View").clicked() {
+                state.galileo_state.write().unwrap().reset_view();
+            }
+            if ui
+                .button("Center on Tide Station")
+                .on_hover_text(&state.battery.name)
+                .clicked()
+            {
+                let (lat, lon) = (state.battery.loc.lat(), state.battery.loc.lon());
+                state.galileo_state.write().unwrap().center_on(lat, lon);
+            }
+            ui.horizontal(|ui| {
+                if ui.add_enabled(!state.locating, Button::new("Locate Me")).clicked() {
+                    state.locating = true;
+                    state.location_error = None;
+                    let location = state.location.clone();
+                    let window = state.window.clone();
+                    geolocation::locate(move |result| {
+                        *location.write().unwrap() = Some(result);
+                        window.request_redraw();
+                    });
+                }
+                ui.checkbox(&mut state.locate_adds_waypoint, "Add a waypoint there");
+            });
+            if let Some(err) = &state.location_error {
+                ui.colored_label(Color32::RED, err);
+            }
+            ui.checkbox(&mut state.show_north_arrow, "Show north arrow");
+            ui.checkbox(&mut state.show_legend, "Show legend");
+            ui.checkbox(&mut state.color_legs_by_speed, "Color legs by speed");
+            let mut current_coloring = *state.current_coloring.read().unwrap();
+            ComboBox::from_label("Current arrow colors")
+                .selected_text(match current_coloring {
+                    CurrentColoring::StationType => "By station type",
+                    CurrentColoring::Speed => "By speed",
+                })
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(
+                        &mut current_coloring,
+                        CurrentColoring::StationType,
+                        "By station type",
+                    );
+                    ui.selectable_value(&mut current_coloring, CurrentColoring::Speed, "By speed");
+                });
+            set_current_coloring(state, current_coloring);
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut state.compare_times, "Compare with")
+                    .on_hover_text("Also draw the currents at a second time, offset from the selected one, as faded arrows behind its own, e.g. to see how they swing over the next few hours.");
+                ui.add(
+                    DragValue::new(&mut state.compare_hours)
+                        .clamp_range(-48.0..=48.0)
+                        .speed(0.5)
+                        .suffix(" h"),
+                );
+            });
+            ui.checkbox(&mut state.show_integrated_path, "Draw legs along the integrated path")
+                .on_hover_text("Draw each leg where the trip calculation puts the paddler at each 5 minute step for the selected departure, rather than as a straight line.");
+            ui.checkbox(
+                &mut state.accessible.write().unwrap(),
+                "Large text and high contrast",
+            );
+            ui.checkbox(&mut state.time_display.utc, "Show times in UTC")
+                .on_hover_text("Times are entered in local time either way.");
+            CollapsingHeader::new("Decimal places").show(ui, |ui| {
+                let precision = &mut state.precision;
+                for (label, digits) in [
+                    ("Distances", &mut precision.distance),
+                    ("Durations", &mut precision.time),
+                    ("Trip speeds", &mut precision.speed),
+                    ("Current speeds", &mut precision.current),
+                    ("Coordinates", &mut precision.coordinates),
+                ] {
+                    ui.horizontal(|ui| {
+                        ui.add(DragValue::new(digits).clamp_range(0..=6));
+                        ui.label(label);
+                    });
+                }
+            });
         });
 
     Window::new("About")
@@ -219,43 +1713,295 @@ pub fn run_ui(state: &mut UiState, ui: &Context) {
             });
         });
 
+    Area::new(Id::new("waypoint_mode"))
+        .anchor(Align2::CENTER_TOP, [0.0, 8.0])
+        .interactable(false)
+        .show(ui, |ui| {
+            Frame::popup(ui.style()).show(ui, |ui| {
+                ui.heading(format!(
+                    "Waypoint mode: {:?}",
+                    *state.waypoint_mode.read().unwrap()
+                ));
+            });
+        });
+
+    if state.confirm_clear_waypoints {
+        Window::new("Clear Waypoints?")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ui, |ui| {
+                ui.label("Remove all waypoints from the route?");
+                ui.label("Use Tab to switch buttons and Enter to choose.");
+                ui.horizontal(|ui| {
+                    let cancel = ui.button("Cancel");
+                    let clear = ui.button("Clear");
+                    // Default to the non-destructive choice.
+                    if ui.memory(|memory| memory.focused().is_none()) {
+                        cancel.request_focus();
+                    }
+                    if clear.clicked() {
+                        state.trip.write().unwrap().clear_waypoints();
+                        state.confirm_clear_waypoints = false;
+                    }
+                    if cancel.clicked() {
+                        state.confirm_clear_waypoints = false;
+                    }
+                });
+            });
+    }
+
+    if let Some(station) = state.inspected_station.clone() {
+        let mut open = true;
+        Window::new("Station Details")
+            .open(&mut open)
+            .default_width(240.0)
+            .show(ui, |ui| {
+                ui.label(format!("{} ({})", station.name, station.id));
+                ui.label(format!(
+                    "{:?} station at {}",
+                    station.type_,
+                    state
+                        .coordinate_system
+                        .format(&station.loc, state.precision.coordinates)
+                ));
+
+                ui.separator();
+
+                ui.label("Jump to current:");
+                ui.horizontal(|ui| {
+                    let trip = state.trip.read().unwrap();
+                    for (label, extremum, forward) in [
+                        ("⬅ Slack", CurrentExtremum::Slack, false),
+                        ("Slack ➡", CurrentExtremum::Slack, true),
+                        ("⬅ Max", CurrentExtremum::Max, false),
+                        ("Max ➡", CurrentExtremum::Max, true),
+                    ] {
+                        let time_idx = state.time_idx.read().unwrap().row();
+                        let target_idx =
+                            trip.next_current_extremum(&station, time_idx, extremum, forward);
+                        if ui
+                            .add_enabled(target_idx.is_some(), Button::new(label))
+                            .clicked()
+                            && state.time_idx.write().unwrap().set_row(target_idx.unwrap())
+                        {
+                            state.galileo_state.read().unwrap().redraw_map();
+                        }
+                    }
+                });
+
+                ui.separator();
+
+                ui.label("Upcoming tides:");
+                let window = state.window.clone();
+                match state
+                    .tide_cache
+                    .get(&station, move || window.request_redraw())
+                {
+                    None => {
+                        ui.label("Loading...");
+                    },
+                    Some(Err(err)) => {
+                        ui.label(format!("Tide predictions are unavailable: {err}"));
+                    },
+                    Some(Ok(tides)) => {
+                        let now =
+                            time_idx::time_at(&time_vec, state.time_idx.read().unwrap().val())
+                                .unwrap();
+                        let tide_times = tides["time"].datetime().unwrap();
+                        let tide_high_low = tides["high_low"].str().unwrap();
+                        for (time, high_low) in tide_times
+                            .into_iter()
+                            .zip(tide_high_low)
+                            .filter_map(|(time, high_low)| Some((time?, high_low?)))
+                            .filter(|(time, high_low)| {
+                                *time >= now && (*high_low == "H" || *high_low == "L")
+                            })
+                            .take(4)
+                        {
+                            ui.label(format!(
+                                "{} {}",
+                                if high_low == "H" { "High" } else { "Low" },
+                                state.time_display.format_millis(time, "%a %Y-%m-%d %H:%M"),
+                            ));
+                        }
+                    },
+                }
+            });
+
+        if !open {
+            state.inspected_station = None;
+        }
+    }
+
+    if let (Some(station), Some(raw_responses)) = (&state.inspected_station, &state.raw_responses) {
+        Window::new("NOAA Response")
+            .default_width(360.0)
+            .show(ui, |ui| {
+                ui.label(format!("{} ({})", station.name, station.id));
+
+                CollapsingHeader::new("Parsed predictions")
+                    .default_open(true)
+                    .show(ui, |ui| {
+                        let trip = state.trip.read().unwrap();
+                        match trip.current_predictions_30m.get(station) {
+                            Some(prediction) => {
+                                match export::csv(&mut prediction.df.head(Some(10))) {
+                                    Ok(head) => ui.monospace(head),
+                                    Err(err) => ui.label(format!("Formatting failed: {err}")),
+                                };
+                            },
+                            None => {
+                                ui.label("No predictions were parsed for this station.");
+                            },
+                        }
+                    });
+
+                CollapsingHeader::new("Raw response").show(ui, |ui| {
+                    let window = state.window.clone();
+                    match raw_responses.get(station, move || window.request_redraw()) {
+                        None => {
+                            ui.label("Loading...");
+                        },
+                        Some(Err(err)) => {
+                            ui.label(format!("Fetching the response failed: {err}"));
+                        },
+                        Some(Ok(json)) => {
+                            ScrollArea::both().max_height(400.0).show(ui, |ui| {
+                                ui.monospace(json);
+                            });
+                        },
+                    }
+                });
+            });
+    }
+
     SidePanel::left("KayakNav")
         .default_width(380.0)
-        .show(ui, |ui| {
+        .show_animated(ui, state.presentation.is_none(), |ui| {
             ScrollArea::vertical().show(ui, |ui| {
-                ui.label("Arrows indicate current predictions; blue are harmonic stations and red are subordinate stations.");
+                ui.label("Arrows indicate current predictions; blue are harmonic stations and red are subordinate stations. Hover over an arrow for its station and speed, or click a station's name in the table below for details.");
+                if state.observed_currents {
+                    ui.label("Teal arrows and speeds are currents observed in the last 72 hours, which replace the predictions where available.");
+                }
                 ui.add(Hyperlink::from_label_and_url(
                     "Details.",
                     "https://tidesandcurrents.noaa.gov/noaacurrents/Help")
                        .open_in_new_tab(true));
 
-                ui.label("Use the left and right arrow keys to shift the time.");
-                ui.label("Left click to place movement waypoints, middle click to place 0.5h pause waypoints, and right click to remove waypoints. Place multiple pause waypoints for a longer pause. Trips are calculated using waypoints in the order they were placed.");
+                ui.label("Use the left and right arrow keys to shift the time by 5 minutes, or by 30 minutes with Ctrl held.");
+                ui.label("Left click to place movement waypoints, middle click to place pause waypoints, and right click to remove waypoints. Set the duration of new pause waypoints in the Controls window. Trips are calculated using waypoints in the order they were placed.");
+                ui.label("Press Tab (Shift+Tab) to select the next (previous) waypoint, Shift and the arrow keys to move the selected waypoint, and Delete to remove it. Press 1, 2, or 3 to switch the waypoint mode, and E to extend the route by repeating the last leg.");
                 ui.label("A base travel speed of 3kt is assumed.");
                 ui.label("WARNING: the current predictions (and, consequently, trip calculation) here are baseline predictions and do not take into account weather (recent rains, wind, etc.).");
 
+                let data_age = Utc::now() - state.data_fetched_at;
+                let fetched_at = if state.time_display.utc {
+                    state.data_fetched_at.format("%a %Y-%m-%d %H:%M UTC").to_string()
+                } else {
+                    state
+                        .data_fetched_at
+                        .with_timezone(&Local)
+                        .format("%a %Y-%m-%d %H:%M")
+                        .to_string()
+                };
+                let freshness = format!(
+                    "Data fetched: {} ({} hours ago{})",
+                    fetched_at,
+                    data_age.num_hours(),
+                    if state.data_from_cache { ", cached" } else { "" },
+                );
+                if data_age.num_hours() >= STALE_DATA_HOURS {
+                    ui.colored_label(Color32::YELLOW, freshness);
+                } else {
+                    ui.label(freshness);
+                }
+                if let Some(message) = no_stations_message(state.region.name, &state.stations) {
+                    ui.colored_label(Color32::YELLOW, message);
+                }
+                if let Some((loaded, total)) = state.current_predictions_loading {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label(format!(
+                            "Loading current predictions: {loaded} of {total} stations. Trips can be calculated once all have loaded."
+                        ));
+                    });
+                }
+                if let Some(scale_factor) = state.unusual_scale_factor {
+                    ui.colored_label(
+                        Color32::YELLOW,
+                        format!("The display is scaled {scale_factor:.2}x, so the map's arrows and waypoints may look too small or too large next to the controls. Adjusting the browser zoom or display scaling may help."),
+                    );
+                }
+                if !state.failed_stations.is_empty() {
+                    ui.horizontal(|ui| {
+                        ui.colored_label(
+                            Color32::YELLOW,
+                            format!(
+                                "{} of {} stations unavailable",
+                                state.failed_stations.len(),
+                                state.stations.len()
+                            ),
+                        );
+                        let retry = ui
+                            .add_enabled(
+                                state.current_predictions_loading.is_none(),
+                                Button::new("Retry"),
+                            )
+                            .on_hover_text("Fetches the unavailable stations' predictions again.");
+                        if retry.clicked() {
+                            state.retry_failed_stations = true;
+                        }
+                    });
+                    ui.collapsing("Unavailable stations", |ui| {
+                        for (station, failure) in &state.failed_stations {
+                            let name = format!("{} ({})", station.name, station.id);
+                            match failure {
+                                StationFailure::NoPredictions => {
+                                    ui.label(format!("{name}: no predictions from NOAA"));
+                                },
+                                StationFailure::Error(err) => {
+                                    ui.label(format!("{name}: fetch failed"))
+                                        .on_hover_text(err);
+                                },
+                            }
+                        }
+                    });
+                }
+
                 ui.separator();
 
                 ui.label("Pointer position:");
                 if let Some(pointer_position) = state.pointer_position {
-                    ui.label(format!(
-                        "Lat: {:.4} Lon: {:.4}",
-                        pointer_position.lat(),
-                        pointer_position.lon()
-                    ));
+                    ui.label(
+                        state
+                            .coordinate_system
+                            .format(&pointer_position, state.precision.coordinates),
+                    );
                 } else {
                     ui.label("<unavaliable>");
                 }
 
                 ui.separator();
 
+                // Recomputed every frame, since now advances.
+                let horizon_row = state.limit_planning_horizon.then(|| {
+                    now_time_idx(&time_vec, &state.clock)
+                        + (state.planning_horizon_days * 24.0 * 2.0).round() as usize
+                });
+                if state.time_idx.write().unwrap().set_horizon(horizon_row) {
+                    state.galileo_state.read().unwrap().redraw_map();
+                }
+
                 ui.with_layout(Layout::left_to_right(Align::Min), |ui| {
                     ui.spacing_mut().slider_width = 280.0;
 
                     // avoid deadlock with the write reference
                     let time_range = 0..=state.time_idx.read().unwrap().upper_bound();
+                    let locked = state.lock_time;
                     let slider = state.time_idx.write().unwrap().with_val_mut(
-                        |val| ui.add(
+                        |val| ui.add_enabled(
+                            !locked,
                             Slider::new(
                                 val,
                                 time_range,
@@ -267,40 +2013,128 @@ pub fn run_ui(state: &mut UiState, ui: &Context) {
                         state.galileo_state.read().unwrap().redraw_map();
                     }
 
-                    if ui.button("Now").clicked() {
-                        let now = Local::now().naive_local();
-                        let current_time_idx = time_vec
-                            .iter()
-                            .enumerate()
-                            .find(|(_, dt)| {
-                                DateTime::from_timestamp_millis(**dt)
-                                    .unwrap()
-                                    .naive_utc() >= now
-                            })
-                            .unwrap()
-                            .0;
-                        if state.time_idx.write().unwrap().set(current_time_idx) {
-                            state.galileo_state.read().unwrap().redraw_map();
+                    if ui.add_enabled(!locked, Button::new("Now")).clicked() {
+                        let now = state.clock.now_millis();
+                        if let Some(idx) = time_idx::nearest_step(&time_vec, now) {
+                            if state.time_idx.write().unwrap().set(idx) {
+                                state.galileo_state.read().unwrap().redraw_map();
+                            }
                         }
                     }
+                    if locked {
+                        ui.label("🔒 Time locked");
+                    }
                 });
 
-                let time_str: &str =
-                    &DateTime::from_timestamp_millis(time_vec[state.time_idx.read().unwrap().val()])
-                    .unwrap()
-                    .naive_utc()
-                    .format("%a %Y-%m-%d %H:%M:%S")
-                    .to_string();
+                ui.with_layout(Layout::left_to_right(Align::Min), |ui| {
+                    ui.toggle_value(&mut state.limit_planning_horizon, "Only show the next")
+                        .on_hover_text("Shortens the time slider. All predictions stay loaded.");
+                    ui.add_enabled(
+                        state.limit_planning_horizon,
+                        DragValue::new(&mut state.planning_horizon_days)
+                            .clamp_range(1.0..=60.0)
+                            .speed(1.0)
+                            .suffix(" days"),
+                    );
+                    if state.limit_planning_horizon && ui.button("Show All").clicked() {
+                        state.limit_planning_horizon = false;
+                    }
+                });
+
+                ui.with_layout(Layout::left_to_right(Align::Min), |ui| {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut state.time_input)
+                            .hint_text("YYYY-MM-DD HH:MM")
+                            .desired_width(140.0),
+                    )
+                    .on_hover_text("Station local time, like the times shown here.");
+                    if ui.add_enabled(!state.lock_time, Button::new("Go")).clicked() {
+                        let parsed = parse_time_input(&state.time_input, &time_vec);
+                        state.time_input_error = match parsed {
+                            Ok(idx) => {
+                                if state.time_idx.write().unwrap().set(idx) {
+                                    state.galileo_state.read().unwrap().redraw_map();
+                                }
+                                None
+                            },
+                            Err(err) => Some(err.to_string()),
+                        };
+                    }
+                });
+                if let Some(err) = &state.time_input_error {
+                    ui.colored_label(Color32::RED, err);
+                }
+
+                let time = time_idx::time_at(&time_vec, state.time_idx.read().unwrap().val());
+                let time_str: &str = &state
+                    .time_display
+                    .format_millis(time.unwrap(), "%a %Y-%m-%d %H:%M:%S");
 
                 let high_low: &str = state.battery_tide_predictions["high_low"]
                     .str()
                     .unwrap()
-                    .get(state.time_idx.read().unwrap().val())
+                    .get(state.time_idx.read().unwrap().row())
                     .unwrap();
 
+                ui.label(format!("Tide at {} ({}):", state.battery.name, state.battery.id));
                 let mut time_high_low: &str = &format!("{}  {}", time_str, high_low);
                 let _ = ui.add(egui::TextEdit::singleline(&mut time_high_low));
 
+                ui.horizontal(|ui| {
+                    let idx = state.time_idx.read().unwrap().val();
+                    let events = &state.tide_events;
+                    for (label, target_idx) in [
+                        ("⬅ Low", adjacent_tide(events, idx, Tide::Low, false)),
+                        ("⬅ High", adjacent_tide(events, idx, Tide::High, false)),
+                        ("Nearest", nearest_tide(events, idx)),
+                        ("High ➡", adjacent_tide(events, idx, Tide::High, true)),
+                        ("Low ➡", adjacent_tide(events, idx, Tide::Low, true)),
+                    ] {
+                        if ui
+                            .add_enabled(target_idx.is_some(), Button::new(label))
+                            .on_hover_text("Jumps to a high or low tide, to the nearest 30 minutes.")
+                            .clicked()
+                            && state.time_idx.write().unwrap().set(target_idx.unwrap())
+                        {
+                            state.galileo_state.read().unwrap().redraw_map();
+                        }
+                    }
+                });
+
+                ui.with_layout(Layout::left_to_right(Align::Min), |ui| {
+                    if ui.button("Best departure in the next").clicked() {
+                        let start = now_time_idx(&time_vec, &state.clock);
+                        let end = (start + (state.quick_suggestion_hours * 2.0) as usize + 1)
+                            .min(time_vec.len());
+                        let best = state.trip.write().unwrap().best_departure(start..end);
+                        if let Some((idx, _)) = best {
+                            if state.time_idx.write().unwrap().set_row(idx) {
+                                state.galileo_state.read().unwrap().redraw_map();
+                            }
+                        }
+                        state.quick_suggestion = Some(best);
+                    }
+                    ui.add(
+                        DragValue::new(&mut state.quick_suggestion_hours)
+                            .clamp_range(0.5..=24.0)
+                            .speed(0.5)
+                            .suffix("h"),
+                    );
+                });
+                match state.quick_suggestion {
+                    Some(Some((idx, duration))) => {
+                        ui.label(format!(
+                            "Best departure: {} ({})",
+                            state.time_display.format_millis(time_vec[idx], "%a %Y-%m-%d %H:%M"),
+                            state.precision.time(duration),
+                        ));
+                    },
+                    Some(None) => {
+                        ui.colored_label(Color32::RED, "No feasible departure in that window");
+                    },
+                    None => {},
+                }
+
                 ui.separator();
 
                 let mut trip = state.trip.write().unwrap();
@@ -329,28 +2163,46 @@ pub fn run_ui(state: &mut UiState, ui: &Context) {
                             let station = &trip.stations[row_index];
                             let pred = &trip.current_predictions_30m[station];
 
+                            // Null where the station has no prediction for
+                            // the selected time.
                             let heading = pred.df["direction"]
                                 .f64()
                                 .unwrap()
-                                .get(state.time_idx.read().unwrap().val())
-                                .unwrap();
+                                .get(state.time_idx.read().unwrap().row());
 
                             let speed = pred.df["speed"]
                                 .f64()
                                 .unwrap()
-                                .get(state.time_idx.read().unwrap().val())
-                                .unwrap();
+                                .get(state.time_idx.read().unwrap().row());
+
+                            let observed = pred.is_observed(state.time_idx.read().unwrap().row());
 
                             row.col(|ui| {
-                                ui.label(pred.station.name.clone());
+                                if ui.link(pred.station.name.clone()).clicked() {
+                                    state.inspected_station = Some(pred.station.clone());
+                                }
                             });
 
-                            row.col(|ui| {
-                                ui.label(format!("{:.2}", speed));
+                            row.col(|ui| match speed {
+                                Some(speed) if observed => {
+                                    let (r, g, b) = features::OBSERVED_CURRENT_COLOR;
+                                    ui.colored_label(
+                                        Color32::from_rgb(r, g, b),
+                                        state.precision.current(speed),
+                                    )
+                                    .on_hover_text("Observed rather than predicted.");
+                                },
+                                Some(speed) => {
+                                    ui.label(state.precision.current(speed));
+                                },
+                                None => {
+                                    ui.label("-");
+                                },
                             });
 
                             row.col(|ui| {
-                                ui.label(degree_to_cardinal_direction(heading));
+                                let heading = heading.map(degree_to_cardinal_direction);
+                                ui.label(heading.as_deref().unwrap_or("-"));
                             });
                         });
 
@@ -358,64 +2210,224 @@ pub fn run_ui(state: &mut UiState, ui: &Context) {
 
                 ui.separator();
 
-                let internal_time_step = Time::new::<minute>(5.0);
-                let time_ratio: Ratio = Time::new::<minute>(30.0) / internal_time_step;
-                let waypoint_time_idx =
-                    time_ratio.value as usize * state.time_idx.read().unwrap().val();
+                ui.label("Routes (only the selected route can be edited):");
+                let time_idx = state.time_idx.read().unwrap().val();
+                let mut selected_route = state.active_route;
+                for i in 0..state.routes.len() {
+                    let route_result = if i == state.active_route {
+                        trip.calculate_departure_5m(time_idx)
+                    } else {
+                        state.routes[i].copy_settings(&trip);
+                        state.routes[i].calculate_departure_5m(time_idx)
+                    };
+                    let summary = match route_result {
+                        Ok(route_result) => format!(
+                            "{}, {}",
+                            state.precision.distance(route_result.distance()),
+                            state.precision.time(route_result.time()),
+                        ),
+                        Err(err) => err.to_string(),
+                    };
+                    let (r, g, b) = features::route_color(i);
+                    ui.horizontal(|ui| {
+                        ui.colored_label(Color32::from_rgb(r, g, b), "⬤");
+                        let label = format!("Route {}: {summary}", i + 1);
+                        if ui.selectable_label(i == state.active_route, label).clicked() {
+                            selected_route = i;
+                        }
+                    });
+                }
+                if selected_route != state.active_route {
+                    mem::swap(&mut *trip, &mut state.routes[state.active_route]);
+                    mem::swap(&mut *trip, &mut state.routes[selected_route]);
+                    state.active_route = selected_route;
+                    state.selected_waypoint = None;
+                    state.replay = None;
+                }
+                if ui.button("New Route").clicked() {
+                    let route = trip.new_route(WaypointSymbol::for_route(
+                        state.routes.len(),
+                        state.accessible.clone(),
+                        state.waypoint_highlight.clone(),
+                    ));
+                    state.galileo_state.read().unwrap().add_waypoint_layer(&route);
+                    state.routes.push(route);
+                }
+
+                ui.separator();
 
-                let trip_result = trip.calculate(waypoint_time_idx);
+                let time_idx = state.time_idx.read().unwrap().val();
+                let trip_result = trip.calculate_departure_5m(time_idx);
 
-                let mut distance_time: &str = match &trip_result {
-                    Some(trip_result) => &format!(
-                        "Total: {:.2}, {:.1}",
-                        trip_result.distance().into_format_args(mile, Abbreviation),
-                        trip_result.time().into_format_args(hour, Abbreviation),
-                    ),
-                    None => "Exceeded fetched data.",
+                // Pause legs have no meaningful speed and aren't drawn.
+                let legs: Vec<Leg> = match &trip_result {
+                    Ok(trip_result) if state.show_integrated_path => {
+                        let paths = trip.leg_paths(time_idx).unwrap_or_default();
+                        trip.waypoints
+                            .windows(2)
+                            .zip(&trip_result.steps[1..])
+                            .zip(&paths)
+                            .filter(|((waypoints, _), _)| {
+                                matches!(waypoints[1].type_, WaypointType::Move)
+                            })
+                            .filter_map(|((_, step), path)| {
+                                let color = if state.color_legs_by_speed {
+                                    features::leg_speed_color(step.speed(), trip.speed)
+                                } else {
+                                    features::route_color(state.active_route)
+                                };
+                                match state.max_path_segment {
+                                    Some(max_segment) => Leg::along(
+                                        &features::resample_path(path, max_segment),
+                                        color,
+                                    ),
+                                    None => Leg::along(path, color),
+                                }
+                            })
+                            .collect()
+                    },
+                    Ok(trip_result) if state.color_legs_by_speed => trip
+                        .waypoints
+                        .windows(2)
+                        .zip(&trip_result.steps[1..])
+                        .filter(|(waypoints, _)| matches!(waypoints[1].type_, WaypointType::Move))
+                        .map(|(waypoints, step)| Leg {
+                            points: vec![waypoints[0].point, waypoints[1].point],
+                            color: features::leg_speed_color(step.speed(), trip.speed),
+                        })
+                        .collect(),
+                    _ => vec![],
                 };
-                ui.add(egui::TextEdit::singleline(&mut distance_time));
+                if legs != state.shown_legs {
+                    state.galileo_state.read().unwrap().set_legs(legs.clone());
+                    state.shown_legs = legs;
+                }
+
+                match &trip_result {
+                    Ok(trip_result) => {
+                        ui.colored_label(Color32::GREEN, "Feasible");
+                        let average_speed = trip_result
+                            .average_speed()
+                            .map(|speed| format!(", avg {}", state.precision.speed(speed)))
+                            .unwrap_or_default();
+                        let mut distance_time: &str = &format!(
+                            "Total: {}, {} ({} moving){average_speed}",
+                            state.precision.distance(trip_result.distance()),
+                            state.precision.time(trip_result.time()),
+                            state.precision.time(trip_result.moving_time()),
+                        );
+                        ui.add(egui::TextEdit::singleline(&mut distance_time));
+                    },
+                    Err(err) => {
+                        ui.colored_label(Color32::RED, format!("Infeasible: {err}"));
+                    },
+                }
+
+                ui.with_layout(Layout::left_to_right(Align::Min), |ui| {
+                    ui.toggle_value(&mut state.warn_sharp_turns, "Flag turns over")
+                        .on_hover_text("Sharp turns between legs often mean a misplaced waypoint");
+                    ui.add(
+                        DragValue::new(&mut state.sharp_turn_degrees)
+                            .clamp_range(0.0..=180.0)
+                            .speed(5.0)
+                            .suffix("°"),
+                    );
+                });
 
                 ui.separator();
 
                 let mut cumulative_time = Time::default();
 
-                if let Some(trip_result) = trip_result {
-                    for (i, (waypoint, step)) in trip.waypoints
+                if let Ok(trip_result) = trip_result {
+                    let arrival_currents = trip.arrival_currents(time_idx, &trip_result);
+                    for (i, ((waypoint, step), arrival_current)) in trip.waypoints
                         .iter()
                         .zip(trip_result.steps)
+                        .zip(arrival_currents)
                         .enumerate() {
                         cumulative_time += step.time;
-                        let mut s: &str = &format!(
-                            "{:?}. ({:.4}, {:.4}): {:.2}, {:.1}. {:.1}, {:.1}",
+                        let mut s = format!(
+                            "{:?}. {}: {}, {}. {}, {}",
                             i,
-                            waypoint.lat(),
-                            waypoint.lon(),
-                            step.distance.into_format_args(mile, Abbreviation),
-                            step.time.into_format_args(hour, Abbreviation),
-                            step.speed().into_format_args(knot, Abbreviation),
-                            cumulative_time.into_format_args(hour, Abbreviation),
+                            state
+                                .coordinate_system
+                                .format_pair(waypoint, state.precision.coordinates),
+                            state.precision.distance(step.distance),
+                            state.precision.time(step.time),
+                            state.precision.speed(step.speed()),
+                            state.precision.time(cumulative_time),
                         );
-                        ui.add(egui::TextEdit::singleline(&mut s));
+                        if let Some((speed, direction)) = arrival_current {
+                            s += &format!(
+                                ". Current {} {}",
+                                state.precision.speed(speed),
+                                degree_to_cardinal_direction(direction),
+                            );
+                        }
+                        let mut warnings = vec![];
+                        if step.low_confidence() {
+                            s += " ⚠ low confidence";
+                            warnings.push(format!(
+                                "Currents for this leg were sampled from stations more than {:.1} away.",
+                                trip.max_station_distance
+                                    .into_format_args(mile, Abbreviation)
+                            ));
+                        }
+                        let sharp_turn = trip.turn_angle(i).filter(|turn| {
+                            state.warn_sharp_turns && *turn > state.sharp_turn_degrees
+                        });
+                        if let Some(turn) = sharp_turn {
+                            s += " ⚠ sharp turn";
+                            warnings.push(format!(
+                                "The route turns {turn:.0}° here. Check that this waypoint is where you meant to put it."
+                            ));
+                        }
+                        let mut s: &str = &s;
+                        let response = ui.add(egui::TextEdit::singleline(&mut s));
+                        if !warnings.is_empty() {
+                            response.on_hover_text(warnings.join("\n"));
+                        }
                     }
                 }
 
                 ui.separator();
 
                 ui.with_layout(Layout::left_to_right(Align::Min), |ui| {
-                    ui.toggle_value(&mut state.sweep_weekdays.mon, "Mon");
-                    ui.toggle_value(&mut state.sweep_weekdays.tue, "Tue");
-                    ui.toggle_value(&mut state.sweep_weekdays.wed, "Wed");
-                    ui.toggle_value(&mut state.sweep_weekdays.thu, "Thu");
-                    ui.toggle_value(&mut state.sweep_weekdays.fri, "Fri");
-                    ui.toggle_value(&mut state.sweep_weekdays.sat, "Sat");
-                    ui.toggle_value(&mut state.sweep_weekdays.sun, "Sun");
+                    ui.add_enabled_ui(!state.sweep_today, |ui| {
+                        ui.toggle_value(&mut state.sweep_weekdays.mon, "Mon");
+                        ui.toggle_value(&mut state.sweep_weekdays.tue, "Tue");
+                        ui.toggle_value(&mut state.sweep_weekdays.wed, "Wed");
+                        ui.toggle_value(&mut state.sweep_weekdays.thu, "Thu");
+                        ui.toggle_value(&mut state.sweep_weekdays.fri, "Fri");
+                        ui.toggle_value(&mut state.sweep_weekdays.sat, "Sat");
+                        ui.toggle_value(&mut state.sweep_weekdays.sun, "Sun");
+                    });
+                    ui.separator();
+                    ui.toggle_value(&mut state.sweep_today, "Today")
+                        .on_hover_text("Only show departures today. Toggle off to go back to the selected weekdays.");
                 });
 
                 trip.set_weekdays(state.sweep_weekdays.into());
+                trip.set_sweep_date(state.sweep_today.then(|| state.clock.now().date()));
 
                 ui.toggle_value(&mut state.daytime, "Leave after 8, Arrive before 9");
                 trip.set_daytime(state.daytime);
 
+                ui.with_layout(Layout::left_to_right(Align::Min), |ui| {
+                    ui.toggle_value(&mut state.limit_trip_duration, "Only show trips under");
+                    ui.add(
+                        DragValue::new(&mut state.max_trip_hours)
+                            .clamp_range(0.5..=48.0)
+                            .speed(0.5)
+                            .suffix("h"),
+                    );
+                });
+                trip.set_max_trip_duration(
+                    state
+                        .limit_trip_duration
+                        .then(|| Time::new::<hour>(state.max_trip_hours)),
+                );
+
                 ui.separator();
 
 
@@ -437,46 +2449,417 @@ pub fn run_ui(state: &mut UiState, ui: &Context) {
                     (vec![], vec![])
                 };
 
-                ui.push_id(1, |ui| {
-                    TableBuilder::new(ui)
-                        .max_scroll_height(400.0)
-                        .column(Column::exact(184.0))
-                        .column(Column::remainder())
-                        .header(18.0, |mut header| {
-                            header.col(|ui| {
-                                ui.heading("Departure Time");
-                            });
-                            header.col(|ui| {
-                                ui.heading("Duration");
-                            });
-                        })
-                        .body(|body| {
-                            let row_height = 18.0;
-                            let num_rows = sweep_idx_vec.len();
-                            body.rows(row_height, num_rows, |mut row| {
-                                let row_index = row.index();
-
-                                let idx = sweep_idx_vec[row_index];
-                                let duration = sweep_duration_vec[row_index];
-
-                                let time_str: &str =
-                                    &DateTime::from_timestamp_millis(time_vec[idx as usize])
-                                        .unwrap()
-                                        .naive_utc()
-                                        .format("%a %Y-%m-%d %H:%M:%S")
-                                        .to_string();
-
-                                row.col(|ui| {
-                                    ui.label(time_str);
-                                });
+                ui.with_layout(Layout::left_to_right(Align::Min), |ui| {
+                    ui.selectable_value(&mut state.sweep_view, SweepView::Table, "Table");
+                    ui.selectable_value(&mut state.sweep_view, SweepView::Heatmap, "Heatmap");
+                });
+
+                match state.sweep_view {
+                    SweepView::Table => {
+                        ui.push_id(1, |ui| {
+                            TableBuilder::new(ui)
+                                .max_scroll_height(400.0)
+                                .column(Column::exact(184.0))
+                                .column(Column::remainder())
+                                .header(18.0, |mut header| {
+                                    header.col(|ui| {
+                                        ui.heading("Departure Time");
+                                    });
+                                    header.col(|ui| {
+                                        ui.heading("Duration");
+                                    });
+                                })
+                                .body(|body| {
+                                    let row_height = 18.0;
+                                    let num_rows = sweep_idx_vec.len();
+                                    body.rows(row_height, num_rows, |mut row| {
+                                        let row_index = row.index();
 
-                                row.col(|ui| {
-                                    ui.label(format!("{:.1}h", duration / 3600.0));
+                                        let idx = sweep_idx_vec[row_index];
+                                        let duration = sweep_duration_vec[row_index];
+
+                                        let time_str: &str = &state.time_display.format_millis(
+                                            time_vec[idx as usize],
+                                            "%a %Y-%m-%d %H:%M:%S",
+                                        );
+
+                                        row.col(|ui| {
+                                            ui.label(time_str);
+                                        });
+
+                                        row.col(|ui| {
+                                            ui.label(state.precision.hours(duration));
+                                        });
+                                    });
                                 });
-                            });
                         });
+                    },
+                    SweepView::Heatmap => {
+                        if let Some(idx) = departure_heatmap(
+                            ui,
+                            &time_vec,
+                            &sweep_idx_vec,
+                            &sweep_duration_vec,
+                            state.precision,
+                            state.time_display,
+                        ) {
+                            if state.time_idx.write().unwrap().set_row(idx) {
+                                state.galileo_state.read().unwrap().redraw_map();
+                            }
+                        }
+                    },
+                }
+
+                ui.separator();
+
+                ui.collapsing("Ebb Out, Flood Back", |ui| {
+                    ui.label("Departures with the most following current on both the outbound and return halves of an out and back trip. Click to select.");
+
+                    let suggestions = if trip.waypoints.len() > 2 {
+                        trip.ebb_out_flood_back(10)
+                    } else {
+                        vec![]
+                    };
+
+                    for (idx, outbound, inbound) in suggestions {
+                        let time_str = state
+                            .time_display
+                            .format_millis(time_vec[idx], "%a %Y-%m-%d %H:%M:%S");
+                        let selected =
+                            state.time_idx.read().unwrap().val() == time_idx::row_to_step(idx);
+                        let label = format!(
+                            "{}: {:.0}% out, {:.0}% back",
+                            time_str,
+                            outbound * 100.0,
+                            inbound * 100.0,
+                        );
+                        if ui.selectable_label(selected, label).clicked()
+                            && state.time_idx.write().unwrap().set_row(idx)
+                        {
+                            state.galileo_state.read().unwrap().redraw_map();
+                        }
+                    }
+                });
+
+            });
+        });
+
+    // Shown after the side panel so the anchor is relative to the map area.
+    let mut trip = state.trip.write().unwrap();
+    if trip.waypoints.len() > 1 {
+        let time_idx = state.time_idx.read().unwrap().val();
+        let trip_result = trip.calculate_departure_5m(time_idx);
+        let start_time = trip.time_at_5m(time_idx);
+        Area::new(Id::new("summary_bar"))
+            .anchor(Align2::CENTER_TOP, [0.0, 8.0])
+            .interactable(false)
+            .show(ui, |ui| {
+                Frame::popup(ui.style()).show(ui, |ui| {
+                    summary_bar(
+                        ui,
+                        &trip_result,
+                        start_time,
+                        state.precision,
+                        state.time_display,
+                    );
+                });
+            });
+    }
+    drop(trip);
+
+    replay_controls(state, ui);
+    presentation_controls(state, ui);
+
+    let meters_per_point =
+        state.galileo_state.read().unwrap().ground_resolution() * ui.pixels_per_point() as f64;
+    Area::new(Id::new("scale_bar"))
+        .anchor(Align2::LEFT_BOTTOM, [8.0, -8.0])
+        .interactable(false)
+        .show(ui, |ui| {
+            Frame::popup(ui.style()).show(ui, |ui| {
+                scale_bar(ui, meters_per_point);
+            });
+        });
+
+    if state.show_legend {
+        Area::new(Id::new("legend"))
+            .anchor(Align2::LEFT_BOTTOM, [8.0, -64.0])
+            .interactable(false)
+            .show(ui, |ui| {
+                Frame::popup(ui.style()).show(ui, |ui| {
+                    legend(
+                        ui,
+                        accessible,
+                        state.color_legs_by_speed,
+                        *state.current_coloring.read().unwrap(),
+                        state.compare_times.then_some(state.compare_hours),
+                        state.observed_currents,
+                    );
                 });
+            });
+    }
+
+    if state.show_north_arrow {
+        let rotation = state.galileo_state.read().unwrap().rotation();
+        Area::new(Id::new("north_arrow"))
+            .anchor(Align2::LEFT_TOP, [8.0, 8.0])
+            .show(ui, |ui| {
+                if north_arrow(ui, rotation) {
+                    state.galileo_state.write().unwrap().reset_rotation();
+                }
+            });
+    }
+
+    station_tooltip(state, ui);
+}
+
+const REPLAY_STEP_MS: i32 = 200;
+
+/// Shows controls to replay the trip from the selected time, advancing the
+/// time by 5 minutes every `REPLAY_STEP_MS` while playing and drawing the boat
+/// where the trip puts it at the selected time.
+fn replay_controls(state: &mut UiState, ui: &Context) {
+    let now = ui.input(|i| i.time);
+    let time_idx = state.time_idx.read().unwrap().val();
+    let trip = state.trip.read().unwrap();
+    let has_legs = trip.waypoints.len() > 1;
+    // The track is stale once the waypoints or settings change.
+    if state
+        .replay
+        .as_ref()
+        .is_some_and(|replay| !has_legs || replay.trip_revision != trip.revision())
+    {
+        state.replay = None;
+    }
+    drop(trip);
 
+    if has_legs {
+        Area::new(Id::new("replay"))
+            .anchor(Align2::CENTER_BOTTOM, [0.0, -8.0])
+            .show(ui, |ui| {
+                Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.horizontal(|ui| match &mut state.replay {
+                        Some(replay) => {
+                            let last_step = replay.track.len() - 1;
+                            let mut step = time_idx.saturating_sub(replay.departure);
+                            let (label, hover_text) = if replay.playing {
+                                ("⏸", "Pause")
+                            } else {
+                                ("▶", "Play")
+                            };
+                            if ui.button(label).on_hover_text(hover_text).clicked() {
+                                replay.playing = !replay.playing;
+                                replay.last_step = now;
+                                if replay.playing && step >= last_step {
+                                    step = 0;
+                                    state.time_idx.write().unwrap().set(replay.departure);
+                                    state.galileo_state.read().unwrap().redraw_map();
+                                }
+                            }
+                            let mut scrubbed = step.min(last_step);
+                            if ui
+                                .add(Slider::new(&mut scrubbed, 0..=last_step).show_value(false))
+                                .changed()
+                                && state
+                                    .time_idx
+                                    .write()
+                                    .unwrap()
+                                    .set(replay.departure + scrubbed)
+                            {
+                                state.galileo_state.read().unwrap().redraw_map();
+                            }
+                            let minutes = scrubbed * 5;
+                            ui.label(format!("+{}:{:02}", minutes / 60, minutes % 60));
+                            if ui.button("⏹").on_hover_text("Stop the replay").clicked() {
+                                state.replay = None;
+                            }
+                        },
+                        _ => {
+                            if ui
+                                .button("▶ Replay Trip")
+                                .on_hover_text("Move a boat along the trip departing at the selected time, advancing the currents with it. Drag the slider to scrub.")
+                                .clicked()
+                            {
+                                let mut trip = state.trip.write().unwrap();
+                                state.replay = trip.track(time_idx).ok().map(|track| Replay {
+                                    departure: time_idx,
+                                    playing: true,
+                                    last_step: now,
+                                    track,
+                                    trip_revision: trip.revision(),
+                                });
+                            }
+                        },
+                    });
+                });
             });
+    }
+
+    let time_idx = state.time_idx.read().unwrap().val();
+    let position = state.replay.as_ref().and_then(|replay| {
+        time_idx
+            .checked_sub(replay.departure)
+            .and_then(|step| replay.track.get(step))
+            .copied()
+    });
+    if position != state.shown_boat {
+        state
+            .galileo_state
+            .read()
+            .unwrap()
+            .set_boat(position.and_then(|(lat, lon)| Boat::at(lat, lon)));
+        state.shown_boat = position;
+    }
+
+    let Some(replay) = &mut state.replay else {
+        return;
+    };
+    if !replay.playing {
+        return;
+    }
+    if time_idx + 1 >= replay.departure + replay.track.len() {
+        replay.playing = false;
+        return;
+    }
+    let mut remaining_ms = REPLAY_STEP_MS - ((now - replay.last_step) * 1000.0) as i32;
+    if remaining_ms <= 0 {
+        if state.time_idx.write().unwrap().step(true) {
+            state.galileo_state.read().unwrap().redraw_map();
+        }
+        replay.last_step = now;
+        remaining_ms = REPLAY_STEP_MS;
+    }
+    // Frames are only drawn in response to events, so wake up for the next
+    // step.
+    if !state.replay_wake_pending.swap(true, Ordering::Relaxed) {
+        let replay_wake_pending = state.replay_wake_pending.clone();
+        let window = state.window.clone();
+        tasks::spawn(async move {
+            tasks::sleep(remaining_ms).await;
+            replay_wake_pending.store(false, Ordering::Relaxed);
+            window.request_redraw();
         });
+    }
+}
+
+/// Shows the presentation speed while presenting, advancing the time by 5
+/// minutes every `5 / presentation_speed` seconds until the predictions end.
+fn presentation_controls(state: &mut UiState, ctx: &Context) {
+    if state.presentation.is_none() {
+        return;
+    }
+
+    let mut stop = false;
+    // Above the replay controls.
+    Area::new(Id::new("presentation"))
+        .anchor(Align2::CENTER_BOTTOM, [0.0, -56.0])
+        .show(ctx, |ui| {
+            Frame::popup(ui.style()).show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Presenting");
+                    ui.add(
+                        DragValue::new(&mut state.presentation_speed)
+                            .clamp_range(1.0..=120.0)
+                            .speed(1.0)
+                            .suffix(" min/s"),
+                    );
+                    stop = ui
+                        .button("⏹")
+                        .on_hover_text("Stop presenting (P)")
+                        .clicked();
+                });
+            });
+        });
+    if stop {
+        state.presentation = None;
+        return;
+    }
+
+    let Some(presentation) = &mut state.presentation else {
+        return;
+    };
+    let now = ctx.input(|i| i.time);
+    let step_ms = (5.0 / state.presentation_speed * 1000.0) as i32;
+    let last_step = *presentation.last_step.get_or_insert(now);
+    let mut remaining_ms = step_ms - ((now - last_step) * 1000.0) as i32;
+    if remaining_ms <= 0 {
+        if !state.time_idx.write().unwrap().step(true) {
+            return;
+        }
+        state.galileo_state.read().unwrap().redraw_map();
+        presentation.last_step = Some(now);
+        remaining_ms = step_ms;
+    }
+    // Frames are only drawn in response to events, so wake up for the next
+    // step.
+    if !state
+        .presentation_wake_pending
+        .swap(true, Ordering::Relaxed)
+    {
+        let presentation_wake_pending = state.presentation_wake_pending.clone();
+        let window = state.window.clone();
+        tasks::spawn(async move {
+            tasks::sleep(remaining_ms).await;
+            presentation_wake_pending.store(false, Ordering::Relaxed);
+            window.request_redraw();
+        });
+    }
+}
+
+const HOVER_DELAY_MS: i32 = 300;
+
+/// Shows the station, speed, and direction of the current arrow under the
+/// pointer once it has rested on the map for `HOVER_DELAY_MS`, so the tooltip
+/// doesn't flicker while panning.
+fn station_tooltip(state: &mut UiState, ctx: &Context) {
+    let (pointer, time, pressed) =
+        ctx.input(|i| (i.pointer.hover_pos(), i.time, i.pointer.any_down()));
+    let Some(pointer) = pointer.filter(|_| !pressed && !ctx.is_pointer_over_area()) else {
+        state.hover_start = None;
+        return;
+    };
+
+    let rested_since = match state.hover_start {
+        Some((pos, since)) if pos == pointer => since,
+        _ => {
+            state.hover_start = Some((pointer, time));
+            time
+        },
+    };
+    let remaining_ms = HOVER_DELAY_MS - ((time - rested_since) * 1000.0) as i32;
+    if remaining_ms > 0 {
+        // Frames are only drawn in response to events, so wake up to show the
+        // tooltip.
+        if !state.hover_wake_pending.swap(true, Ordering::Relaxed) {
+            let hover_wake_pending = state.hover_wake_pending.clone();
+            let window = state.window.clone();
+            tasks::spawn(async move {
+                tasks::sleep(remaining_ms).await;
+                hover_wake_pending.store(false, Ordering::Relaxed);
+                window.request_redraw();
+            });
+        }
+        return;
+    }
+
+    let Some(prediction) = state.galileo_state.read().unwrap().hovered_prediction() else {
+        return;
+    };
+    let time_idx = *state.time_idx.read().unwrap();
+    show_tooltip_at_pointer(ctx, Id::new("station_tooltip"), |ui| {
+        ui.strong(&prediction.station.name);
+        ui.label(format!("{:?} station", prediction.station.type_));
+        match prediction.interpolated(time_idx.row_pos()) {
+            Some((speed, direction)) => ui.label(format!(
+                "{} kt toward {:.0}° ({})",
+                state.precision.current(speed),
+                direction,
+                degree_to_cardinal_direction(direction)
+            )),
+            None => ui.label("No prediction at this time"),
+        };
+        if prediction.is_observed(time_idx.row()) {
+            ui.label("Observed rather than predicted.");
+        }
+    });
 }
@@ -48,21 +48,11 @@ impl<N: Saturatingable> Saturating<N> {
     }
 
     pub fn inc(&mut self) -> bool {
-        if self.val == self.upper_bound {
-            false
-        } else {
-            *self += 1u8.into();
-            true
-        }
+        self.val < self.upper_bound && self.set(self.val + 1u8.into())
     }
 
     pub fn dec(&mut self) -> bool {
-        if self.val == self.lower_bound {
-            false
-        } else {
-            *self -= 1u8.into();
-            true
-        }
+        self.val > self.lower_bound && self.set(self.val - 1u8.into())
     }
 
     pub fn set(&mut self, val: N) -> bool {
@@ -90,6 +80,24 @@ impl<N: Saturatingable> Saturating<N> {
     pub fn upper_bound(&self) -> N {
         self.upper_bound
     }
+
+    // Compares against the distance to the bound before adding so unsigned
+    // types can't overflow.
+    fn clamped_add(&self, other: N) -> N {
+        if other > self.upper_bound - self.val {
+            self.upper_bound
+        } else {
+            (self.val + other).max(self.lower_bound)
+        }
+    }
+
+    fn clamped_sub(&self, other: N) -> N {
+        if other > self.val - self.lower_bound {
+            self.lower_bound
+        } else {
+            (self.val - other).min(self.upper_bound)
+        }
+    }
 }
 
 impl<N: Saturatingable> Add<N> for Saturating<N> {
@@ -97,7 +105,7 @@ impl<N: Saturatingable> Add<N> for Saturating<N> {
 
     fn add(self, other: N) -> Self {
         Self {
-            val: (self.val + other).clamp(self.lower_bound, self.upper_bound),
+            val: self.clamped_add(other),
             lower_bound: self.lower_bound,
             upper_bound: self.upper_bound,
         }
@@ -106,7 +114,7 @@ impl<N: Saturatingable> Add<N> for Saturating<N> {
 
 impl<N: Saturatingable> AddAssign<N> for Saturating<N> {
     fn add_assign(&mut self, other: N) {
-        self.val = self.val + other;
+        self.val = self.clamped_add(other);
     }
 }
 
@@ -115,7 +123,7 @@ impl<N: Saturatingable> Sub<N> for Saturating<N> {
 
     fn sub(self, other: N) -> Self {
         Self {
-            val: (self.val - other).clamp(self.lower_bound, self.upper_bound),
+            val: self.clamped_sub(other),
             lower_bound: self.lower_bound,
             upper_bound: self.upper_bound,
         }
@@ -124,6 +132,124 @@ impl<N: Saturatingable> Sub<N> for Saturating<N> {
 
 impl<N: Saturatingable> SubAssign<N> for Saturating<N> {
     fn sub_assign(&mut self, other: N) {
-        self.val = self.val - other
+        self.val = self.clamped_sub(other);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_clamps() {
+        assert_eq!(Saturating::new(12usize, 2, 10).val(), 10);
+        assert_eq!(Saturating::new(0usize, 2, 10).val(), 2);
+    }
+
+    #[test]
+    fn inc_stops_at_upper_bound() {
+        let mut s = Saturating::new(9usize, 0, 10);
+        assert!(s.inc());
+        assert_eq!(s.val(), 10);
+        assert!(!s.inc());
+        assert_eq!(s.val(), 10);
+    }
+
+    #[test]
+    fn dec_stops_at_lower_bound() {
+        let mut s = Saturating::new(1usize, 0, 10);
+        assert!(s.dec());
+        assert_eq!(s.val(), 0);
+        assert!(!s.dec());
+        assert_eq!(s.val(), 0);
+    }
+
+    #[test]
+    fn set_clamps_and_reports_changes() {
+        let mut s = Saturating::new(5usize, 2, 10);
+        assert!(s.set(7));
+        assert_eq!(s.val(), 7);
+        assert!(!s.set(7));
+
+        assert!(s.set(20));
+        assert_eq!(s.val(), 10);
+        // Clamped to the value it already has.
+        assert!(!s.set(30));
+
+        assert!(s.set(0));
+        assert_eq!(s.val(), 2);
+        assert!(!s.set(1));
+    }
+
+    #[test]
+    fn with_val_mut_clamps_and_returns() {
+        let mut s = Saturating::new(5i64, -10, 10);
+        let res = s.with_val_mut(|val| {
+            *val += 100;
+            "done"
+        });
+        assert_eq!(res, "done");
+        assert_eq!(s.val(), 10);
+
+        s.with_val_mut(|val| *val = -100);
+        assert_eq!(s.val(), -10);
+    }
+
+    #[test]
+    fn add_and_add_assign_agree() {
+        let s = Saturating::new(5usize, 0, 10);
+        let added = s + 3;
+        // Add returns a new value, leaving the original alone.
+        assert_eq!(s.val(), 5);
+        assert_eq!(added.val(), 8);
+        assert_eq!(added.lower_bound(), 0);
+        assert_eq!(added.upper_bound(), 10);
+
+        let mut assigned = s;
+        assigned += 3;
+        assert_eq!(assigned, added);
+    }
+
+    #[test]
+    fn sub_and_sub_assign_agree() {
+        let s = Saturating::new(5usize, 0, 10);
+        let subtracted = s - 3;
+        assert_eq!(s.val(), 5);
+        assert_eq!(subtracted.val(), 2);
+
+        let mut assigned = s;
+        assigned -= 3;
+        assert_eq!(assigned, subtracted);
+    }
+
+    #[test]
+    fn clamped_add_saturates_without_overflow() {
+        let s = Saturating::new(usize::MAX - 1, 0, usize::MAX);
+        assert_eq!(s.clamped_add(10), usize::MAX);
+        assert_eq!((s + 10).val(), usize::MAX);
+
+        let s = Saturating::new(5usize, 0, 10);
+        assert_eq!(s.clamped_add(5), 10);
+        assert_eq!(s.clamped_add(6), 10);
+    }
+
+    #[test]
+    fn clamped_sub_saturates_without_overflow() {
+        let s = Saturating::new(1usize, 0, 10);
+        assert_eq!(s.clamped_sub(10), 0);
+        assert_eq!((s - 10).val(), 0);
+
+        let s = Saturating::new(5usize, 2, 10);
+        assert_eq!(s.clamped_sub(3), 2);
+        assert_eq!(s.clamped_sub(4), 2);
+    }
+
+    #[test]
+    fn clamped_add_and_sub_handle_negative_amounts() {
+        let s = Saturating::new(0i32, -5, 5);
+        assert_eq!(s.clamped_add(-10), -5);
+        assert_eq!(s.clamped_sub(-10), 5);
+        assert_eq!(s.clamped_add(-3), -3);
+        assert_eq!(s.clamped_sub(-3), 3);
     }
 }
@@ -1,16 +1,27 @@
+use std::cmp::Reverse;
 use std::collections::HashMap;
+use std::fmt;
+use std::iter;
+use std::mem;
 use std::num::NonZeroUsize;
+use std::ops::Range;
+use std::str::FromStr;
 use std::sync::Arc;
 use std::sync::RwLock;
 
 use chrono::DateTime;
 use chrono::Datelike;
+use chrono::NaiveDate;
 use chrono::NaiveDateTime;
 use chrono::TimeDelta;
 use chrono::Timelike;
 use galileo::layer::feature_layer::FeatureLayer;
 use galileo_types::cartesian::Point2d;
+use galileo_types::geo::impls::GeoPoint2d;
+use galileo_types::geo::Crs;
 use galileo_types::geo::GeoPoint;
+use galileo_types::geo::NewGeoPoint;
+use galileo_types::geo::Projection;
 use galileo_types::geometry_type::CartesianSpace2d;
 use itertools::Itertools;
 use jord::ellipsoidal::Ellipsoid;
@@ -25,55 +36,273 @@ use lru::LruCache;
 use ordered_float::OrderedFloat;
 use polars::prelude::*;
 use rstar::RTree;
+use serde_json::json;
+use serde_json::Value;
+use uom::fmt::DisplayStyle::Abbreviation;
 use uom::si::f64::Length;
 use uom::si::f64::Ratio;
 use uom::si::f64::Time;
 use uom::si::f64::Velocity;
 use uom::si::length::meter;
+use uom::si::length::mile;
 use uom::si::time::hour;
-use uom::si::time::minute;
+use uom::si::time::millisecond;
 use uom::si::time::second;
 use uom::si::velocity::knot;
+use uom::si::velocity::meter_per_second;
 
+use crate::coordinates::CoordinateSystem;
 use crate::features;
 use crate::features::Waypoint;
 use crate::features::WaypointSymbol;
 use crate::features::WaypointType;
 use crate::noaa::CurrentPrediction;
+use crate::noaa::SpeedInterpolation;
 use crate::noaa::Station;
+use crate::noaa::StationType;
+use crate::noaa::TideHeights;
 use crate::prelude::*;
 use crate::run_ui::WeekdayFlags;
+use crate::time_idx;
+
+const PLAN_JSON_VERSION: u32 = 1;
+const PLAN_TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum CurrentExtremum {
+    Slack,
+    Max,
+}
+
+/// How the sweep's fastest 20% duration cutoff is computed from the trip
+/// durations on either side of it. Departures at or under the cutoff are
+/// shown.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum QuantileMethod {
+    /// The nearer of the two durations, so the cutoff is always some
+    /// departure's duration.
+    #[default]
+    Nearest,
+    /// Linearly interpolated between the two durations.
+    Linear,
+    /// Halfway between the two durations.
+    Midpoint,
+}
+
+impl FromStr for QuantileMethod {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "nearest" => Ok(Self::Nearest),
+            "linear" => Ok(Self::Linear),
+            "midpoint" => Ok(Self::Midpoint),
+            _ => Err(anyhow!(
+                "Unknown quantile method {s:?}, expected 'nearest', 'linear', or 'midpoint'"
+            )),
+        }
+    }
+}
+
+impl From<QuantileMethod> for QuantileInterpolOptions {
+    fn from(method: QuantileMethod) -> Self {
+        match method {
+            QuantileMethod::Nearest => Self::Nearest,
+            QuantileMethod::Linear => Self::Linear,
+            QuantileMethod::Midpoint => Self::Midpoint,
+        }
+    }
+}
+
+/// Experimental: scales the paddling speed by the tide height, for routes
+/// through shallows that are slower at low tide. The speed is scaled by
+/// `min_factor` at or below `low` feet above MLLW, unscaled at or above
+/// `high`, and linearly in between.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TideSpeedProfile {
+    pub low: f64,
+    pub high: f64,
+    pub min_factor: f64,
+}
+
+impl Default for TideSpeedProfile {
+    fn default() -> Self {
+        Self {
+            low: 0.0,
+            high: 3.0,
+            min_factor: 0.8,
+        }
+    }
+}
+
+impl TideSpeedProfile {
+    /// The factor the paddling speed is scaled by at a tide `height`, in feet.
+    pub fn factor(&self, height: f64) -> f64 {
+        if height >= self.high {
+            1.0
+        } else if height <= self.low {
+            self.min_factor
+        } else {
+            let frac = (height - self.low) / (self.high - self.low);
+            self.min_factor + (1.0 - self.min_factor) * frac
+        }
+    }
+}
+
+impl FromStr for TideSpeedProfile {
+    type Err = Error;
+
+    /// Parses `LOW:HIGH:FACTOR`.
+    fn from_str(s: &str) -> Result<Self> {
+        let err = || anyhow!("Expected a tide speed profile like 0:3:0.8, got {s:?}");
+        let values: Vec<f64> = s
+            .split(':')
+            .map(|value| value.trim().parse().map_err(|_| err()))
+            .collect::<Result<_>>()?;
+        let [low, high, min_factor] = values[..] else {
+            return Err(err());
+        };
+        if low >= high || !(0.0..=1.0).contains(&min_factor) {
+            return Err(anyhow!(
+                "The tide speed profile's low height must be under its high height, and its \
+                 factor between 0 and 1, got {s:?}"
+            ));
+        }
+        Ok(Self {
+            low,
+            high,
+            min_factor,
+        })
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum TripError {
+    /// The departure is past the end of the fetched current predictions.
+    StartOutOfRange,
+    /// The trip ran past the end of the fetched current predictions.
+    ExceededData,
+    /// The trip took longer than the maximum trip duration.
+    ExceededMaxDuration,
+    /// The trip stalled against a current faster than the paddling speed.
+    ImpassableCurrent,
+    /// The trip departs before 8:00 or arrives after 21:00.
+    OutsideDaytime,
+}
+
+impl fmt::Display for TripError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::StartOutOfRange => {
+                write!(
+                    f,
+                    "Departs after the end of the fetched current predictions."
+                )
+            },
+            Self::ExceededData => write!(f, "Ran past the end of the fetched current predictions."),
+            Self::ExceededMaxDuration => write!(f, "Exceeded the maximum trip duration."),
+            Self::ImpassableCurrent => {
+                write!(
+                    f,
+                    "Stalled against a current faster than the paddling speed."
+                )
+            },
+            Self::OutsideDaytime => write!(f, "Departs before 8:00 or arrives after 21:00."),
+        }
+    }
+}
+
+impl std::error::Error for TripError {}
 
 #[derive(Copy, Clone, Default, Debug)]
 pub struct StepResult {
     pub distance: Length,
     pub time: Time,
     pub time_steps: usize,
+    /// Time spent with a following (rather than opposing) current.
+    pub assisted_time: Time,
+    /// Number of time steps whose current was sampled from a station farther
+    /// than the trip's `max_station_distance`.
+    pub far_station_steps: usize,
 }
 
 impl StepResult {
+    /// Zero for steps that take no time, i.e. the departure step or a pause of
+    /// no duration, rather than NaN.
     pub fn speed(&self) -> Velocity {
-        self.distance / self.time
+        if self.time.value > 0.0 {
+            self.distance / self.time
+        } else {
+            Velocity::new::<knot>(0.0)
+        }
     }
+
+    /// Whether the leg repeatedly used currents from distant stations.
+    pub fn low_confidence(&self) -> bool {
+        self.far_station_steps > 1
+    }
+}
+
+fn station_distance(pos: GeodeticPos, station: &Station) -> Length {
+    let station_pos = GeodeticPos::new(
+        NVector::from_lat_long_degrees(station.loc.lat(), station.loc.lon()),
+        jLength::ZERO,
+    );
+    let ned = LocalFrame::ned(pos, Ellipsoid::WGS84);
+    Length::new::<meter>(
+        ned.geodetic_to_local_pos(station_pos)
+            .slant_range()
+            .as_metres(),
+    )
 }
 
+/// Bearing, in degrees, from `from` to `to`, or `None` if they coincide.
+fn bearing(from: &Waypoint, to: &Waypoint) -> Option<f64> {
+    let from = GeodeticPos::new(
+        NVector::from_lat_long_degrees(from.lat(), from.lon()),
+        jLength::ZERO,
+    );
+    let to = GeodeticPos::new(
+        NVector::from_lat_long_degrees(to.lat(), to.lon()),
+        jLength::ZERO,
+    );
+    let delta = LocalFrame::ned(from, Ellipsoid::WGS84).geodetic_to_local_pos(to);
+    (delta.slant_range().as_metres() > 0.0).then(|| delta.azimuth().as_degrees())
+}
+
+/// Integrates the leg from `start` to `end` departing at the 5 minute
+/// `start_time_idx`, where index 0 is at `time_origin`, in milliseconds since
+/// the epoch. Each station's predictions are looked up by time, so they need
+/// not share a time base or length. If `path` is given, the position, as
+/// latitude and longitude, at the start of each 5 minute step is appended to
+/// it. If `tide_speed` is given, `base_speed` is scaled by the profile at each
+/// step's tide height.
 pub fn calculate_step(
     start: &Waypoint,
     end: &Waypoint,
     base_speed: Velocity,
     current_predictions: &HashMap<Station, CurrentPrediction<5>>,
+    time_origin: i64,
     start_time_idx: usize,
     nn_calc: &mut NearestNeighborCalculator,
-) -> Option<StepResult> {
-    // TODO: derive from argument
-    let internal_time_step =
-        Time::new::<minute>(CurrentPrediction::<5>::resolution_minutes() as f64);
+    max_time: Option<Time>,
+    max_station_distance: Length,
+    mut path: Option<&mut Vec<(f64, f64)>>,
+    tide_speed: Option<(&TideHeights, TideSpeedProfile)>,
+) -> Result<StepResult, TripError> {
+    let internal_time_step = Time::new::<millisecond>(time_idx::STEP_MILLIS as f64);
 
-    if matches!(end.type_, WaypointType::Pause) {
-        return Some(StepResult {
+    if let WaypointType::Pause(duration) = end.type_ {
+        let time_steps = (duration / internal_time_step).value.round() as usize;
+        if let Some(path) = path {
+            path.extend(iter::repeat((start.lat(), start.lon())).take(time_steps));
+        }
+        return Ok(StepResult {
             distance: Length::new::<meter>(0.0),
-            time: Time::new::<hour>(0.5),
-            time_steps: (Time::new::<hour>(0.5) / internal_time_step).value as usize,
+            time: duration,
+            time_steps,
+            assisted_time: Time::new::<hour>(0.0),
+            far_station_steps: 0,
         });
     }
 
@@ -97,31 +326,70 @@ pub fn calculate_step(
     let mut distance_remaining = Length::new::<meter>(delta.slant_range().as_metres());
     let mut total_time = Time::new::<hour>(0.0);
     let mut total_distance = Length::new::<meter>(0.0);
+    let mut assisted_time = Time::new::<hour>(0.0);
+    let mut far_station_steps = 0;
+    // Whether the last step made no progress, in which case running out of
+    // time is attributed to the current.
+    let mut stalled = false;
+    let stalled_or = |stalled: bool, err: TripError| {
+        if stalled {
+            TripError::ImpassableCurrent
+        } else {
+            err
+        }
+    };
 
     while distance_remaining > Length::new::<meter>(0.0) {
+        if max_time.is_some_and(|max_time| total_time > max_time) {
+            return Err(stalled_or(stalled, TripError::ExceededMaxDuration));
+        }
+
         let l_frame = LocalFrame::local_level(delta.azimuth(), step_start, Ellipsoid::WGS84);
 
         let ll_step_start = LatLong::from_nvector(step_start.horizontal_position());
-        let station = nn_calc.nearest_neighbor(ll_step_start);
-        let prediction = &current_predictions[&station];
-
-        if time_idx >= prediction.df.height() {
-            return None;
+        if let Some(path) = path.as_deref_mut() {
+            path.push((
+                ll_step_start.latitude().as_degrees(),
+                ll_step_start.longitude().as_degrees(),
+            ));
         }
+        let Some(station) = nn_calc.nearest_neighbor(ll_step_start) else {
+            return Err(stalled_or(stalled, TripError::ExceededData));
+        };
+        let prediction = &current_predictions[&station];
 
-        let current_speed = prediction.df["speed"].f64().unwrap().get(time_idx).unwrap();
+        let time = time_origin + time_idx as i64 * time_idx::STEP_MILLIS;
+        // Stations' predictions are null where they don't cover the time grid.
+        let current = prediction.time_idx(time).and_then(|idx| {
+            Some((
+                prediction.df["speed"].f64().ok()?.get(idx)?,
+                prediction.df["direction"].f64().ok()?.get(idx)?,
+            ))
+        });
+        let Some((current_speed, current_direction)) = current else {
+            return Err(stalled_or(stalled, TripError::ExceededData));
+        };
+        let current_direction = Angle::from_degrees(current_direction);
 
-        let current_direction = Angle::from_degrees(
-            prediction.df["direction"]
-                .f64()
-                .unwrap()
-                .get(time_idx)
-                .unwrap(),
-        );
+        if station_distance(step_start, &station) > max_station_distance {
+            far_station_steps += 1;
+        }
 
         let angle_delta = step_remaining_delta.azimuth() - current_direction;
         let angle_delta_cos = angle_delta.as_radians().cos();
-        let net_speed = base_speed + angle_delta_cos * Velocity::new::<knot>(current_speed);
+        let current_component = angle_delta_cos * Velocity::new::<knot>(current_speed);
+        let paddling_speed = match tide_speed {
+            Some((heights, profile)) => heights
+                .at(time)
+                .map_or(base_speed, |height| base_speed * profile.factor(height)),
+            None => base_speed,
+        };
+        let net_speed = paddling_speed + current_component;
+        stalled = net_speed <= Velocity::new::<knot>(0.0);
+
+        if current_component > Velocity::new::<knot>(0.0) {
+            assisted_time += internal_time_step;
+        }
 
         let step_distance = internal_time_step * net_speed;
         distance_remaining -= step_distance;
@@ -136,19 +404,57 @@ pub fn calculate_step(
         total_distance += step_distance;
     }
 
-    Some(StepResult {
+    Ok(StepResult {
         distance: total_distance,
         time: total_time,
         time_steps: time_idx - start_time_idx,
+        assisted_time,
+        far_station_steps,
     })
 }
 
+/// Fraction of the moving (non-pause) time in `steps` spent with a following
+/// current.
+fn following_current_fraction(steps: &[StepResult]) -> f64 {
+    let moving_time: Time = steps
+        .iter()
+        .filter(|s| s.distance > Length::new::<meter>(0.0))
+        .map(|s| s.time)
+        .sum();
+    let assisted_time: Time = steps.iter().map(|s| s.assisted_time).sum();
+
+    if moving_time > Time::new::<hour>(0.0) {
+        (assisted_time / moving_time).value
+    } else {
+        0.0
+    }
+}
+
+/// Whether a trip departing at `departure` and taking `duration` leaves after
+/// 8:00 and arrives before 21:00.
+fn within_daytime(departure: NaiveDateTime, duration: Time) -> bool {
+    departure.hour() >= 8
+        && departure + TimeDelta::seconds(duration.get::<second>() as i64)
+            < departure.date().and_hms_opt(21, 0, 0).unwrap()
+}
+
+/// The result of a trip departing at some time, or why it isn't feasible.
+pub type TripOutcome = Result<TripResult, TripError>;
+
 #[derive(Clone, Default, Debug)]
 pub struct TripResult {
+    /// One step per waypoint: an empty step for the departure, then the leg
+    /// into each following waypoint.
     pub steps: Vec<StepResult>,
 }
 
 impl TripResult {
+    /// The legs, without the departure step, so `legs()[i]` is the leg from
+    /// waypoint `i` to waypoint `i + 1`.
+    pub fn legs(&self) -> &[StepResult] {
+        self.steps.get(1..).unwrap_or_default()
+    }
+
     pub fn distance(&self) -> Length {
         self.steps.iter().map(|s| s.distance).sum()
     }
@@ -156,6 +462,87 @@ impl TripResult {
     pub fn time(&self) -> Time {
         self.steps.iter().map(|s| s.time).sum()
     }
+
+    /// Total time excluding pauses, whose legs cover no distance.
+    pub fn moving_time(&self) -> Time {
+        self.steps
+            .iter()
+            .filter(|s| s.distance.value > 0.0)
+            .map(|s| s.time)
+            .sum()
+    }
+
+    /// Average speed made good over the moving time, or `None` if the trip
+    /// doesn't move.
+    pub fn average_speed(&self) -> Option<Velocity> {
+        let moving_time = self.moving_time();
+        (moving_time.value > 0.0).then(|| self.distance() / moving_time)
+    }
+
+    /// One row per waypoint (the first row being the departure, with no
+    /// speed), in SI units.
+    pub fn to_dataframe(
+        &self,
+        waypoints: &[Waypoint],
+        start_time: NaiveDateTime,
+        coordinate_system: CoordinateSystem,
+    ) -> Result<DataFrame> {
+        let (a_name, b_name) = coordinate_system.axis_names();
+        let (a, b): (Vec<f64>, Vec<f64>) = waypoints
+            .iter()
+            .map(|w| coordinate_system.coordinates(w))
+            .unzip();
+        let cumulative_times: Vec<Time> = self
+            .steps
+            .iter()
+            .scan(Time::new::<hour>(0.0), |cumulative_time, s| {
+                *cumulative_time += s.time;
+                Some(*cumulative_time)
+            })
+            .collect();
+
+        Ok(DataFrame::new(vec![
+            Series::new("leg", (0..self.steps.len() as u64).collect::<Vec<u64>>()),
+            Series::new(a_name, a),
+            Series::new(b_name, b),
+            Series::new(
+                "distance",
+                self.steps
+                    .iter()
+                    .map(|s| s.distance.get::<meter>())
+                    .collect::<Vec<f64>>(),
+            ),
+            Series::new(
+                "time",
+                self.steps
+                    .iter()
+                    .map(|s| s.time.get::<second>())
+                    .collect::<Vec<f64>>(),
+            ),
+            Series::new(
+                "speed",
+                self.steps
+                    .iter()
+                    .map(|s| (s.time.value > 0.0).then(|| s.speed().get::<meter_per_second>()))
+                    .collect::<Vec<Option<f64>>>(),
+            ),
+            Series::new(
+                "cumulative_time",
+                cumulative_times
+                    .iter()
+                    .map(|t| t.get::<second>())
+                    .collect::<Vec<f64>>(),
+            ),
+            Series::new(
+                "eta",
+                cumulative_times
+                    .iter()
+                    .map(|t| start_time + TimeDelta::seconds(t.get::<second>() as i64))
+                    .collect::<Vec<NaiveDateTime>>(),
+            ),
+        ])
+        .log()?)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -172,19 +559,56 @@ impl NearestNeighborCalculator {
         }
     }
 
-    pub fn nearest_neighbor(&mut self, point: LatLong) -> Station {
+    /// Returns the station nearest to `point`, or `None` if there are no
+    /// stations.
+    pub fn nearest_neighbor(&mut self, point: LatLong) -> Option<Station> {
         let lat = point.latitude().as_degrees();
         let lon = point.longitude().as_degrees();
 
         if let Some(p) = self.cache.get(&(OrderedFloat(lat), OrderedFloat(lon))) {
-            return p.clone();
+            return Some(p.clone());
         }
 
-        let station = self.tree.nearest_neighbor(&[lat, lon]).unwrap();
+        let station = self.tree.nearest_neighbor(&[lat, lon])?;
         self.cache
             .put((OrderedFloat(lat), OrderedFloat(lon)), station.clone());
-        station.clone()
+        Some(station.clone())
+    }
+}
+
+/// Reindexes every prediction onto `time_grid`, in milliseconds since the
+/// epoch, so that a shared time index refers to the same time in every frame.
+/// Rows a station has no prediction for are null.
+fn align_predictions<const R: u8>(
+    mut predictions: Vec<CurrentPrediction<R>>,
+    time_grid: &[i64],
+) -> Result<Vec<CurrentPrediction<R>>> {
+    let time = Series::new("time", time_grid)
+        .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+        .log()?;
+    let grid = DataFrame::new(vec![time]).log()?;
+
+    for prediction in &mut predictions {
+        prediction.df = grid
+            .clone()
+            .lazy()
+            .join(
+                mem::take(&mut prediction.df).lazy(),
+                [col("time")],
+                [col("time")],
+                JoinArgs::new(JoinType::Left),
+            )
+            .collect()
+            .log()?;
+        if !prediction.df["time"].equals(&grid["time"]) {
+            return Err(anyhow!(
+                "Failed to align the predictions for {:?}",
+                prediction.station.id
+            ));
+        }
     }
+
+    Ok(predictions)
 }
 
 #[derive(Clone)]
@@ -196,10 +620,38 @@ pub struct Trip {
     pub stations: Vec<Station>,
     pub current_predictions_30m: HashMap<Station, CurrentPrediction<30>>,
     pub current_predictions_5m: HashMap<Station, CurrentPrediction<5>>,
+    /// The tide station's 30 minute prediction times, in milliseconds since
+    /// the epoch, which the UI's time indices refer to. Current predictions
+    /// are aligned to them.
+    time_grid: Vec<i64>,
+    /// Time of 5 minute time index 0, in milliseconds since the epoch.
+    time_origin: i64,
     pub weekdays: WeekdayFlags,
+    /// The only day the sweep considers departures on, in place of
+    /// `weekdays`, if set.
+    pub sweep_date: Option<NaiveDate>,
     pub daytime: bool,
-    results: HashMap<usize, Option<TripResult>>,
+    pub max_trip_duration: Option<Time>,
+    /// Currents sampled from stations farther than this are flagged as low
+    /// confidence.
+    pub max_station_distance: Length,
+    pub sweep_quantile_method: QuantileMethod,
+    /// How predictions are resampled from 30 to 5 minutes.
+    pub speed_interpolation: SpeedInterpolation,
+    pub snap_to_stations: bool,
+    /// Whether currents are sampled from subordinate stations, whose
+    /// predictions are less accurate, as well as harmonic ones.
+    pub subordinate_stations: bool,
+    /// The tide station's heights, for `tide_speed_profile`.
+    pub tide_heights: Option<TideHeights>,
+    /// Experimental: scales the paddling speed by the tide height, if set and
+    /// `tide_heights` were fetched.
+    pub tide_speed_profile: Option<TideSpeedProfile>,
+    pub waypoint_warning: Option<String>,
+    results: HashMap<usize, TripOutcome>,
     sweep_result: Option<DataFrame>,
+    /// Incremented whenever the cached results are cleared.
+    revision: u64,
     nn_calc: NearestNeighborCalculator,
 }
 
@@ -210,7 +662,55 @@ impl Trip {
             RwLock<FeatureLayer<Point2d, Waypoint, WaypointSymbol, CartesianSpace2d>>,
         >,
         current_predictions_30m: Vec<CurrentPrediction<30>>,
+        max_station_distance: Length,
+        sweep_quantile_method: QuantileMethod,
+        time_grid: Vec<i64>,
     ) -> Result<Self> {
+        let mut trip = Self {
+            waypoints: Vec::new(),
+            speed,
+            waypoint_layer,
+            stations: Vec::new(),
+            current_predictions_30m: HashMap::new(),
+            current_predictions_5m: HashMap::new(),
+            time_origin: time_grid.first().copied().unwrap_or(0),
+            time_grid,
+            weekdays: WeekdayFlags::empty(),
+            sweep_date: None,
+            daytime: false,
+            max_trip_duration: None,
+            max_station_distance,
+            sweep_quantile_method,
+            speed_interpolation: SpeedInterpolation::default(),
+            snap_to_stations: false,
+            subordinate_stations: true,
+            tide_heights: None,
+            tide_speed_profile: None,
+            waypoint_warning: None,
+            results: HashMap::new(),
+            sweep_result: None,
+            revision: 0,
+            nn_calc: NearestNeighborCalculator::new(&[]),
+        };
+        trip.set_current_predictions(current_predictions_30m)?;
+        Ok(trip)
+    }
+
+    /// `prediction` reindexed onto the trip's time grid, like the trip's own
+    /// predictions, so that a time index refers to the same time in it.
+    pub fn align_prediction(
+        &self,
+        prediction: CurrentPrediction<30>,
+    ) -> Result<CurrentPrediction<30>> {
+        let mut aligned = align_predictions(vec![prediction], &self.time_grid)?;
+        Ok(aligned.remove(0))
+    }
+
+    /// Replaces the current predictions, e.g. once they have all been fetched.
+    pub fn set_current_predictions(
+        &mut self,
+        current_predictions_30m: Vec<CurrentPrediction<30>>,
+    ) -> Result<()> {
         let mut stations: Vec<Station> = current_predictions_30m
             .iter()
             .map(|p| p.station.clone())
@@ -223,10 +723,18 @@ impl Trip {
             )
         });
 
+        let current_predictions_30m = align_predictions(current_predictions_30m, &self.time_grid)?;
+        assert!(
+            current_predictions_30m
+                .windows(2)
+                .all(|pair| pair[0].df["time"].equals(&pair[1].df["time"])),
+            "Aligned predictions have different times"
+        );
+
         let current_predictions_5m: Vec<CurrentPrediction<5>> = current_predictions_30m
             .iter()
             .fallible()
-            .map(CurrentPrediction::resampled::<5>)
+            .map(|p| p.resampled::<5>(self.speed_interpolation))
             .collect()?;
 
         let current_predictions_30m = HashMap::from_iter(
@@ -241,28 +749,82 @@ impl Trip {
                 .map(|p| (p.station.clone(), p)),
         );
 
-        Ok(Self {
-            waypoints: Vec::new(),
-            speed,
-            waypoint_layer,
-            stations: stations.clone(),
-            current_predictions_30m,
-            current_predictions_5m,
-            weekdays: WeekdayFlags::empty(),
-            daytime: false,
-            results: HashMap::new(),
-            sweep_result: None,
-            nn_calc: NearestNeighborCalculator::new(&stations),
-        })
+        self.stations = stations;
+        self.current_predictions_30m = current_predictions_30m;
+        self.current_predictions_5m = current_predictions_5m;
+        self.update_nn_calc();
+        self.clear_cache();
+        Ok(())
+    }
+
+    /// Rebuilds the nearest station lookup from the stations currents are
+    /// sampled from.
+    fn update_nn_calc(&mut self) {
+        let stations: Vec<Station> = self
+            .stations
+            .iter()
+            .filter(|station| {
+                self.subordinate_stations || !matches!(station.type_, StationType::Subordinate)
+            })
+            .cloned()
+            .collect();
+        self.nn_calc = NearestNeighborCalculator::new(&stations);
     }
 
     fn clear_cache(&mut self) {
         self.results.clear();
         self.sweep_result = None;
+        self.revision += 1;
     }
 
-    pub fn add_waypoint(&mut self, waypoint: Waypoint) {
-        self.waypoints.push(waypoint);
+    /// Changes whenever the waypoints or a setting that affects the trip's
+    /// results change, so results computed outside the trip, like a replay's
+    /// track, can tell when they're stale.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Warns if `waypoint` is far from every station, since currents are
+    /// sampled from the nearest station. If `snap_to_stations` is set, instead
+    /// moves it toward the nearest station until it is close enough.
+    fn check_station_distance(&mut self, idx: usize, waypoint: &mut Waypoint) {
+        let nvector = NVector::from_lat_long_degrees(waypoint.lat(), waypoint.lon());
+        let Some(station) = self
+            .nn_calc
+            .nearest_neighbor(LatLong::from_nvector(nvector))
+        else {
+            return;
+        };
+        let distance = station_distance(GeodeticPos::new(nvector, jLength::ZERO), &station);
+        let max_distance = self.max_station_distance;
+
+        if distance <= max_distance {
+            self.waypoint_warning = None;
+        } else if self.snap_to_stations {
+            let proj: Box<dyn Projection<InPoint = GeoPoint2d, OutPoint = Point2d>> =
+                Crs::EPSG3857.get_projection().unwrap();
+            let station_point = proj.project(&station.loc).unwrap();
+            let ratio: Ratio = max_distance / distance;
+            waypoint.point = Point2d::new(
+                station_point.x + (waypoint.point.x - station_point.x) * ratio.value,
+                station_point.y + (waypoint.point.y - station_point.y) * ratio.value,
+            );
+            self.waypoint_warning = None;
+        } else {
+            let warning = format!(
+                "Waypoint {} is {:.1} from the nearest current station, {}. Currents there may be inaccurate.",
+                idx,
+                distance.into_format_args(mile, Abbreviation),
+                station.name,
+            );
+            warn!("{warning}");
+            self.waypoint_warning = Some(warning);
+        }
+    }
+
+    pub fn add_waypoint(&mut self, mut waypoint: Waypoint) {
+        self.check_station_distance(self.waypoints.len(), &mut waypoint);
+        self.waypoints.push(waypoint.clone());
         self.waypoint_layer
             .write()
             .unwrap()
@@ -271,6 +833,83 @@ impl Trip {
         self.clear_cache();
     }
 
+    /// Appends `waypoints` in order, clearing the cache once rather than per
+    /// waypoint.
+    pub fn add_waypoints(&mut self, waypoints: Vec<Waypoint>) {
+        let first_idx = self.waypoints.len();
+        let waypoints: Vec<Waypoint> = waypoints
+            .into_iter()
+            .enumerate()
+            .map(|(i, mut waypoint)| {
+                self.check_station_distance(first_idx + i, &mut waypoint);
+                waypoint
+            })
+            .collect();
+        let mut layer = self.waypoint_layer.write().unwrap();
+        let feature_store = layer.features_mut();
+        for waypoint in waypoints {
+            self.waypoints.push(waypoint.clone());
+            feature_store.insert(waypoint);
+        }
+        drop(layer);
+        self.clear_cache();
+    }
+
+    /// Appends a movement waypoint that continues the last leg with the same
+    /// bearing and distance. Does nothing and returns false if there are
+    /// fewer than two waypoints.
+    pub fn extend_route(&mut self) -> bool {
+        let [.., a, b] = &self.waypoints[..] else {
+            return false;
+        };
+
+        let a = GeodeticPos::new(
+            NVector::from_lat_long_degrees(a.lat(), a.lon()),
+            jLength::ZERO,
+        );
+        let b = GeodeticPos::new(
+            NVector::from_lat_long_degrees(b.lat(), b.lon()),
+            jLength::ZERO,
+        );
+        let delta = LocalFrame::ned(a, Ellipsoid::WGS84).geodetic_to_local_pos(b);
+        let l_frame = LocalFrame::local_level(delta.azimuth(), b, Ellipsoid::WGS84);
+        let end = l_frame.local_to_geodetic_pos(LocalPositionVector::from_metres(
+            delta.slant_range().as_metres(),
+            0.0,
+            0.0,
+        ));
+        let end = LatLong::from_nvector(end.horizontal_position());
+
+        let proj: Box<dyn Projection<InPoint = GeoPoint2d, OutPoint = Point2d>> =
+            Crs::EPSG3857.get_projection().unwrap();
+        let Some(point) = proj.project(&GeoPoint2d::latlon(
+            end.latitude().as_degrees(),
+            end.longitude().as_degrees(),
+        )) else {
+            return false;
+        };
+
+        self.add_waypoint(Waypoint {
+            point,
+            type_: WaypointType::Move,
+            label: None,
+        });
+        true
+    }
+
+    /// Change in bearing, from 0 to 180 degrees, between the legs into and out
+    /// of waypoint `idx`, or `None` if it doesn't join two legs of nonzero
+    /// length.
+    pub fn turn_angle(&self, idx: usize) -> Option<f64> {
+        let prev = self.waypoints.get(idx.checked_sub(1)?)?;
+        let waypoint = self.waypoints.get(idx)?;
+        let next = self.waypoints.get(idx + 1)?;
+        // The bearing back along the incoming leg, measured at the waypoint.
+        let back = bearing(waypoint, prev)?;
+        let out = bearing(waypoint, next)?;
+        Some(((out - back).rem_euclid(360.0) - 180.0).abs())
+    }
+
     pub fn remove_waypoint(&mut self, idx: usize) {
         self.waypoints.remove(idx);
         self.waypoint_layer
@@ -281,12 +920,73 @@ impl Trip {
         self.clear_cache();
     }
 
+    /// Removes the first waypoint labeled `label` and returns its index.
+    pub fn remove_waypoint_by_label(&mut self, label: &str) -> Result<usize> {
+        let idx = self
+            .waypoints
+            .iter()
+            .position(|waypoint| waypoint.label.as_deref() == Some(label))
+            .ok_or_else(|| anyhow!("No waypoint labeled {label:?}"))?;
+        self.remove_waypoint(idx);
+        Ok(idx)
+    }
+
+    pub fn set_waypoint_label(&mut self, idx: usize, label: Option<String>) {
+        self.waypoints[idx].label = label;
+        self.reload_waypoint_layer();
+    }
+
     pub fn clear_waypoints(&mut self) {
         self.waypoints.clear();
+        self.waypoint_warning = None;
         features::clear_features(self.waypoint_layer.clone());
         self.clear_cache();
     }
 
+    pub fn move_waypoint(&mut self, idx: usize, point: Point2d) {
+        self.waypoints[idx].point = point;
+        self.reload_waypoint_layer();
+        self.clear_cache();
+    }
+
+    /// Features are only re-rendered when they are inserted, so edits to
+    /// existing waypoints require re-inserting all of them.
+    pub fn reload_waypoint_layer(&self) {
+        features::clear_features(self.waypoint_layer.clone());
+        let mut layer = self.waypoint_layer.write().unwrap();
+        let feature_store = layer.features_mut();
+        for waypoint in &self.waypoints {
+            feature_store.insert(waypoint.clone());
+        }
+    }
+
+    /// Returns an empty route with the same settings, drawn with `symbol`.
+    pub fn new_route(&self, symbol: WaypointSymbol) -> Self {
+        let mut route = self.clone();
+        route.waypoints.clear();
+        route.waypoint_layer = Arc::new(RwLock::new(FeatureLayer::new(
+            vec![],
+            symbol,
+            Crs::EPSG3857,
+        )));
+        route.waypoint_warning = None;
+        route.clear_cache();
+        route
+    }
+
+    /// Copies the settings that affect trip results from `other`.
+    pub fn copy_settings(&mut self, other: &Trip) {
+        if self.speed != other.speed {
+            self.set_speed(other.speed);
+        }
+        self.set_weekdays(other.weekdays);
+        self.set_sweep_date(other.sweep_date);
+        self.set_daytime(other.daytime);
+        self.set_max_trip_duration(other.max_trip_duration);
+        self.set_subordinate_stations(other.subordinate_stations);
+        self.set_tide_speed_profile(other.tide_speed_profile);
+    }
+
     pub fn set_speed(&mut self, speed: Velocity) {
         self.speed = speed;
         self.clear_cache()
@@ -299,6 +999,13 @@ impl Trip {
         }
     }
 
+    pub fn set_sweep_date(&mut self, sweep_date: Option<NaiveDate>) {
+        if self.sweep_date != sweep_date {
+            self.sweep_date = sweep_date;
+            self.clear_cache();
+        }
+    }
+
     pub fn set_daytime(&mut self, daytime: bool) {
         if self.daytime != daytime {
             self.daytime = daytime;
@@ -306,11 +1013,45 @@ impl Trip {
         }
     }
 
-    pub fn calculate(&mut self, mut start_time_idx: usize) -> Option<TripResult> {
+    pub fn set_subordinate_stations(&mut self, subordinate_stations: bool) {
+        if self.subordinate_stations != subordinate_stations {
+            self.subordinate_stations = subordinate_stations;
+            self.update_nn_calc();
+            self.clear_cache();
+        }
+    }
+
+    pub fn set_tide_speed_profile(&mut self, tide_speed_profile: Option<TideSpeedProfile>) {
+        if self.tide_speed_profile != tide_speed_profile {
+            self.tide_speed_profile = tide_speed_profile;
+            self.clear_cache();
+        }
+    }
+
+    pub fn set_max_trip_duration(&mut self, max_trip_duration: Option<Time>) {
+        if self.max_trip_duration != max_trip_duration {
+            self.max_trip_duration = max_trip_duration;
+            self.clear_cache();
+        }
+    }
+
+    pub fn calculate(&mut self, mut start_time_idx: usize) -> TripOutcome {
+        // Not memoized, so arbitrary indices don't grow the cache.
+        let num_time_steps = self
+            .current_predictions_5m
+            .values()
+            .map(|prediction| prediction.df.height())
+            .min()
+            .unwrap_or(0);
+        if start_time_idx >= num_time_steps {
+            return Err(TripError::StartOutOfRange);
+        }
+
         self.results
             .entry(start_time_idx)
             .or_insert_with(|| {
                 let mut steps: Vec<StepResult> = vec![StepResult::default()];
+                let mut total_time = Time::new::<hour>(0.0);
 
                 for (a, b) in self.waypoints[..].iter().tuple_windows() {
                     let res = calculate_step(
@@ -318,45 +1059,465 @@ impl Trip {
                         b,
                         self.speed,
                         &self.current_predictions_5m,
+                        self.time_origin,
                         start_time_idx,
                         &mut self.nn_calc,
-                    );
-                    if let Some(res) = res {
-                        start_time_idx += res.time_steps;
-                        steps.push(res)
-                    } else {
-                        return None;
+                        self.max_trip_duration
+                            .map(|max_trip_duration| max_trip_duration - total_time),
+                        self.max_station_distance,
+                        None,
+                        self.tide_heights.as_ref().zip(self.tide_speed_profile),
+                    )?;
+                    start_time_idx += res.time_steps;
+                    total_time += res.time;
+                    steps.push(res);
+
+                    if self
+                        .max_trip_duration
+                        .is_some_and(|max_trip_duration| total_time > max_trip_duration)
+                    {
+                        return Err(TripError::ExceededMaxDuration);
                     }
                 }
 
-                Some(TripResult { steps })
+                Ok(TripResult { steps })
             })
             .clone()
     }
 
+    /// The path, as latitude and longitude, of each leg of the trip departing
+    /// at the 5 minute `start_time_idx`: the position at the start of each of
+    /// the leg's 5 minute steps, beginning with the leg's start, followed by
+    /// the leg's end. Not memoized.
+    pub fn leg_paths(
+        &mut self,
+        mut start_time_idx: usize,
+    ) -> Result<Vec<Vec<(f64, f64)>>, TripError> {
+        let mut paths = vec![];
+        let mut total_time = Time::new::<hour>(0.0);
+        for (a, b) in self.waypoints[..].iter().tuple_windows() {
+            let mut path = vec![];
+            let res = calculate_step(
+                a,
+                b,
+                self.speed,
+                &self.current_predictions_5m,
+                self.time_origin,
+                start_time_idx,
+                &mut self.nn_calc,
+                self.max_trip_duration
+                    .map(|max_trip_duration| max_trip_duration - total_time),
+                self.max_station_distance,
+                Some(&mut path),
+                self.tide_heights.as_ref().zip(self.tide_speed_profile),
+            )?;
+            start_time_idx += res.time_steps;
+            total_time += res.time;
+
+            // A leg that takes no steps, having no length, is just its end.
+            path.push((b.lat(), b.lon()));
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+
+    /// Position, as latitude and longitude, at each 5 minute step of the trip
+    /// departing at the 5 minute `start_time_idx`, ending with the last
+    /// waypoint. Not memoized.
+    pub fn track(&mut self, start_time_idx: usize) -> Result<Vec<(f64, f64)>, TripError> {
+        let paths = self.leg_paths(start_time_idx)?;
+        let mut track: Vec<_> = paths
+            .iter()
+            .flat_map(|path| &path[..path.len() - 1])
+            .copied()
+            .collect();
+        if let Some(last) = self.waypoints.last() {
+            track.push((last.lat(), last.lon()));
+        }
+        Ok(track)
+    }
+
+    /// Calculates the trip departing at the 30 minute `time_idx`, also
+    /// checking the daytime window when `daytime` is set.
+    pub fn calculate_departure(&mut self, time_idx: usize) -> TripOutcome {
+        self.calculate_departure_5m(time_idx::row_to_step(time_idx))
+    }
+
+    /// Like `calculate_departure`, but departing at the 5 minute
+    /// `start_time_idx`.
+    pub fn calculate_departure_5m(&mut self, start_time_idx: usize) -> TripOutcome {
+        let trip_result = self.calculate(start_time_idx)?;
+
+        match self.time_at_5m(start_time_idx) {
+            Some(departure) if self.daytime && !within_daytime(departure, trip_result.time()) => {
+                Err(TripError::OutsideDaytime)
+            },
+            _ => Ok(trip_result),
+        }
+    }
+
+    /// Like `calculate_departure_5m`, but departing at the 5 minute step
+    /// nearest `start`, a time in the predictions' time zone, so callers
+    /// needn't map times to indices themselves.
+    pub fn plan_at(&mut self, start: NaiveDateTime) -> TripOutcome {
+        let start = start.and_utc().timestamp_millis();
+        let start_time_idx = time_idx::nearest_step_in_range(&self.time_grid, start)
+            .ok_or(TripError::StartOutOfRange)?;
+        self.calculate_departure_5m(start_time_idx)
+    }
+
+    /// The current at each waypoint when `trip_result`, departing at the 5
+    /// minute `start_time_idx`, arrives there, as the speed and direction in
+    /// degrees from the station nearest the waypoint. `None` where no
+    /// prediction covers the arrival.
+    pub fn arrival_currents(
+        &mut self,
+        start_time_idx: usize,
+        trip_result: &TripResult,
+    ) -> Vec<Option<(Velocity, f64)>> {
+        let mut arrival_idx = start_time_idx;
+        self.waypoints
+            .iter()
+            .zip(&trip_result.steps)
+            .map(|(waypoint, step)| {
+                arrival_idx += step.time_steps;
+                let nvector = NVector::from_lat_long_degrees(waypoint.lat(), waypoint.lon());
+                let station = self
+                    .nn_calc
+                    .nearest_neighbor(LatLong::from_nvector(nvector))?;
+                let prediction = self.current_predictions_5m.get(&station)?;
+                let row = prediction
+                    .time_idx(self.time_origin + arrival_idx as i64 * time_idx::STEP_MILLIS)?;
+                let speed = prediction.df["speed"].f64().ok()?.get(row)?;
+                let direction = prediction.df["direction"].f64().ok()?.get(row)?;
+                Some((Velocity::new::<knot>(speed), direction))
+            })
+            .collect()
+    }
+
+    /// Returns the 30 minute time index in `time_idxs` with the shortest
+    /// feasible trip, along with that trip's duration.
+    pub fn best_departure(&mut self, time_idxs: Range<usize>) -> Option<(usize, Time)> {
+        time_idxs
+            .filter_map(|idx| Some((idx, self.calculate_departure(idx).ok()?.time())))
+            .min_by(|(_, a), (_, b)| a.value.total_cmp(&b.value))
+    }
+
+    /// Index of the waypoint farthest from the first waypoint, where an out and
+    /// back trip turns around.
+    fn turnaround_idx(&self) -> Option<usize> {
+        let first = self.waypoints.first()?;
+        let start = GeodeticPos::new(
+            NVector::from_lat_long_degrees(first.lat(), first.lon()),
+            jLength::ZERO,
+        );
+        let ned = LocalFrame::ned(start, Ellipsoid::WGS84);
+
+        self.waypoints
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, waypoint)| {
+                let pos = GeodeticPos::new(
+                    NVector::from_lat_long_degrees(waypoint.lat(), waypoint.lon()),
+                    jLength::ZERO,
+                );
+                OrderedFloat(ned.geodetic_to_local_pos(pos).slant_range().as_metres())
+            })
+            .map(|(i, _)| i)
+    }
+
+    /// Scores every feasible departure by the fraction of moving time spent
+    /// with a following current on the outbound and return halves of an out
+    /// and back trip. Returns the best `count` as (30 minute time index,
+    /// outbound fraction, return fraction).
+    pub fn ebb_out_flood_back(&mut self, count: usize) -> Vec<(usize, f64, f64)> {
+        let Some(turnaround_idx) = self.turnaround_idx() else {
+            return vec![];
+        };
+        if turnaround_idx == 0 || turnaround_idx == self.waypoints.len() - 1 {
+            return vec![];
+        }
+
+        // Not limited to the sweep, which keeps only the fastest departures on
+        // the selected days.
+        let num_rows = self
+            .current_predictions_30m
+            .values()
+            .map(|prediction| prediction.df.height())
+            .min()
+            .unwrap_or(0);
+
+        let mut suggestions: Vec<_> = (0..num_rows)
+            .filter_map(|idx| {
+                let result = self.calculate(time_idx::row_to_step(idx)).ok()?;
+                if self.daytime && !within_daytime(self.time_at(idx)?, result.time()) {
+                    return None;
+                }
+                let (outbound, inbound) = result.legs().split_at(turnaround_idx);
+                Some((
+                    idx,
+                    following_current_fraction(outbound),
+                    following_current_fraction(inbound),
+                ))
+            })
+            .collect();
+
+        suggestions.sort_by_key(|(idx, outbound, inbound)| {
+            (Reverse(OrderedFloat(outbound.min(*inbound))), *idx)
+        });
+        suggestions.truncate(count);
+        suggestions
+    }
+
+    /// Returns the 30 minute index of the nearest slack (local minimum of
+    /// speed) or max current (local maximum of speed) at `station` strictly
+    /// after, or if `forward` is false strictly before, `time_idx`.
+    pub fn next_current_extremum(
+        &self,
+        station: &Station,
+        time_idx: usize,
+        extremum: CurrentExtremum,
+        forward: bool,
+    ) -> Option<usize> {
+        let speeds: Vec<f64> = self.current_predictions_30m.get(station)?.df["speed"]
+            .f64()
+            .ok()?
+            .into_iter()
+            .map(|speed| speed.map_or(f64::NAN, f64::abs))
+            .collect();
+
+        let is_extremum = |idx: usize| {
+            let (prev, cur, next) = (speeds[idx - 1], speeds[idx], speeds[idx + 1]);
+            match extremum {
+                CurrentExtremum::Slack => cur <= prev && cur < next,
+                CurrentExtremum::Max => cur >= prev && cur > next,
+            }
+        };
+
+        let mut candidates = 1..speeds.len().saturating_sub(1);
+        if forward {
+            candidates.find(|idx| *idx > time_idx && is_extremum(*idx))
+        } else {
+            candidates
+                .rev()
+                .find(|idx| *idx < time_idx && is_extremum(*idx))
+        }
+    }
+
+    /// Returns the time of the 30 minute `time_idx`.
+    pub fn time_at(&self, time_idx: usize) -> Option<NaiveDateTime> {
+        let ts = self.current_predictions_30m.values().next()?.df["time"]
+            .datetime()
+            .ok()?
+            .get(time_idx)?;
+        Some(DateTime::from_timestamp_millis(ts)?.naive_utc())
+    }
+
+    /// Returns the time of the 5 minute `time_idx`.
+    pub fn time_at_5m(&self, time_idx: usize) -> Option<NaiveDateTime> {
+        let ts = self.current_predictions_5m.values().next()?.df["time"]
+            .datetime()
+            .ok()?
+            .get(time_idx)?;
+        Some(DateTime::from_timestamp_millis(ts)?.naive_utc())
+    }
+
+    /// Serializes the waypoints, settings, and trip result when departing at
+    /// the 5 minute `time_idx`.
+    pub fn to_plan_json(&mut self, time_idx: usize, coordinate_system: CoordinateSystem) -> Value {
+        let start_time = self.time_at_5m(time_idx);
+        let trip_result = self.calculate_departure_5m(time_idx);
+
+        let waypoints: Vec<Value> = self
+            .waypoints
+            .iter()
+            .map(|waypoint| {
+                let (a_name, b_name) = coordinate_system.axis_names();
+                let (a, b) = coordinate_system.coordinates(waypoint);
+                json!({
+                    "type": match waypoint.type_ {
+                        WaypointType::Move => "move",
+                        WaypointType::Pause(_) => "pause",
+                    },
+                    "label": waypoint.label,
+                    a_name: a,
+                    b_name: b,
+                })
+            })
+            .collect();
+
+        let mut plan = json!({
+            "version": PLAN_JSON_VERSION,
+            "settings": {
+                "speed": self.speed.get::<knot>(),
+                "units": {
+                    "distance": "mi",
+                    "time": "h",
+                    "speed": "kt",
+                },
+                "start_time": start_time.map(|t| t.format(PLAN_TIME_FORMAT).to_string()),
+                "weekdays": self.weekdays.iter_names().map(|(name, _)| name).collect::<Vec<_>>(),
+                "daytime": self.daytime,
+                "subordinate_stations": self.subordinate_stations,
+                "tide_speed_profile": self.tide_speed_profile.map(|profile| json!({
+                    "low_ft": profile.low,
+                    "high_ft": profile.high,
+                    "min_factor": profile.min_factor,
+                })),
+                "coordinate_system": format!("{coordinate_system:?}"),
+            },
+            "waypoints": waypoints,
+        });
+
+        let trip_result = match trip_result {
+            Ok(trip_result) => trip_result,
+            Err(err) => {
+                plan["error"] = json!(err.to_string());
+                return plan;
+            },
+        };
+        let arrival_currents = self.arrival_currents(time_idx, &trip_result);
+
+        let mut cumulative_time = Time::default();
+        // The first step is the zero-length step to the first waypoint.
+        let legs: Vec<Value> = trip_result
+            .steps
+            .iter()
+            .enumerate()
+            .skip(1)
+            .map(|(i, step)| {
+                cumulative_time += step.time;
+                let eta = start_time.map(|t| {
+                    (t + TimeDelta::seconds(cumulative_time.get::<second>() as i64))
+                        .format(PLAN_TIME_FORMAT)
+                        .to_string()
+                });
+                json!({
+                    "from": i - 1,
+                    "to": i,
+                    "distance": step.distance.get::<mile>(),
+                    "time": step.time.get::<hour>(),
+                    "speed": step.speed().get::<knot>(),
+                    "eta": eta,
+                    "arrival_current": arrival_currents[i].map(|(speed, direction)| json!({
+                        "speed": speed.get::<knot>(),
+                        "direction": direction,
+                    })),
+                    "low_confidence": step.low_confidence(),
+                })
+            })
+            .collect();
+
+        plan["legs"] = json!(legs);
+        plan["totals"] = json!({
+            "distance": trip_result.distance().get::<mile>(),
+            "time": trip_result.time().get::<hour>(),
+            "moving_time": trip_result.moving_time().get::<hour>(),
+            "average_speed": trip_result.average_speed().map(|speed| speed.get::<knot>()),
+        });
+
+        plan
+    }
+
+    /// Every station's 30 minute predictions on the common time grid, as a
+    /// `time` column and a `{id}_speed` and `{id}_direction` column per
+    /// station, in `stations` order.
+    pub fn current_field_dataframe(&self) -> Result<DataFrame> {
+        let mut field: Option<LazyFrame> = None;
+        for station in &self.stations {
+            let Some(prediction) = self.current_predictions_30m.get(station) else {
+                continue;
+            };
+            let columns = prediction.df.clone().lazy().select([
+                col("time"),
+                col("speed").alias(&format!("{}_speed", station.id)),
+                col("direction").alias(&format!("{}_direction", station.id)),
+            ]);
+            field = Some(match field {
+                Some(field) => field.join(
+                    columns,
+                    [col("time")],
+                    [col("time")],
+                    JoinArgs::new(JoinType::Left),
+                ),
+                None => columns,
+            });
+        }
+
+        let Some(field) = field else {
+            return Err(anyhow!("No current predictions have been loaded"));
+        };
+        Ok(field.sort(["time"], Default::default()).collect().log()?)
+    }
+
+    /// Serializes every station's current at the 30 minute `time_idx` as a
+    /// GeoJSON FeatureCollection of points.
+    pub fn currents_geojson(&self, time_idx: usize) -> Value {
+        let time = self
+            .time_at(time_idx)
+            .map(|t| t.format(PLAN_TIME_FORMAT).to_string());
+
+        let features: Vec<Value> = self
+            .stations
+            .iter()
+            .filter_map(|station| {
+                let pred = self.current_predictions_30m.get(station)?;
+                let speed = pred.df["speed"].f64().ok()?.get(time_idx)?;
+                let direction = pred.df["direction"].f64().ok()?.get(time_idx)?;
+                Some(json!({
+                    "type": "Feature",
+                    "geometry": {
+                        "type": "Point",
+                        "coordinates": [station.loc.lon(), station.loc.lat()],
+                    },
+                    "properties": {
+                        "id": station.id,
+                        "name": station.name,
+                        "type": match station.type_ {
+                            StationType::Harmonic => "harmonic",
+                            StationType::Subordinate => "subordinate",
+                        },
+                        "time": time,
+                        "speed": speed,
+                        "direction": direction,
+                        "observed": pred.is_observed(time_idx),
+                    },
+                }))
+            })
+            .collect();
+
+        json!({
+            "type": "FeatureCollection",
+            "features": features,
+        })
+    }
+
     pub fn sweep(&mut self) -> DataFrame {
         match &self.sweep_result {
             Some(sweep_result) => sweep_result.clone(),
             None => {
-                // TODO: derive from arguments
-                let internal_time_step = Time::new::<minute>(5.0);
-                let time_ratio: Ratio = Time::new::<minute>(30.0) / internal_time_step;
-                let mut time_idx_vec: Vec<(usize, NaiveDateTime)> =
-                    self.current_predictions_30m.values().next().unwrap().df["time"]
+                // No stations may have predictions, e.g. in a region without
+                // current stations.
+                let times = match self.current_predictions_30m.values().next() {
+                    Some(prediction) => prediction.df["time"]
                         .datetime()
                         .unwrap()
                         .to_vec_null_aware()
-                        .unwrap_left()
-                        .iter()
-                        .map(|ts| DateTime::from_timestamp_millis(*ts).unwrap().naive_utc())
-                        .enumerate()
-                        .filter(|(_, dt)| {
-                            self.weekdays.contains(
-                                WeekdayFlags::from_bits(1 << dt.weekday().num_days_from_monday())
-                                    .unwrap(),
-                            )
-                        })
-                        .collect();
+                        .unwrap_left(),
+                    None => vec![],
+                };
+                let mut time_idx_vec: Vec<(usize, NaiveDateTime)> = times
+                    .iter()
+                    .map(|ts| DateTime::from_timestamp_millis(*ts).unwrap().naive_utc())
+                    .enumerate()
+                    .filter(|(_, dt)| match self.sweep_date {
+                        Some(date) => dt.date() == date,
+                        None => self.weekdays.contains(
+                            WeekdayFlags::from_bits(1 << dt.weekday().num_days_from_monday())
+                                .unwrap(),
+                        ),
+                    })
+                    .collect();
 
                 if self.daytime {
                     time_idx_vec.retain(|(_, dt)| dt.hour() >= 8);
@@ -364,16 +1525,18 @@ impl Trip {
 
                 let mut trip_results: Vec<_> = time_idx_vec
                     .iter()
-                    .map(|(idx, dt)| (idx, dt, self.calculate(time_ratio.value as usize * idx)))
-                    .filter(|(_, _, result)| result.is_some())
-                    .map(|(i, dt, result)| (i, dt, result.unwrap()))
+                    .map(|(idx, dt)| (idx, dt, self.calculate(time_idx::row_to_step(*idx))))
+                    .filter_map(|(i, dt, result)| match result {
+                        Ok(result) => Some((i, dt, result)),
+                        Err(err) => {
+                            debug!("Skipping departure at {dt}: {err}");
+                            None
+                        },
+                    })
                     .collect();
 
                 if self.daytime {
-                    trip_results.retain(|(_, dt, result)| {
-                        (**dt + TimeDelta::seconds(result.time().get::<second>() as i64))
-                            < dt.date().and_hms_opt(21, 0, 0).unwrap()
-                    });
+                    trip_results.retain(|(_, dt, result)| within_daytime(**dt, result.time()));
                 }
 
                 let time_idx_vec: Vec<usize> = trip_results.iter().map(|(i, _, _)| **i).collect();
@@ -388,25 +1551,29 @@ impl Trip {
                     time_idx_vec.iter().map(|i| *i as u64).collect::<Vec<u64>>(),
                 );
                 let duration = Series::new("duration", trip_results.clone());
+                let mut df = DataFrame::new(vec![idx, duration]).unwrap();
 
-                let mut quant_df = DataFrame::new(vec![duration]).unwrap();
+                // The quantile is undefined if no departure was feasible.
+                if !trip_results.is_empty() {
+                    let duration = Series::new("duration", trip_results);
 
-                quant_df = quant_df
-                    .lazy()
-                    .with_columns([
-                        col("duration").quantile(lit(0.2), QuantileInterpolOptions::Nearest)
-                    ])
-                    .collect()
-                    .unwrap();
+                    let mut quant_df = DataFrame::new(vec![duration]).unwrap();
 
-                let percentile = quant_df["duration"].f64().unwrap().get(0).unwrap();
-                let duration = Series::new("duration", trip_results);
-                let mut df = DataFrame::new(vec![idx, duration]).unwrap();
-                df = df
-                    .lazy()
-                    .filter(col("duration").lt_eq(lit(percentile)))
-                    .collect()
-                    .unwrap();
+                    quant_df = quant_df
+                        .lazy()
+                        .with_columns([
+                            col("duration").quantile(lit(0.2), self.sweep_quantile_method.into())
+                        ])
+                        .collect()
+                        .unwrap();
+
+                    let percentile = quant_df["duration"].f64().unwrap().get(0).unwrap();
+                    df = df
+                        .lazy()
+                        .filter(col("duration").lt_eq(lit(percentile)))
+                        .collect()
+                        .unwrap();
+                }
 
                 self.sweep_result = Some(df);
                 self.sweep_result.as_ref().unwrap().clone()
@@ -414,3 +1581,518 @@ impl Trip {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use uom::si::time::minute;
+
+    use super::*;
+
+    fn leg(meters: f64, seconds: f64) -> StepResult {
+        StepResult {
+            distance: Length::new::<meter>(meters),
+            time: Time::new::<second>(seconds),
+            ..Default::default()
+        }
+    }
+
+    fn start_time() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 6, 1)
+            .unwrap()
+            .and_hms_opt(8, 0, 0)
+            .unwrap()
+    }
+
+    /// Milliseconds since the epoch `minutes` after `start_time`.
+    fn millis(minutes: i64) -> i64 {
+        (start_time() + TimeDelta::minutes(minutes))
+            .and_utc()
+            .timestamp_millis()
+    }
+
+    fn station(id: &str, lat: f64, lon: f64) -> Station {
+        Station {
+            id: id.to_string(),
+            name: id.to_string(),
+            loc: GeoPoint2d::latlon(lat, lon),
+            type_: StationType::Harmonic,
+            api_proxy: None,
+        }
+    }
+
+    /// `rows` predictions, `R` minutes apart, of a northward current of
+    /// `speed` knots starting `offset` minutes after `start_time`.
+    fn prediction<const R: u8>(
+        station: &Station,
+        offset: i64,
+        rows: usize,
+        speed: f64,
+    ) -> CurrentPrediction<R> {
+        let times: Vec<_> = (0..rows as i64)
+            .map(|i| start_time() + TimeDelta::minutes(offset + R as i64 * i))
+            .collect();
+        CurrentPrediction {
+            station: station.clone(),
+            df: df!(
+                "time" => times,
+                "speed" => vec![speed; rows],
+                "direction" => vec![0.0; rows],
+            )
+            .unwrap(),
+        }
+    }
+
+    fn trip(predictions: Vec<CurrentPrediction<30>>, time_grid: Vec<i64>) -> Trip {
+        let symbol =
+            WaypointSymbol::for_route(0, Arc::new(RwLock::new(false)), Arc::new(RwLock::new(None)));
+        Trip::new(
+            Velocity::new::<knot>(3.0),
+            Arc::new(RwLock::new(FeatureLayer::new(
+                vec![],
+                symbol,
+                Crs::EPSG3857,
+            ))),
+            predictions,
+            Length::new::<mile>(2.0),
+            QuantileMethod::default(),
+            time_grid,
+        )
+        .unwrap()
+    }
+
+    /// Integrates the leg from `lat` to about 890 m north of it, two 5 minute
+    /// steps at 3 knots in slack water.
+    fn leg_north(
+        lat: f64,
+        predictions: &HashMap<Station, CurrentPrediction<5>>,
+        time_origin: i64,
+        start_time_idx: usize,
+        nn_calc: &mut NearestNeighborCalculator,
+    ) -> Result<StepResult, TripError> {
+        calculate_step(
+            &Waypoint::at(lat, -74.02, WaypointType::Move).unwrap(),
+            &Waypoint::at(lat + 0.008, -74.02, WaypointType::Move).unwrap(),
+            Velocity::new::<knot>(3.0),
+            predictions,
+            time_origin,
+            start_time_idx,
+            nn_calc,
+            None,
+            Length::new::<mile>(2.0),
+            None,
+            None,
+        )
+    }
+
+    #[test]
+    fn to_dataframe_has_a_row_per_waypoint() {
+        let waypoints: Vec<_> = [(40.70, -74.02), (40.71, -74.02), (40.71, -74.02)]
+            .into_iter()
+            .map(|(lat, lon)| Waypoint::at(lat, lon, WaypointType::Move).unwrap())
+            .collect();
+        let result = TripResult {
+            steps: vec![StepResult::default(), leg(1000.0, 600.0), leg(0.0, 1800.0)],
+        };
+        let start_time = start_time();
+
+        let df = result
+            .to_dataframe(&waypoints, start_time, CoordinateSystem::Wgs84)
+            .unwrap();
+
+        assert_eq!(df.shape(), (3, 8));
+        assert_eq!(
+            df.get_column_names(),
+            [
+                "leg",
+                "lat",
+                "lon",
+                "distance",
+                "time",
+                "speed",
+                "cumulative_time",
+                "eta"
+            ]
+        );
+        let f64s = |name: &str| df[name].f64().unwrap().into_iter().collect::<Vec<_>>();
+        assert_eq!(f64s("distance"), [Some(0.0), Some(1000.0), Some(0.0)]);
+        assert_eq!(f64s("speed"), [None, Some(1000.0 / 600.0), Some(0.0)]);
+        assert_eq!(
+            f64s("cumulative_time"),
+            [Some(0.0), Some(600.0), Some(2400.0)]
+        );
+        let etas: Vec<_> = df["eta"].datetime().unwrap().into_iter().collect();
+        assert_eq!(etas, [Some(millis(0)), Some(millis(10)), Some(millis(40))]);
+    }
+
+    #[test]
+    fn moving_time_excludes_pauses() {
+        let result = TripResult {
+            steps: vec![
+                StepResult::default(),
+                leg(1000.0, 600.0),
+                leg(0.0, 1800.0),
+                leg(500.0, 300.0),
+            ],
+        };
+
+        assert_eq!(result.time().get::<second>(), 2700.0);
+        assert_eq!(result.moving_time().get::<second>(), 900.0);
+    }
+
+    #[test]
+    fn average_speed_over_moving_time() {
+        let result = TripResult {
+            steps: vec![StepResult::default(), leg(1000.0, 600.0), leg(0.0, 1800.0)],
+        };
+        let speed = result.average_speed().unwrap().get::<meter_per_second>();
+        assert!((speed - 1000.0 / 600.0).abs() < 1e-9);
+
+        // Only a pause, so no distance.
+        let result = TripResult {
+            steps: vec![StepResult::default(), leg(0.0, 1800.0)],
+        };
+        assert_eq!(result.average_speed(), None);
+
+        // Only the departure, so no distance or time.
+        let result = TripResult {
+            steps: vec![StepResult::default()],
+        };
+        assert_eq!(result.average_speed(), None);
+
+        // A leg that takes no time.
+        let result = TripResult {
+            steps: vec![StepResult::default(), leg(1000.0, 0.0)],
+        };
+        assert_eq!(result.average_speed(), None);
+    }
+
+    #[test]
+    fn stations_with_different_time_ranges() {
+        let a = station("a", 40.70, -74.02);
+        let b = station("b", 40.80, -74.02);
+        let a_prediction = prediction::<5>(&a, 0, 12, 0.0);
+        // Starts 10 minutes later and ends sooner.
+        let b_prediction = prediction::<5>(&b, 10, 6, 0.0);
+
+        assert_eq!(a_prediction.time_idx(millis(0)), Some(0));
+        assert_eq!(a_prediction.time_idx(millis(55)), Some(11));
+        assert_eq!(a_prediction.time_idx(millis(60)), None);
+        assert_eq!(a_prediction.time_idx(millis(2)), None);
+        assert_eq!(b_prediction.time_idx(millis(0)), None);
+        assert_eq!(b_prediction.time_idx(millis(10)), Some(0));
+        assert_eq!(b_prediction.time_idx(millis(35)), Some(5));
+        assert_eq!(b_prediction.time_idx(millis(40)), None);
+
+        let mut nn_calc = NearestNeighborCalculator::new(&[a.clone(), b.clone()]);
+        let predictions = HashMap::from([(a, a_prediction), (b, b_prediction)]);
+        let mut leg_at = |lat, start_time_idx| {
+            leg_north(lat, &predictions, millis(0), start_time_idx, &mut nn_calc)
+        };
+
+        assert_eq!(leg_at(40.70, 0).unwrap().time_steps, 2);
+        // Station b's predictions start at step 2 and end at step 7.
+        assert!(matches!(leg_at(40.80, 0), Err(TripError::ExceededData)));
+        assert_eq!(leg_at(40.80, 2).unwrap().time_steps, 2);
+        assert!(matches!(leg_at(40.80, 7), Err(TripError::ExceededData)));
+    }
+
+    #[test]
+    fn align_predictions_to_time_grid() {
+        let a = station("a", 40.70, -74.02);
+        let b = station("b", 40.80, -74.02);
+        let time_grid: Vec<i64> = (0..6).map(|i| millis(30 * i)).collect();
+
+        let aligned = align_predictions(
+            vec![
+                prediction::<30>(&a, 0, 6, 1.0),
+                prediction::<30>(&b, 60, 3, 2.0),
+            ],
+            &time_grid,
+        )
+        .unwrap();
+
+        for prediction in &aligned {
+            let times: Vec<_> = prediction.df["time"]
+                .datetime()
+                .unwrap()
+                .into_iter()
+                .collect();
+            assert_eq!(
+                times,
+                time_grid.iter().copied().map(Some).collect::<Vec<_>>()
+            );
+        }
+        let speeds = |prediction: &CurrentPrediction<30>| {
+            prediction.df["speed"]
+                .f64()
+                .unwrap()
+                .into_iter()
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(speeds(&aligned[0]), [Some(1.0); 6]);
+        assert_eq!(
+            speeds(&aligned[1]),
+            [None, None, Some(2.0), Some(2.0), Some(2.0), None]
+        );
+    }
+
+    #[test]
+    fn trip_time_indices_follow_the_time_grid() {
+        let a = station("a", 40.70, -74.02);
+        let b = station("b", 40.80, -74.02);
+        // Station b's predictions start an hour into the tide station's and
+        // end half an hour before them.
+        let time_grid: Vec<i64> = (0..6).map(|i| millis(30 * i)).collect();
+        let mut trip = trip(
+            vec![
+                prediction::<30>(&a, 0, 6, 0.0),
+                prediction::<30>(&b, 60, 3, 0.0),
+            ],
+            time_grid,
+        );
+
+        assert_eq!(trip.time_origin, millis(0));
+        assert_eq!(trip.time_at(2), Some(start_time() + TimeDelta::minutes(60)));
+        for prediction in trip.current_predictions_5m.values() {
+            assert_eq!(prediction.df.height(), 31);
+        }
+
+        let mut leg_at = |lat, start_time_idx| {
+            leg_north(
+                lat,
+                &trip.current_predictions_5m,
+                trip.time_origin,
+                start_time_idx,
+                &mut trip.nn_calc,
+            )
+        };
+        assert_eq!(leg_at(40.70, 0).unwrap().time_steps, 2);
+        assert!(matches!(leg_at(40.80, 0), Err(TripError::ExceededData)));
+        assert_eq!(leg_at(40.80, 12).unwrap().time_steps, 2);
+        // Station b's last prediction, at step 24, isn't extended.
+        assert!(matches!(leg_at(40.80, 24), Err(TripError::ExceededData)));
+    }
+
+    #[test]
+    fn current_field_dataframe_has_columns_per_station() {
+        let a = station("a", 40.70, -74.02);
+        let b = station("b", 40.80, -74.02);
+        let time_grid: Vec<i64> = (0..6).map(|i| millis(30 * i)).collect();
+        let trip = trip(
+            vec![
+                prediction::<30>(&a, 0, 6, 1.0),
+                prediction::<30>(&b, 60, 3, 2.0),
+            ],
+            time_grid.clone(),
+        );
+
+        let df = trip.current_field_dataframe().unwrap();
+
+        // Stations are ordered north to south.
+        assert_eq!(
+            df.get_column_names(),
+            ["time", "b_speed", "b_direction", "a_speed", "a_direction"]
+        );
+        assert_eq!(df.height(), time_grid.len());
+        let times: Vec<_> = df["time"].datetime().unwrap().into_iter().collect();
+        assert_eq!(times, time_grid.into_iter().map(Some).collect::<Vec<_>>());
+        let b_speeds: Vec<_> = df["b_speed"].f64().unwrap().into_iter().collect();
+        assert_eq!(
+            b_speeds,
+            [None, None, Some(2.0), Some(2.0), Some(2.0), None]
+        );
+    }
+
+    #[test]
+    fn leg_paths_run_from_leg_start_to_end() {
+        let a = station("a", 40.70, -74.02);
+        let time_grid: Vec<i64> = (0..6).map(|i| millis(30 * i)).collect();
+        let mut trip = trip(vec![prediction::<30>(&a, 0, 6, 0.0)], time_grid);
+        let pause = WaypointType::Pause(Time::new::<minute>(10.0));
+        trip.add_waypoints(vec![
+            Waypoint::at(40.70, -74.02, WaypointType::Move).unwrap(),
+            Waypoint::at(40.708, -74.02, WaypointType::Move).unwrap(),
+            Waypoint::at(40.708, -74.02, pause).unwrap(),
+            Waypoint::at(40.716, -74.02, WaypointType::Move).unwrap(),
+        ]);
+
+        let paths = trip.leg_paths(0).unwrap();
+
+        assert_eq!(paths.len(), trip.waypoints.len() - 1);
+        for (path, (start, end)) in paths.iter().zip(trip.waypoints.iter().tuple_windows()) {
+            // Two 5 minute steps, then the end.
+            assert_eq!(path.len(), 3);
+            let first = path[0];
+            assert!(
+                (first.0 - start.lat()).abs() < 1e-9,
+                "{first:?} != {start:?}"
+            );
+            assert!(
+                (first.1 - start.lon()).abs() < 1e-9,
+                "{first:?} != {start:?}"
+            );
+            assert_eq!(*path.last().unwrap(), (end.lat(), end.lon()));
+        }
+    }
+
+    #[test]
+    fn plan_at_departs_at_the_nearest_step() {
+        let a = station("a", 40.70, -74.02);
+        let time_grid: Vec<i64> = (0..6).map(|i| millis(30 * i)).collect();
+        // A current that changes every row, so departures at different steps
+        // take different times.
+        let mut a_prediction = prediction::<30>(&a, 0, 6, 0.0);
+        a_prediction
+            .df
+            .replace(
+                "speed",
+                Series::new("speed", [0.0, 0.5, 1.0, 1.5, 1.0, 0.5]),
+            )
+            .unwrap();
+        let mut trip = trip(vec![a_prediction], time_grid);
+        trip.add_waypoints(vec![
+            Waypoint::at(40.70, -74.02, WaypointType::Move).unwrap(),
+            Waypoint::at(40.716, -74.02, WaypointType::Move).unwrap(),
+        ]);
+        let at = |minutes| start_time() + TimeDelta::minutes(minutes);
+        let debug = |outcome: TripOutcome| format!("{outcome:?}");
+
+        assert_eq!(debug(trip.plan_at(at(10))), debug(trip.calculate(2)));
+        // Off the grid, so rounded to the nearest step.
+        assert_eq!(debug(trip.plan_at(at(12))), debug(trip.calculate(2)));
+        assert_eq!(debug(trip.plan_at(at(13))), debug(trip.calculate(3)));
+        assert_ne!(debug(trip.calculate(2)), debug(trip.calculate(3)));
+        // More than half a step before or after the predictions.
+        let out_of_range = trip.plan_at(at(-5)).unwrap_err();
+        assert_eq!(out_of_range, TripError::StartOutOfRange);
+        let out_of_range = trip.plan_at(at(155)).unwrap_err();
+        assert_eq!(out_of_range, TripError::StartOutOfRange);
+    }
+
+    /// A trip from `lat` to about 890 m north of it.
+    fn trip_north(lat: f64, predictions: Vec<CurrentPrediction<30>>, time_grid: Vec<i64>) -> Trip {
+        let mut trip = trip(predictions, time_grid);
+        trip.add_waypoints(vec![
+            Waypoint::at(lat, -74.02, WaypointType::Move).unwrap(),
+            Waypoint::at(lat + 0.008, -74.02, WaypointType::Move).unwrap(),
+        ]);
+        trip
+    }
+
+    #[test]
+    fn departing_after_the_predictions_is_out_of_range() {
+        let a = station("a", 40.70, -74.02);
+        let time_grid: Vec<i64> = (0..6).map(|i| millis(30 * i)).collect();
+        let mut trip = trip_north(40.70, vec![prediction::<30>(&a, 0, 6, 0.0)], time_grid);
+
+        assert!(trip.calculate(0).is_ok());
+        // Six 30 minute rows cover steps 0 through 30.
+        assert_eq!(trip.calculate(31).unwrap_err(), TripError::StartOutOfRange);
+    }
+
+    #[test]
+    fn opposing_current_faster_than_paddling_is_impassable() {
+        let a = station("a", 40.70, -74.02);
+        let mut nn_calc = NearestNeighborCalculator::new(&[a.clone()]);
+        // 4 knots south against 3 knots of paddling north.
+        let predictions = HashMap::from([(a.clone(), prediction::<5>(&a, 0, 12, -4.0))]);
+
+        let err = leg_north(40.70, &predictions, millis(0), 0, &mut nn_calc).unwrap_err();
+
+        assert_eq!(err, TripError::ImpassableCurrent);
+    }
+
+    #[test]
+    fn trips_longer_than_the_max_duration_fail() {
+        let a = station("a", 40.70, -74.02);
+        let time_grid: Vec<i64> = (0..6).map(|i| millis(30 * i)).collect();
+        let mut trip = trip_north(40.70, vec![prediction::<30>(&a, 0, 6, 0.0)], time_grid);
+
+        // The leg takes two 5 minute steps.
+        trip.set_max_trip_duration(Some(Time::new::<minute>(10.0)));
+        assert!(trip.calculate(0).is_ok());
+        trip.set_max_trip_duration(Some(Time::new::<minute>(4.0)));
+        assert_eq!(
+            trip.calculate(0).unwrap_err(),
+            TripError::ExceededMaxDuration
+        );
+    }
+
+    #[test]
+    fn daytime_trips_depart_after_eight() {
+        let a = station("a", 40.70, -74.02);
+        // Starts at 7:00, an hour before `start_time`.
+        let time_grid: Vec<i64> = (0..6).map(|i| millis(30 * i - 60)).collect();
+        let mut trip = trip_north(40.70, vec![prediction::<30>(&a, -60, 6, 0.0)], time_grid);
+        trip.set_daytime(true);
+
+        assert_eq!(
+            trip.calculate_departure_5m(0).unwrap_err(),
+            TripError::OutsideDaytime
+        );
+        // 8:00.
+        assert!(trip.calculate_departure_5m(12).is_ok());
+        // Outside the daytime window, but daytime isn't checked.
+        assert!(trip.calculate(0).is_ok());
+    }
+
+    #[test]
+    fn remove_waypoint_by_label() {
+        let a = station("a", 40.70, -74.02);
+        let time_grid: Vec<i64> = (0..6).map(|i| millis(30 * i)).collect();
+        let mut trip = trip(vec![prediction::<30>(&a, 0, 6, 0.0)], time_grid);
+        let waypoints = ["start", "island", "beach", "island", "end"]
+            .into_iter()
+            .enumerate()
+            .map(|(i, label)| Waypoint {
+                label: Some(label.to_string()),
+                ..Waypoint::at(40.70 + 0.008 * i as f64, -74.02, WaypointType::Move).unwrap()
+            })
+            .collect();
+        trip.add_waypoints(waypoints);
+        let labels = |trip: &Trip| {
+            let waypoints: Vec<_> = trip
+                .waypoints
+                .iter()
+                .map(|waypoint| waypoint.label.clone().unwrap())
+                .collect();
+            let features: Vec<_> = trip
+                .waypoint_layer
+                .read()
+                .unwrap()
+                .features()
+                .iter()
+                .map(|waypoint| waypoint.label.clone().unwrap())
+                .collect();
+            assert_eq!(waypoints, features);
+            waypoints
+        };
+
+        assert_eq!(trip.remove_waypoint_by_label("beach").unwrap(), 2);
+        assert_eq!(labels(&trip), ["start", "island", "island", "end"]);
+        // Only the first of the duplicates.
+        assert_eq!(trip.remove_waypoint_by_label("island").unwrap(), 1);
+        assert_eq!(labels(&trip), ["start", "island", "end"]);
+        assert!(trip.remove_waypoint_by_label("beach").is_err());
+        assert_eq!(labels(&trip), ["start", "island", "end"]);
+    }
+
+    #[test]
+    fn trips_without_stations_fail_to_calculate() {
+        // A region without current stations still has the tide station's
+        // times.
+        let time_grid: Vec<i64> = (0..6).map(|i| millis(30 * i)).collect();
+        let mut trip = trip_north(40.70, vec![], time_grid);
+
+        assert!(trip.stations.is_empty());
+        assert_eq!(trip.calculate(0).unwrap_err(), TripError::StartOutOfRange);
+        assert_eq!(
+            trip.calculate_departure_5m(0).unwrap_err(),
+            TripError::StartOutOfRange
+        );
+        assert_eq!(
+            trip.plan_at(start_time()).unwrap_err(),
+            TripError::StartOutOfRange
+        );
+    }
+}
@@ -0,0 +1,235 @@
+use chrono::NaiveDateTime;
+use galileo_types::geo::GeoPoint;
+use uom::si::f64::Time;
+use uom::si::f64::Velocity;
+use uom::si::time::hour;
+use uom::si::time::minute;
+use uom::si::velocity::knot;
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::JsCast;
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::JsValue;
+
+use crate::features::Waypoint;
+use crate::features::WaypointType;
+use crate::polyline;
+use crate::prelude::*;
+use crate::regions::Region;
+use crate::scheduling::Trip;
+
+const TIME_FORMAT: &str = "%Y-%m-%dT%H:%M";
+
+/// A waypoint as stored in a shareable link.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SharedWaypoint {
+    pub lat: f64,
+    pub lon: f64,
+    /// How long to stop, for pause waypoints.
+    pub pause: Option<Time>,
+    pub label: Option<String>,
+}
+
+/// A trip plan encoded in a shareable link's query parameters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SharedPlan {
+    pub region: Region,
+    /// Departure time, in station local time.
+    pub departure: Option<NaiveDateTime>,
+    pub speed: Velocity,
+    pub daytime: bool,
+    pub max_trip_duration: Option<Time>,
+    pub waypoints: Vec<SharedWaypoint>,
+}
+
+impl SharedPlan {
+    pub fn new(trip: &Trip, region: Region, departure: Option<NaiveDateTime>) -> Self {
+        let waypoints = trip
+            .waypoints
+            .iter()
+            .map(|waypoint| SharedWaypoint {
+                lat: waypoint.lat(),
+                lon: waypoint.lon(),
+                pause: match waypoint.type_ {
+                    WaypointType::Move => None,
+                    WaypointType::Pause(duration) => Some(duration),
+                },
+                label: waypoint.label.clone(),
+            })
+            .collect();
+
+        Self {
+            region,
+            departure,
+            speed: trip.speed,
+            daytime: trip.daytime,
+            max_trip_duration: trip.max_trip_duration,
+            waypoints,
+        }
+    }
+
+    /// Encodes the plan as query parameters. The waypoints' coordinates are an
+    /// encoded polyline in `path`, and their pauses and labels are repeated
+    /// `pause` and `label` parameters of `index:minutes` and `index:label`.
+    pub fn to_params(&self) -> Vec<(&'static str, String)> {
+        let mut params = vec![("region", self.region.key.to_string())];
+        if let Some(departure) = self.departure {
+            params.push(("departure", departure.format(TIME_FORMAT).to_string()));
+        }
+        params.push(("speed", self.speed.get::<knot>().to_string()));
+        if self.daytime {
+            params.push(("daytime", "1".to_string()));
+        }
+        if let Some(max_trip_duration) = self.max_trip_duration {
+            params.push(("max_hours", max_trip_duration.get::<hour>().to_string()));
+        }
+
+        let coordinates: Vec<_> = self
+            .waypoints
+            .iter()
+            .map(|waypoint| (waypoint.lat, waypoint.lon))
+            .collect();
+        params.push(("path", polyline::encode(&coordinates)));
+        for (i, waypoint) in self.waypoints.iter().enumerate() {
+            if let Some(pause) = waypoint.pause {
+                params.push(("pause", format!("{i}:{}", pause.get::<minute>().round())));
+            }
+            if let Some(label) = &waypoint.label {
+                params.push(("label", format!("{i}:{label}")));
+            }
+        }
+
+        params
+    }
+
+    /// Decodes a plan from query parameters, returning `None` if they don't
+    /// contain one.
+    pub fn from_params(params: &[(String, String)]) -> Result<Option<Self>> {
+        let get = |key: &str| {
+            params
+                .iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, value)| value.as_str())
+        };
+        let Some(region) = get("region") else {
+            return Ok(None);
+        };
+
+        let departure = get("departure")
+            .map(|departure| NaiveDateTime::parse_from_str(departure, TIME_FORMAT))
+            .transpose()
+            .map_err(|err| anyhow!("Invalid departure in link: {err}"))?;
+        let speed = get("speed")
+            .map(str::parse::<f64>)
+            .transpose()
+            .map_err(|err| anyhow!("Invalid speed in link: {err}"))?
+            .unwrap_or(3.0);
+        let max_hours = get("max_hours")
+            .map(str::parse::<f64>)
+            .transpose()
+            .map_err(|err| anyhow!("Invalid max_hours in link: {err}"))?;
+
+        let mut waypoints: Vec<_> = polyline::decode(get("path").unwrap_or_default())?
+            .into_iter()
+            .map(|(lat, lon)| SharedWaypoint {
+                lat,
+                lon,
+                pause: None,
+                label: None,
+            })
+            .collect();
+        for (key, value) in params {
+            if key != "pause" && key != "label" {
+                continue;
+            }
+            let (idx, value) = parse_indexed(value)?;
+            let waypoint = waypoints
+                .get_mut(idx)
+                .ok_or_else(|| anyhow!("Invalid waypoint index in link: {idx}"))?;
+            if key == "pause" {
+                let minutes = value
+                    .parse()
+                    .map_err(|err| anyhow!("Invalid pause in link: {err}"))?;
+                waypoint.pause = Some(Time::new::<minute>(minutes));
+            } else {
+                waypoint.label = Some(value.to_string());
+            }
+        }
+
+        Ok(Some(Self {
+            region: region.parse()?,
+            departure,
+            speed: Velocity::new::<knot>(speed),
+            daytime: get("daytime") == Some("1"),
+            max_trip_duration: max_hours.map(Time::new::<hour>),
+            waypoints,
+        }))
+    }
+
+    /// Replaces `trip`'s settings and waypoints with the plan's.
+    pub fn apply(&self, trip: &mut Trip) {
+        trip.set_speed(self.speed);
+        trip.set_daytime(self.daytime);
+        trip.set_max_trip_duration(self.max_trip_duration);
+        trip.clear_waypoints();
+
+        let waypoints = self
+            .waypoints
+            .iter()
+            .filter_map(|shared| {
+                let type_ = shared.pause.map_or(WaypointType::Move, WaypointType::Pause);
+                let mut waypoint = Waypoint::at(shared.lat, shared.lon, type_)?;
+                waypoint.label = shared.label.clone();
+                Some(waypoint)
+            })
+            .collect();
+        trip.add_waypoints(waypoints);
+    }
+
+    /// Builds a link to the current page that restores this plan.
+    #[cfg(target_arch = "wasm32")]
+    pub fn link(&self) -> Result<String> {
+        let js_err = |err: JsValue| anyhow!("Error building link: {err:?}");
+
+        let href = web_sys::window()
+            .log()?
+            .location()
+            .href()
+            .map_err(js_err)
+            .log()?;
+        let url = web_sys::Url::new(&href).map_err(js_err).log()?;
+        let params = web_sys::UrlSearchParams::new().map_err(js_err).log()?;
+        for (key, value) in self.to_params() {
+            params.append(key, &value);
+        }
+        url.set_search(&String::from(params.to_string()));
+        url.set_hash("");
+        Ok(url.href())
+    }
+}
+
+/// Splits an `index:value` parameter.
+fn parse_indexed(param: &str) -> Result<(usize, &str)> {
+    let err = || anyhow!("Invalid parameter in link: {param:?}");
+    let (idx, value) = param.split_once(':').ok_or_else(err)?;
+    Ok((idx.parse().map_err(|_| err())?, value))
+}
+
+/// Reads a plan from the page's query parameters, if it has one.
+#[cfg(target_arch = "wasm32")]
+pub fn load() -> Option<SharedPlan> {
+    let search = web_sys::window()?.location().search().ok()?;
+    let params = web_sys::UrlSearchParams::new_with_str(&search).ok()?;
+    let params: Vec<(String, String)> = js_sys::try_iter(&params)
+        .ok()??
+        .filter_map(|entry| {
+            let entry: js_sys::Array = entry.ok()?.dyn_into().ok()?;
+            Some((entry.get(0).as_string()?, entry.get(1).as_string()?))
+        })
+        .collect();
+    SharedPlan::from_params(&params).log().ok()?
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load() -> Option<SharedPlan> {
+    None
+}
@@ -61,6 +61,10 @@ impl EguiState {
         response
     }
 
+    pub fn wants_keyboard_input(&self) -> bool {
+        self.context.wants_keyboard_input()
+    }
+
     pub fn render(&mut self, wgpu_frame: &mut WgpuFrame<'_>, run_ui: impl FnOnce(&Context)) {
         let screen_descriptor = ScreenDescriptor {
             size_in_pixels: [wgpu_frame.size.width, wgpu_frame.size.height],
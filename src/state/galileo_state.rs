@@ -1,3 +1,4 @@
+use std::cell::Cell;
 use std::rc::Rc;
 use std::sync::Arc;
 use std::sync::RwLock;
@@ -19,27 +20,132 @@ use galileo::Map;
 use galileo::MapBuilder;
 use galileo::MapView;
 use galileo::TileSchema;
+use galileo_types::cartesian::CartesianPoint2d;
 use galileo_types::cartesian::Point2d;
 use galileo_types::cartesian::Size;
 use galileo_types::geo::impls::GeoPoint2d;
+use galileo_types::geo::GeoPoint;
+use galileo_types::geometry_type::CartesianSpace2d;
 use galileo_types::geometry_type::GeoSpace2d;
 use galileo_types::latlon;
+use serde_json::json;
+use serde_json::Value;
+use uom::si::f64::Time;
 use wgpu::Device;
 use wgpu::Queue;
 use wgpu::Surface;
 use wgpu::SurfaceConfiguration;
 use winit::dpi::PhysicalSize;
+use winit::event::ElementState;
+use winit::event::Touch;
+use winit::event::TouchPhase;
 use winit::event::WindowEvent;
 use winit::window::Window;
 
 use crate::features;
+use crate::features::Boat;
+use crate::features::BoatSymbol;
 use crate::features::CurrentPredictionSymbol;
+use crate::features::Leg;
+use crate::features::LegSymbol;
 use crate::features::WaypointType;
 use crate::noaa::CurrentPrediction;
 use crate::prelude::*;
+use crate::regions::Region;
 use crate::scheduling::Trip;
 use crate::state::WaypointClickAction;
 use crate::state::WgpuFrame;
+use crate::storage;
+use crate::time_idx::TimeIdx;
+
+const MAP_VIEW_KEY: &str = "map_view";
+const DEFAULT_ZOOM: u32 = 12;
+/// Highest zoom level tile.openstreetmap.org serves.
+pub const TILE_SOURCE_MAX_LEVEL: u32 = 19;
+
+/// How far, as a fraction of the view's width and height, arrows are kept
+/// beyond each edge of the view, so that small pans don't rebuild them.
+const ARROW_CULL_MARGIN: f64 = 0.5;
+/// How many times wider than the view the arrows' area may get, by zooming
+/// in, before they're rebuilt for the smaller view.
+const ARROW_CULL_ZOOM_RATIO: f64 = 8.0;
+
+/// Views are saved per region, so switching regions starts at its center.
+fn view_key(region: &Region) -> String {
+    format!("{MAP_VIEW_KEY}.{}", region.key)
+}
+
+fn load_view(region: &Region) -> Option<MapView> {
+    let json: Value = serde_json::from_str(&storage::load(&view_key(region))?)
+        .log()
+        .ok()?;
+
+    Some(MapView::new(
+        &latlon!(json["lat"].as_f64()?, json["lon"].as_f64()?),
+        json["resolution"].as_f64()?,
+    ))
+}
+
+/// Whether `event` ends a pan or zoom, after which the view is saved. Saving
+/// on every event would write the view many times a second while dragging.
+fn view_settles(event: &WindowEvent) -> bool {
+    matches!(
+        event,
+        WindowEvent::MouseInput {
+            state: ElementState::Released,
+            ..
+        } | WindowEvent::Touch(Touch {
+            phase: TouchPhase::Ended | TouchPhase::Cancelled,
+            ..
+        }) | WindowEvent::CursorLeft { .. }
+            | WindowEvent::Focused(false)
+    )
+}
+
+fn view_to_json(view: &MapView) -> Option<Value> {
+    let position = view.position()?;
+    Some(json!({
+        "lat": position.lat(),
+        "lon": position.lon(),
+        "resolution": view.resolution(),
+    }))
+}
+
+/// An axis-aligned rectangle in map coordinates.
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Extent {
+    x_min: f64,
+    y_min: f64,
+    x_max: f64,
+    y_max: f64,
+}
+
+impl Extent {
+    fn contains_point(&self, x: f64, y: f64) -> bool {
+        self.x_min <= x && x <= self.x_max && self.y_min <= y && y <= self.y_max
+    }
+
+    fn width(&self) -> f64 {
+        self.x_max - self.x_min
+    }
+
+    fn contains(&self, other: &Extent) -> bool {
+        self.contains_point(other.x_min, other.y_min)
+            && self.contains_point(other.x_max, other.y_max)
+    }
+
+    /// The extent grown by `margin` times its width and height on each side.
+    fn grown(&self, margin: f64) -> Self {
+        let dx = (self.x_max - self.x_min) * margin;
+        let dy = (self.y_max - self.y_min) * margin;
+        Self {
+            x_min: self.x_min - dx,
+            y_min: self.y_min - dy,
+            x_max: self.x_max + dx,
+            y_max: self.y_max + dy,
+        }
+    }
+}
 
 pub struct GalileoState {
     input_handler: WinitInputHandler,
@@ -47,11 +153,45 @@ pub struct GalileoState {
     renderer: Arc<RwLock<WgpuRenderer>>,
     map: Rc<RwLock<Map>>,
     pointer_position: Arc<RwLock<Point2d>>,
+    window: Arc<Window>,
+    hit_tolerance: f64,
     current_prediction_layer: Arc<
         RwLock<
             FeatureLayer<GeoPoint2d, CurrentPrediction<30>, CurrentPredictionSymbol, GeoSpace2d>,
         >,
     >,
+    leg_layer: Arc<RwLock<FeatureLayer<Point2d, Leg, LegSymbol, CartesianSpace2d>>>,
+    boat_layer: Arc<RwLock<FeatureLayer<Point2d, Boat, BoatSymbol, CartesianSpace2d>>>,
+    default_view: MapView,
+    saved_view: Option<Value>,
+    region: Region,
+    time_idx: Arc<RwLock<TimeIdx>>,
+    current_predictions: Vec<CurrentPrediction<30>>,
+    arrow_update_epsilon: f64,
+    /// Offset of the compared time, shared with the arrows' symbol.
+    compare_rows: Arc<RwLock<Option<f64>>>,
+    rendered_time_idx: Cell<Option<TimeIdx>>,
+    arrow_rebuilds: Cell<usize>,
+    arrow_rebuilds_skipped: Cell<usize>,
+    /// Size of the map, in physical pixels.
+    screen_size: Cell<(f64, f64)>,
+    /// The extent arrows were last added for, or `None` if all of them were.
+    arrow_extent: Cell<Option<Extent>>,
+}
+
+/// Magnitude, in knots, of the difference between the current vectors at the
+/// two fractional 30 minute rows, as drawn by the arrows.
+fn current_change(prediction: &CurrentPrediction<30>, a: f64, b: f64) -> f64 {
+    let (Some((speed_a, direction_a)), Some((speed_b, direction_b))) =
+        (prediction.interpolated(a), prediction.interpolated(b))
+    else {
+        return f64::INFINITY;
+    };
+
+    let (direction_a, direction_b) = (direction_a.to_radians(), direction_b.to_radians());
+    let dx = speed_a * direction_a.sin() - speed_b * direction_b.sin();
+    let dy = speed_a * direction_a.cos() - speed_b * direction_b.cos();
+    dx.hypot(dy)
 }
 
 impl GalileoState {
@@ -62,6 +202,8 @@ impl GalileoState {
         queue: Arc<Queue>,
         config: SurfaceConfiguration,
         waypoint_mode: Arc<RwLock<WaypointClickAction>>,
+        pause_duration: Arc<RwLock<Time>>,
+        remove_all_waypoints: Arc<RwLock<bool>>,
         current_prediction_layer: Arc<
             RwLock<
                 FeatureLayer<
@@ -72,9 +214,19 @@ impl GalileoState {
                 >,
             >,
         >,
+        leg_layer: Arc<RwLock<FeatureLayer<Point2d, Leg, LegSymbol, CartesianSpace2d>>>,
+        boat_layer: Arc<RwLock<FeatureLayer<Point2d, Boat, BoatSymbol, CartesianSpace2d>>>,
         trip: Arc<RwLock<Trip>>,
+        time_idx: Arc<RwLock<TimeIdx>>,
+        arrow_update_epsilon: f64,
+        compare_rows: Arc<RwLock<Option<f64>>>,
+        initial_zoom: Option<u32>,
+        max_tile_level: u32,
+        hit_tolerance: f64,
+        region: Region,
     ) -> Self {
-        let messenger = WinitMessenger::new(window);
+        let scale_factor_window = window.clone();
+        let messenger = WinitMessenger::new(window.clone());
         let trip_clone = trip.clone();
 
         let renderer = WgpuRenderer::new_with_device_and_surface(device, surface, queue, config);
@@ -142,7 +294,7 @@ impl GalileoState {
                         map,
                         trip.clone(),
                         *screen_pointer_position,
-                        WaypointType::Pause,
+                        WaypointType::Pause(*pause_duration.read().unwrap()),
                     )
                     .unwrap();
                 },
@@ -167,8 +319,16 @@ impl GalileoState {
                     ),
                     WaypointClickAction::Remove,
                 ) => {
-                    features::remove_waypoints(map, trip.clone(), *screen_pointer_position)
-                        .unwrap();
+                    // Screen positions are in physical pixels.
+                    let tolerance = hit_tolerance * scale_factor_window.scale_factor();
+                    features::remove_waypoints(
+                        map,
+                        trip.clone(),
+                        *screen_pointer_position,
+                        tolerance,
+                        *remove_all_waypoints.read().unwrap(),
+                    )
+                    .unwrap();
                 },
 
                 _ => {},
@@ -186,10 +346,23 @@ impl GalileoState {
 
         event_processor.add_handler(MapController::default());
 
-        let view = MapView::new(
-            &latlon!(40.7127, -74.0059),
-            TileSchema::web(18).lod_resolution(12).unwrap(),
+        if max_tile_level > TILE_SOURCE_MAX_LEVEL {
+            warn!("Max tile level {max_tile_level} isn't served, using {TILE_SOURCE_MAX_LEVEL}");
+        }
+        let max_tile_level = max_tile_level.min(TILE_SOURCE_MAX_LEVEL);
+
+        let default_view = MapView::new(
+            &latlon!(region.center.0, region.center.1),
+            TileSchema::web(max_tile_level)
+                .lod_resolution(initial_zoom.unwrap_or(DEFAULT_ZOOM).min(max_tile_level))
+                .unwrap(),
         );
+        // An explicitly requested zoom takes precedence over the saved view.
+        let view = match initial_zoom {
+            Some(_) => default_view.clone(),
+            None => load_view(&region).unwrap_or_else(|| default_view.clone()),
+        };
+        let saved_view = view_to_json(&view);
 
         let tile_source = |index: &TileIndex| {
             // TODO: These are cached to .tile_cache, figure out a way to redirect it.
@@ -199,9 +372,13 @@ impl GalileoState {
             )
         };
 
+        // TODO: retry failed tiles with backoff and draw a placeholder for
+        // tiles that never load. create_raster_tile_layer builds the tile
+        // provider internally and doesn't report failed tiles, so this needs a
+        // custom provider or upstream support in galileo.
         let layer = Box::new(MapBuilder::create_raster_tile_layer(
             tile_source,
-            TileSchema::web(18),
+            TileSchema::web(max_tile_level),
         ));
 
         let map = Rc::new(RwLock::new(Map::new(view, vec![layer], Some(messenger))));
@@ -210,12 +387,22 @@ impl GalileoState {
         map.write()
             .unwrap()
             .layers_mut()
-            .insert(1, trip_clone.read().unwrap().waypoint_layer.clone());
+            .insert(1, leg_layer.clone());
+
+        map.write()
+            .unwrap()
+            .layers_mut()
+            .insert(2, trip_clone.read().unwrap().waypoint_layer.clone());
+
+        map.write()
+            .unwrap()
+            .layers_mut()
+            .insert(3, current_prediction_layer.clone());
 
         map.write()
             .unwrap()
             .layers_mut()
-            .insert(2, current_prediction_layer.clone());
+            .insert(4, boat_layer.clone());
 
         Self {
             input_handler,
@@ -223,8 +410,69 @@ impl GalileoState {
             renderer,
             map,
             pointer_position,
+            window,
+            hit_tolerance,
             current_prediction_layer,
+            leg_layer,
+            boat_layer,
+            default_view,
+            saved_view,
+            region,
+            time_idx,
+            current_predictions: Vec::new(),
+            arrow_update_epsilon,
+            compare_rows,
+            rendered_time_idx: Cell::new(None),
+            arrow_rebuilds: Cell::new(0),
+            arrow_rebuilds_skipped: Cell::new(0),
+            screen_size: Cell::new((config.width as f64, config.height as f64)),
+            arrow_extent: Cell::new(None),
+        }
+    }
+
+    /// Adds the waypoint layer of a route created after startup to the map.
+    pub fn add_waypoint_layer(&self, trip: &Trip) {
+        // TODO: make layer indices constants.
+        self.map
+            .write()
+            .unwrap()
+            .layers_mut()
+            .insert(2, trip.waypoint_layer.clone());
+        self.redraw();
+    }
+
+    /// Replaces the drawn trip legs.
+    pub fn set_legs(&self, legs: Vec<Leg>) {
+        features::clear_features(self.leg_layer.clone());
+        let mut layer = self.leg_layer.write().unwrap();
+        let feature_store = layer.features_mut();
+        for leg in legs {
+            feature_store.insert(leg);
+        }
+        drop(layer);
+        self.redraw();
+    }
+
+    /// Replaces the replayed boat's marker, or removes it.
+    pub fn set_boat(&self, boat: Option<Boat>) {
+        features::clear_features(self.boat_layer.clone());
+        if let Some(boat) = boat {
+            self.boat_layer.write().unwrap().features_mut().insert(boat);
         }
+        self.redraw();
+    }
+
+    /// Adds the arrow for a station whose predictions were just fetched.
+    pub fn add_current_prediction(&mut self, prediction: CurrentPrediction<30>) {
+        if self.in_arrow_extent(&prediction) {
+            self.current_prediction_layer
+                .write()
+                .unwrap()
+                .features_mut()
+                .insert(prediction.clone());
+        }
+        self.current_predictions.push(prediction);
+        self.map.read().unwrap().redraw();
     }
 
     pub fn about_to_wait(&self) {
@@ -233,13 +481,56 @@ impl GalileoState {
 
     #[instrument(level = "debug", skip_all)]
     pub fn redraw_map(&self) {
-        let features = features::clear_features(self.current_prediction_layer.clone());
+        let time_idx = *self.time_idx.read().unwrap();
+
+        if let Some(rendered_time_idx) = self.rendered_time_idx.get() {
+            let compare_rows = *self.compare_rows.read().unwrap();
+            let offsets = [Some(0.0), compare_rows];
+            let changed = self.current_predictions.iter().any(|prediction| {
+                offsets.iter().flatten().any(|offset| {
+                    current_change(
+                        prediction,
+                        rendered_time_idx.row_pos() + offset,
+                        time_idx.row_pos() + offset,
+                    ) > self.arrow_update_epsilon
+                })
+            });
+            if !changed {
+                self.arrow_rebuilds_skipped
+                    .set(self.arrow_rebuilds_skipped.get() + 1);
+                debug!(
+                    "Skipped arrow rebuild, {} skipped, {} rebuilt",
+                    self.arrow_rebuilds_skipped.get(),
+                    self.arrow_rebuilds.get()
+                );
+                return;
+            }
+        }
+
+        self.rendered_time_idx.set(Some(time_idx));
+        self.arrow_rebuilds.set(self.arrow_rebuilds.get() + 1);
+        self.arrow_extent.set(
+            self.view_extent()
+                .map(|extent| extent.grown(ARROW_CULL_MARGIN)),
+        );
+
+        features::clear_features(self.current_prediction_layer.clone());
         let mut feature_layer = self.current_prediction_layer.write().unwrap();
         let feature_store = feature_layer.features_mut();
 
-        for feature in features.iter() {
-            feature_store.insert(feature.to_owned());
+        // Only arrows in or near the view are drawn. Panning out of that area
+        // rebuilds them, see `render`.
+        let mut shown = 0;
+        for prediction in &self.current_predictions {
+            if self.in_arrow_extent(prediction) {
+                feature_store.insert(prediction.clone());
+                shown += 1;
+            }
         }
+        debug!(
+            "Drawing {shown} of {} arrows",
+            self.current_predictions.len()
+        );
 
         // TODO: can we do anything better than removing and re-adding all the
         // features? Does re-creating the layer make sense? Maybe that will fix
@@ -252,6 +543,94 @@ impl GalileoState {
         self.map.read().unwrap().redraw();
     }
 
+    /// Draws the currents `rows` 30 minute rows after the selected time faded
+    /// behind its arrows, or stops if `None`.
+    pub fn set_compare_rows(&self, rows: Option<f64>) {
+        let mut compare_rows = self.compare_rows.write().unwrap();
+        if *compare_rows != rows {
+            *compare_rows = rows;
+            drop(compare_rows);
+            self.rebuild_arrows();
+        }
+    }
+
+    /// Rebuilds the current arrows even if the currents haven't changed, e.g.
+    /// after their symbol's style changed.
+    pub fn rebuild_arrows(&self) {
+        self.rendered_time_idx.set(None);
+        self.redraw_map();
+    }
+
+    pub fn redraw(&self) {
+        self.map.read().unwrap().redraw();
+    }
+
+    /// The area shown on screen, in map coordinates, or `None` if it can't be
+    /// determined.
+    fn view_extent(&self) -> Option<Extent> {
+        let view = self.map.read().expect("poisoned lock").view().clone();
+        let (width, height) = self.screen_size.get();
+        // The view may be rotated, so bound all four corners.
+        let corners = [(0.0, 0.0), (width, 0.0), (0.0, height), (width, height)]
+            .into_iter()
+            .map(|(x, y)| view.screen_to_map(Point2d::new(x, y)))
+            .collect::<Option<Vec<_>>>()?;
+        Some(Extent {
+            x_min: corners.iter().map(|p| p.x()).fold(f64::INFINITY, f64::min),
+            y_min: corners.iter().map(|p| p.y()).fold(f64::INFINITY, f64::min),
+            x_max: corners
+                .iter()
+                .map(|p| p.x())
+                .fold(f64::NEG_INFINITY, f64::max),
+            y_max: corners
+                .iter()
+                .map(|p| p.y())
+                .fold(f64::NEG_INFINITY, f64::max),
+        })
+    }
+
+    fn in_arrow_extent(&self, prediction: &CurrentPrediction<30>) -> bool {
+        self.arrow_extent.get().map_or(true, |extent| {
+            extent.contains_point(prediction.x(), prediction.y())
+        })
+    }
+
+    pub fn resolution(&self) -> f64 {
+        self.map.read().expect("poisoned lock").view().resolution()
+    }
+
+    /// Map units per logical pixel. Screen positions and `resolution` are in
+    /// physical pixels, so distances given in logical pixels, like hit
+    /// tolerances, are scaled by this to stay the same size on HiDPI displays.
+    pub fn logical_resolution(&self) -> f64 {
+        self.resolution() * self.window.scale_factor()
+    }
+
+    /// Ground distance, in meters, covered by one pixel at the center of the
+    /// view. Unlike `resolution`, this accounts for mercator scale distortion.
+    pub fn ground_resolution(&self) -> f64 {
+        let view = self.map.read().expect("poisoned lock").view().clone();
+        let lat = view.position().map_or(0.0, |position| position.lat());
+        view.resolution() * lat.to_radians().cos()
+    }
+
+    /// Rotation of the map about the vertical axis, in radians. Zero is
+    /// north-up.
+    pub fn rotation(&self) -> f64 {
+        self.map.read().expect("poisoned lock").view().rotation_z()
+    }
+
+    pub fn reset_rotation(&mut self) {
+        let view = self
+            .map
+            .read()
+            .expect("poisoned lock")
+            .view()
+            .with_rotation_z(0.0);
+        self.map.write().expect("poisoned lock").set_view(view);
+        self.save_view();
+    }
+
     pub fn resize(&self, size: PhysicalSize<u32>) {
         self.renderer
             .write()
@@ -261,10 +640,26 @@ impl GalileoState {
             .write()
             .expect("poisoned lock")
             .set_size(Size::new(size.width as f64, size.height as f64));
+        self.screen_size
+            .set((size.width as f64, size.height as f64));
     }
 
     #[instrument(level = "debug", skip_all)]
     pub fn render(&self, wgpu_frame: &WgpuFrame<'_>) {
+        // Rebuild the arrows once the view leaves the area they were added
+        // for, or is zoomed in far enough that most of them are off screen.
+        let rebuild = match (self.arrow_extent.get(), self.view_extent()) {
+            (Some(arrow_extent), Some(view_extent)) => {
+                !arrow_extent.contains(&view_extent)
+                    || view_extent.width() * ARROW_CULL_ZOOM_RATIO < arrow_extent.width()
+            },
+            (None, Some(_)) => !self.current_predictions.is_empty(),
+            (_, None) => false,
+        };
+        if rebuild {
+            self.rebuild_arrows();
+        }
+
         let galileo_map = self.map.read().unwrap();
         galileo_map.load_layers();
 
@@ -275,12 +670,50 @@ impl GalileoState {
     }
 
     pub fn handle_event(&mut self, event: &WindowEvent) {
+        // Galileo divides pointer positions by this to get screen positions.
+        // The map is sized in physical pixels, as are winit's pointer
+        // positions, so they must not be scaled to logical pixels.
         let scale = 1.0;
 
         if let Some(raw_event) = self.input_handler.process_user_input(event, scale) {
             let mut map = self.map.write().expect("poisoned lock");
             self.event_processor.handle(raw_event, &mut map);
         }
+
+        if view_settles(event) {
+            self.save_view();
+        }
+    }
+
+    /// Saves the view if it changed since it was last saved.
+    pub fn save_view(&mut self) {
+        let view = self.map.read().expect("poisoned lock").view().clone();
+        let json = view_to_json(&view);
+        if json.is_none() || json == self.saved_view {
+            return;
+        }
+
+        if storage::store(&view_key(&self.region), &json.as_ref().unwrap().to_string()).is_ok() {
+            self.saved_view = json;
+        }
+    }
+
+    /// Centers the view on `lat` and `lon`, keeping the zoom level.
+    pub fn center_on(&mut self, lat: f64, lon: f64) {
+        let resolution = self.resolution();
+        self.map
+            .write()
+            .expect("poisoned lock")
+            .set_view(MapView::new(&latlon!(lat, lon), resolution));
+        self.save_view();
+    }
+
+    pub fn reset_view(&mut self) {
+        self.map
+            .write()
+            .expect("poisoned lock")
+            .set_view(self.default_view.clone());
+        self.save_view();
     }
 
     pub fn pointer_position(&self) -> Option<GeoPoint2d> {
@@ -288,4 +721,25 @@ impl GalileoState {
         let view = self.map.read().expect("poisoned lock").view().clone();
         view.screen_to_map_geo(pointer_position)
     }
+
+    /// The current prediction whose station is nearest the pointer, if it's
+    /// within the hit tolerance.
+    pub fn hovered_prediction(&self) -> Option<CurrentPrediction<30>> {
+        let pointer_position = *self.pointer_position.read().expect("poisoned lock");
+        let view = self.map.read().expect("poisoned lock").view().clone();
+        let map_pos = view.screen_to_map(pointer_position)?;
+        let tolerance = self.logical_resolution() * self.hit_tolerance;
+
+        // The arrows are in geographic space, where get_features_at isn't
+        // available, so compare against their projected station locations.
+        self.current_predictions
+            .iter()
+            .map(|prediction| {
+                let distance = (prediction.x() - map_pos.x).hypot(prediction.y() - map_pos.y);
+                (prediction, distance)
+            })
+            .filter(|(_, distance)| *distance <= tolerance)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(prediction, _)| prediction.clone())
+    }
 }
@@ -1,17 +1,27 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::io;
+#[cfg(not(target_arch = "wasm32"))]
+use std::io::Write;
 use std::iter;
 use std::mem;
 use std::rc::Rc;
 use std::sync::Arc;
 use std::sync::RwLock;
 
-use chrono::offset::Local;
+use chrono::DateTime;
 use chrono::Datelike;
+use chrono::Duration;
 use chrono::NaiveDate;
-use futures::future;
+use chrono::Utc;
 use galileo::layer::feature_layer::FeatureLayer;
+use galileo_types::cartesian::Point2d;
 use galileo_types::geo::Crs;
 use polars::prelude::*;
+use uom::si::f64::Length;
+use uom::si::f64::Time;
 use uom::si::f64::Velocity;
+use uom::si::length::mile;
+use uom::si::time::hour;
 use uom::si::velocity::knot;
 use wgpu::Backends;
 use wgpu::CommandEncoder;
@@ -38,25 +48,45 @@ use winit::event::ElementState;
 use winit::event::KeyEvent;
 use winit::event::WindowEvent;
 use winit::keyboard::Key;
+use winit::keyboard::ModifiersState;
 use winit::keyboard::NamedKey;
 use winit::window::Window;
 
+use crate::clock::Clock;
+use crate::export;
+use crate::features::BoatSymbol;
+use crate::features::CurrentColoring;
 use crate::features::CurrentPredictionSymbol;
+use crate::features::LegSymbol;
 use crate::features::WaypointSymbol;
-use crate::http::ApiProxy;
+use crate::http;
+use crate::noaa::CurrentPrediction;
+use crate::noaa::RawResponseCache;
+use crate::noaa::SpeedInterpolation;
 use crate::noaa::Station;
+use crate::noaa::TideCache;
 use crate::prelude::*;
+use crate::regions;
+use crate::run_ui;
 use crate::run_ui::run_ui;
+use crate::run_ui::Settings;
+use crate::run_ui::StationFailure;
 use crate::run_ui::UiState;
-use crate::saturating::Saturating;
 use crate::scheduling::Trip;
+use crate::share;
 use crate::state::egui_state::EguiState;
 use crate::state::galileo_state::GalileoState;
+use crate::tasks;
+use crate::time_idx;
+use crate::time_idx::TimeIdx;
 use crate::Config;
+use crate::OutputFormat;
 
 mod egui_state;
 pub mod galileo_state;
 
+const WAYPOINT_NUDGE_PIXELS: f64 = 5.0;
+
 pub struct WgpuFrame<'frame> {
     device: &'frame Device,
     queue: &'frame Queue,
@@ -83,7 +113,121 @@ pub struct State {
     pub egui_state: EguiState,
     pub galileo_state: Rc<RwLock<GalileoState>>,
     pub ui_state: UiState,
-    pub time_idx: Arc<RwLock<Saturating<usize>>>,
+    pub time_idx: Arc<RwLock<TimeIdx>>,
+    pub trip: Arc<RwLock<Trip>>,
+    modifiers: ModifiersState,
+    output: Option<OutputFormat>,
+    load_start: DateTime<Utc>,
+    prediction_request: PredictionRequest,
+    fetched_predictions: FetchedPredictions,
+    current_predictions: Vec<CurrentPrediction<30>>,
+    /// Number of predictions the drawn arrows are averaged over, if smoothed.
+    smoothing_window: Option<usize>,
+    pending_stations: usize,
+}
+
+/// Hours of predictions fetched, from `prediction_start`.
+pub(crate) const PREDICTION_HOURS: u32 = 24 * 30 * 2;
+
+/// First day of the predictions fetched to plan trips on `today`.
+pub(crate) fn prediction_start(today: NaiveDate) -> Option<NaiveDate> {
+    NaiveDate::from_ymd_opt(today.year(), today.month(), 1)
+}
+
+/// Fetches the station's current predictions from `start_time`, in milliseconds
+/// since the epoch, on.
+async fn fetch_current_prediction(
+    station: Station,
+    start_date: NaiveDate,
+    duration_hours: u32,
+    start_time: i64,
+    observed_currents: bool,
+    speed_interpolation: SpeedInterpolation,
+) -> Result<CurrentPrediction<30>> {
+    let mut prediction = station
+        .current_prediction(start_date, duration_hours, speed_interpolation)
+        .await
+        .log()?;
+    prediction.df = mem::take(&mut prediction.df)
+        .lazy()
+        .filter(col("time").gt_eq(start_time))
+        .collect()
+        .log()?;
+
+    // Most stations have no current meter, so missing observations are
+    // expected and the predictions are used as is.
+    if observed_currents {
+        match station.observed_currents().await {
+            Ok(observed) => {
+                let _ = prediction.add_observations(observed).log();
+            },
+            Err(err) => debug!("No observed currents for {:?}: {err}", station.id),
+        }
+    }
+
+    Ok(prediction)
+}
+
+/// Results of the current prediction fetches, with the error of each failed
+/// fetch.
+type FetchedPredictions = Arc<RwLock<Vec<(Station, Result<CurrentPrediction<30>, String>)>>>;
+
+/// The current predictions fetched for every station, kept to retry the
+/// stations whose fetches failed.
+#[derive(Debug, Copy, Clone)]
+struct PredictionRequest {
+    start_date: NaiveDate,
+    duration_hours: u32,
+    start_time: i64,
+    observed_currents: bool,
+    speed_interpolation: SpeedInterpolation,
+}
+
+impl PredictionRequest {
+    /// Fetches each station's current predictions in the background, pushing
+    /// the results to `fetched` and redrawing `window` as each arrives.
+    fn spawn(
+        self,
+        stations: impl IntoIterator<Item = Station>,
+        fetched: &FetchedPredictions,
+        window: &Arc<Window>,
+    ) {
+        for station in stations {
+            let fetched = fetched.clone();
+            let window = window.clone();
+            tasks::spawn(async move {
+                let prediction = fetch_current_prediction(
+                    station.clone(),
+                    self.start_date,
+                    self.duration_hours,
+                    self.start_time,
+                    self.observed_currents,
+                    self.speed_interpolation,
+                )
+                .await
+                .map_err(|err| err.to_string());
+                fetched.write().unwrap().push((station, prediction));
+                window.request_redraw();
+            });
+        }
+    }
+}
+
+/// When the loaded predictions were fetched, and whether they came from a
+/// cache, judging by whether they are older than `load_start`.
+fn data_freshness(load_start: DateTime<Utc>) -> (DateTime<Utc>, bool) {
+    let data_fetched_at = http::oldest_prediction_date().unwrap_or(load_start);
+    // Allow for some clock skew between us and the server.
+    let data_from_cache = data_fetched_at < load_start - Duration::minutes(5);
+    (data_fetched_at, data_from_cache)
+}
+
+/// Why trips can't be planned in `region_name` if it has no current
+/// `stations`, or None if it has some.
+pub(crate) fn no_stations_message(region_name: &str, stations: &[Station]) -> Option<String> {
+    stations.is_empty().then(|| {
+        format!("No current predictions are available for {region_name}: NOAA has no current stations with predictions there. Tides and the map still work, but trips can't be calculated.")
+    })
 }
 
 impl State {
@@ -144,23 +288,43 @@ impl State {
         };
         surface.configure(&device, &surface_config);
 
+        // TODO: make the MSAA sample count configurable. egui antialiases by
+        // feathering and draws over the map with LoadOp::Load, so rendering it
+        // multisampled needs its own target composited onto the frame, since
+        // resolving would overwrite the map. The jagged arrows and waypoints
+        // are drawn by galileo, whose renderer doesn't take a sample count.
         let egui_state = EguiState::new(&device, surface_config.format, None, 1, &window);
 
         let surface = Arc::new(surface);
         let device = Arc::new(device);
         let queue = Arc::new(queue);
 
-        let api_proxy = if config.use_api_proxy {
-            Some(ApiProxy {
-                url: config.api_proxy_url,
-            })
-        } else {
-            None
-        };
-
-        let battery = Station::new("8518750", api_proxy.clone()).await.log()?;
+        let output = config.output;
+
+        let api_proxy = config.api_proxy();
+
+        let load_start = Utc::now();
+        // A plan from a shareable link takes precedence over the configuration.
+        let shared_plan = share::load();
+        let region = shared_plan
+            .as_ref()
+            .map(|plan| plan.region)
+            .or(config.region)
+            .or_else(regions::load)
+            .unwrap_or_default();
+        info!("Loading region {:?}", region.name);
+        let battery = Station::new(region.tide_station, api_proxy.clone())
+            .await
+            .log()?;
 
-        let today = Local::now().date_naive();
+        let clock = Clock::new(config.now, region.time_zone);
+        // Fetch the month of a shared plan's departure, which may not be this
+        // month.
+        let today = shared_plan
+            .as_ref()
+            .and_then(|plan| plan.departure)
+            .unwrap_or_else(|| clock.now())
+            .date();
         // https://tidesandcurrents.noaa.gov/noaacurrents/Faq#07
         // TODO: make a function
         // let start_month = match today.month() {
@@ -176,74 +340,128 @@ impl State {
         //                                          1)
         //     .log()?;
 
-        let start_date = NaiveDate::from_ymd_opt(today.year(), today.month(), 1).log()?;
+        let start_date = prediction_start(today).log()?;
 
-        let duration_hours = 24 * 30 * 2;
-        let nyc_lat_range = (39.0, 42.0);
-        let nyc_lon_range = (-73.0, -75.0);
+        let duration_hours = PREDICTION_HOURS;
 
         let battery_tide_predictions = battery
             .tide_prediction(start_date, duration_hours)
             .await
             .log()?;
 
+        // Only the experimental tide speed profile uses the heights, so trips
+        // are planned without it if they're unavailable.
+        let tide_heights = battery.tide_heights(start_date, duration_hours).await.ok();
+
         let time_vec = battery_tide_predictions["time"]
             .datetime()
             .log()?
             .to_vec_null_aware()
             .unwrap_left();
 
-        let mut max_time_idx = time_vec.len() - 1;
-
-        let stations = Station::in_area(nyc_lat_range, nyc_lon_range, api_proxy)
-            .await
-            .log()?;
+        let stations = match &config.stations_file {
+            Some(path) => Station::from_file(path, api_proxy).log()?,
+            None => Station::in_area(region.lat_range, region.lon_range, api_proxy)
+                .await
+                .log()?,
+        };
         info!("Found stations: {:?}", stations);
-
-        let mut current_prediction_futures = Vec::new();
-        for station in stations.iter() {
-            current_prediction_futures.push(station.current_prediction(start_date, duration_hours))
+        // Tides and the map still work, and the UI explains why trips can't
+        // be planned.
+        if let Some(message) = no_stations_message(region.name, &stations) {
+            warn!("{message}");
         }
 
-        let mut current_predictions: Vec<_> = future::join_all(current_prediction_futures)
-            .await
-            .into_iter()
-            .flatten()
-            .collect();
-
-        for pred in &mut current_predictions {
-            pred.df = mem::take(&mut pred.df)
-                .lazy()
-                .filter(col("time").gt_eq(time_vec[0]))
-                .collect()
-                .log()?;
-            let max_idx = pred.df.height() - 1;
-            if max_idx < max_time_idx {
-                max_time_idx = max_idx;
-            }
+        // Arrows are added as each station's predictions arrive, see
+        // `receive_current_predictions`.
+        let prediction_request = PredictionRequest {
+            start_date,
+            duration_hours,
+            start_time: time_vec[0],
+            observed_currents: config.observed_currents,
+            speed_interpolation: config.speed_interpolation,
+        };
+        let fetched_predictions = Arc::new(RwLock::new(Vec::new()));
+        prediction_request.spawn(stations.iter().cloned(), &fetched_predictions, &window);
+
+        let mut time_idx = TimeIdx::new(time_vec.len() - 1);
+        // Times outside the predictions are clamped to them.
+        let start = match shared_plan.as_ref().and_then(|plan| plan.departure) {
+            Some(departure) => Some(departure.and_utc().timestamp_millis()),
+            None => config.start_at_now.then(|| clock.now_millis()),
+        };
+        if let Some(idx) = start.and_then(|start| time_idx::nearest_step(&time_vec, start)) {
+            time_idx.set(idx);
         }
+        let time_idx = Arc::new(RwLock::new(time_idx));
 
-        let time_idx = Arc::new(RwLock::new(Saturating::new(0, 0, max_time_idx)));
+        let (data_fetched_at, data_from_cache) = data_freshness(load_start);
+
+        let arrow_update_epsilon = config.arrow_update_epsilon;
+        let initial_zoom = config.initial_zoom;
+        let max_tile_level = config.max_tile_level;
+        let hit_tolerance = config.hit_tolerance;
+        let coordinate_system = config.coordinate_system;
+        let accessible = Arc::new(RwLock::new(run_ui::load_accessibility()));
+        let current_coloring = Arc::new(RwLock::new(CurrentColoring::default()));
+        let waypoint_highlight = Arc::new(RwLock::new(None));
+        let compare_rows = Arc::new(RwLock::new(None));
 
         let current_prediction_layer = FeatureLayer::new(
-            current_predictions.clone(),
+            vec![],
             CurrentPredictionSymbol {
                 time_idx: time_idx.clone(),
+                accessible: accessible.clone(),
+                coloring: current_coloring.clone(),
+                compare_rows: compare_rows.clone(),
             },
             Crs::EPSG3857,
         );
         let current_prediction_layer = Arc::new(RwLock::new(current_prediction_layer));
 
-        let waypoint_layer = FeatureLayer::new(vec![], WaypointSymbol {}, Crs::EPSG3857);
+        let leg_layer = FeatureLayer::new(
+            vec![],
+            LegSymbol {
+                accessible: accessible.clone(),
+            },
+            Crs::EPSG3857,
+        );
+        let leg_layer = Arc::new(RwLock::new(leg_layer));
+
+        let boat_layer = FeatureLayer::new(
+            vec![],
+            BoatSymbol {
+                accessible: accessible.clone(),
+            },
+            Crs::EPSG3857,
+        );
+        let boat_layer = Arc::new(RwLock::new(boat_layer));
+
+        let waypoint_layer = FeatureLayer::new(
+            vec![],
+            WaypointSymbol::for_route(0, accessible.clone(), waypoint_highlight.clone()),
+            Crs::EPSG3857,
+        );
         let waypoint_layer = Arc::new(RwLock::new(waypoint_layer));
 
         let trip = Arc::new(RwLock::new(Trip::new(
             Velocity::new::<knot>(3.0),
             waypoint_layer,
-            current_predictions,
+            vec![],
+            Length::new::<mile>(config.max_station_distance),
+            config.sweep_quantile_method,
+            time_vec.clone(),
         )?));
+        {
+            let mut trip = trip.write().unwrap();
+            trip.tide_heights = tide_heights;
+            trip.speed_interpolation = config.speed_interpolation;
+            trip.set_tide_speed_profile(config.tide_speed_profile);
+        }
 
         let waypoint_mode = Arc::new(RwLock::new(WaypointClickAction::Move));
+        let pause_duration = Arc::new(RwLock::new(Time::new::<hour>(config.default_pause_hours)));
+        let remove_all_waypoints = Arc::new(RwLock::new(false));
 
         let galileo_state = GalileoState::new(
             Arc::clone(&window),
@@ -252,18 +470,75 @@ impl State {
             Arc::clone(&queue),
             surface_config.clone(),
             waypoint_mode.clone(),
+            pause_duration.clone(),
+            remove_all_waypoints.clone(),
             current_prediction_layer,
+            leg_layer,
+            boat_layer,
             trip.clone(),
+            time_idx.clone(),
+            arrow_update_epsilon,
+            compare_rows,
+            initial_zoom,
+            max_tile_level,
+            hit_tolerance,
+            region,
         );
         let galileo_state = Rc::new(RwLock::new(galileo_state));
 
-        let ui_state = UiState::new(
+        let mut ui_state = UiState::new(
             time_idx.clone(),
+            battery,
             battery_tide_predictions,
+            TideCache::new(start_date, duration_hours),
             waypoint_mode,
-            trip,
+            pause_duration,
+            remove_all_waypoints,
+            trip.clone(),
             galileo_state.clone(),
+            window.clone(),
+            coordinate_system,
+            data_fetched_at,
+            data_from_cache,
+            accessible,
+            current_coloring,
+            waypoint_highlight,
+            stations.iter().cloned().collect(),
+            region,
+            config.observed_currents,
+            clock,
         );
+        ui_state.max_path_segment = config.max_path_segment.map(Length::new::<mile>);
+        ui_state.tide_speed_profile = config.tide_speed_profile.unwrap_or_default();
+        ui_state.presentation_speed = config.presentation_speed;
+        ui_state.set_scale_factor(window.scale_factor());
+        if config.debug_panel {
+            ui_state.raw_responses = Some(RawResponseCache::new(start_date, duration_hours));
+        }
+        if let Some(planning_horizon_days) = config.planning_horizon_days {
+            ui_state.limit_planning_horizon = true;
+            ui_state.planning_horizon_days = planning_horizon_days;
+        }
+        if let Some(sharp_turn_warning) = config.sharp_turn_warning {
+            ui_state.warn_sharp_turns = true;
+            ui_state.sharp_turn_degrees = sharp_turn_warning;
+        }
+        // Before the shared plan's settings, which Reset Settings discards.
+        ui_state.default_settings = Some(Settings::of(&ui_state));
+        if let Some(plan) = &shared_plan {
+            plan.apply(&mut trip.write().unwrap());
+            if let Some(first) = plan.waypoints.first() {
+                galileo_state
+                    .write()
+                    .unwrap()
+                    .center_on(first.lat, first.lon);
+            }
+            ui_state.daytime = plan.daytime;
+            ui_state.limit_trip_duration = plan.max_trip_duration.is_some();
+            if let Some(max_trip_duration) = plan.max_trip_duration {
+                ui_state.max_trip_hours = max_trip_duration.get::<hour>();
+            }
+        }
 
         Ok(Self {
             surface,
@@ -276,14 +551,152 @@ impl State {
             galileo_state,
             ui_state,
             time_idx,
+            trip,
+            modifiers: ModifiersState::empty(),
+            output,
+            load_start,
+            prediction_request,
+            fetched_predictions,
+            current_predictions: Vec::new(),
+            smoothing_window: config.arrow_smoothing,
+            pending_stations: stations.len(),
         })
     }
 
+    /// Adds the arrows for current predictions fetched since the last call,
+    /// and hands all of them to the trip once every fetch has finished.
+    /// Stations whose fetches failed are refetched if the UI asked to.
+    fn receive_current_predictions(&mut self) {
+        if mem::take(&mut self.ui_state.retry_failed_stations) {
+            let failed = mem::take(&mut self.ui_state.failed_stations);
+            self.pending_stations += failed.len();
+            self.prediction_request.spawn(
+                failed.into_iter().map(|(station, _)| station),
+                &self.fetched_predictions,
+                &self.window,
+            );
+            self.update_loading_progress();
+        }
+
+        let fetched = mem::take(&mut *self.fetched_predictions.write().unwrap());
+        if fetched.is_empty() {
+            return;
+        }
+        self.pending_stations -= fetched.len();
+
+        for (station, prediction) in fetched {
+            // Arrows are drawn at the shared time index, so they need the
+            // same time grid as the trip.
+            let aligned = prediction.and_then(|prediction| {
+                let trip = self.trip.read().unwrap();
+                trip.align_prediction(prediction)
+                    .map_err(|err| err.to_string())
+            });
+            let prediction = match aligned {
+                Ok(prediction) => prediction,
+                Err(err) => {
+                    self.ui_state
+                        .failed_stations
+                        .push((station, StationFailure::of(err)));
+                    continue;
+                },
+            };
+
+            // Only the arrows are smoothed; trips use the predictions as is.
+            let shown = match self.smoothing_window {
+                Some(window) => prediction
+                    .smoothed(window)
+                    .unwrap_or_else(|_| prediction.clone()),
+                None => prediction.clone(),
+            };
+            self.galileo_state
+                .write()
+                .unwrap()
+                .add_current_prediction(shown);
+            self.current_predictions.push(prediction);
+        }
+        self.update_loading_progress();
+
+        if self.pending_stations == 0 {
+            // Kept so stations loaded by a retry are added to the rest.
+            let current_predictions = self.current_predictions.clone();
+            for (i, route) in self.ui_state.routes.iter_mut().enumerate() {
+                if i != self.ui_state.active_route {
+                    let _ = route
+                        .set_current_predictions(current_predictions.clone())
+                        .log();
+                }
+            }
+            let _ = self
+                .trip
+                .write()
+                .unwrap()
+                .set_current_predictions(current_predictions)
+                .log();
+
+            (self.ui_state.data_fetched_at, self.ui_state.data_from_cache) =
+                data_freshness(self.load_start);
+        }
+    }
+
+    fn update_loading_progress(&mut self) {
+        let loaded = self.current_predictions.len();
+        self.ui_state.current_predictions_loading =
+            (self.pending_stations > 0).then_some((loaded, loaded + self.pending_stations));
+    }
+
     pub fn window(&self) -> &Window {
         &self.window
     }
 
+    /// Saves the map view, e.g. on exit, in case it changed without settling.
+    pub fn save_view(&self) {
+        self.galileo_state.write().unwrap().save_view();
+    }
+
+    pub fn write_output(&self) {
+        let time_idx = *self.time_idx.read().unwrap();
+        match self.output {
+            Some(OutputFormat::Json) => {
+                println!(
+                    "{:#}",
+                    self.trip
+                        .write()
+                        .unwrap()
+                        .to_plan_json(time_idx.val(), self.ui_state.coordinate_system)
+                )
+            },
+            Some(OutputFormat::CurrentsGeoJson) => {
+                println!(
+                    "{:#}",
+                    self.trip.read().unwrap().currents_geojson(time_idx.row())
+                )
+            },
+            Some(OutputFormat::Stations) => {
+                println!("{:#}", Station::list_to_json(&self.ui_state.stations))
+            },
+            Some(OutputFormat::CurrentFieldCsv) => {
+                let field = self.trip.read().unwrap().current_field_dataframe();
+                if let Ok(csv) = field.and_then(|mut field| export::csv(&mut field)).log() {
+                    print!("{csv}");
+                }
+            },
+            #[cfg(not(target_arch = "wasm32"))]
+            Some(OutputFormat::CurrentFieldParquet) => {
+                let field = self.trip.read().unwrap().current_field_dataframe();
+                if let Ok(parquet) = field
+                    .and_then(|mut field| export::parquet(&mut field))
+                    .log()
+                {
+                    let _ = io::stdout().write_all(&parquet).log();
+                }
+            },
+            None => {},
+        }
+    }
+
     pub fn about_to_wait(&mut self) {
+        self.receive_current_predictions();
         self.galileo_state.read().unwrap().about_to_wait();
     }
 
@@ -297,9 +710,28 @@ impl State {
         }
     }
 
+    /// Reconfigures the surface and redraws the map after the page was
+    /// hidden, since mobile browsers may invalidate the surface meanwhile.
+    pub fn resume(&mut self) {
+        self.surface.configure(&self.device, &self.surface_config);
+        self.galileo_state.read().unwrap().redraw_map();
+    }
+
+    /// Rechecks the display's scale factor and redraws the map, whose hit
+    /// tolerances depend on it, e.g. after the window moves to another
+    /// monitor or the browser zoom changes. The resize that follows updates
+    /// the surface.
+    pub fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.ui_state.set_scale_factor(scale_factor);
+        self.galileo_state.read().unwrap().redraw_map();
+    }
+
     pub fn handle_event(&mut self, event: &WindowEvent) {
         // TODO: pass through other keys, e.g., F5 to refresh
         match event {
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers.state();
+            },
             WindowEvent::KeyboardInput {
                 event:
                     KeyEvent {
@@ -308,8 +740,8 @@ impl State {
                         ..
                     },
                 ..
-            } => {
-                if self.time_idx.write().unwrap().inc() {
+            } if !self.modifiers.shift_key() && !self.ui_state.lock_time => {
+                if self.step_time(true) {
                     self.galileo_state.read().unwrap().redraw_map();
                 }
             },
@@ -321,11 +753,35 @@ impl State {
                         ..
                     },
                 ..
-            } => {
-                if self.time_idx.write().unwrap().dec() {
+            } if !self.modifiers.shift_key() && !self.ui_state.lock_time => {
+                if self.step_time(false) {
                     self.galileo_state.read().unwrap().redraw_map();
                 }
             },
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        state: ElementState::Pressed,
+                        logical_key: Key::Named(key),
+                        ..
+                    },
+                ..
+            } if !self.egui_state.wants_keyboard_input() => {
+                self.handle_waypoint_key(*key);
+            },
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        state: ElementState::Pressed,
+                        logical_key: Key::Character(key),
+                        ..
+                    },
+                ..
+            } if !self.egui_state.wants_keyboard_input() => match key.as_str() {
+                "e" => self.extend_route(),
+                "p" => self.ui_state.toggle_presentation(),
+                key => self.handle_waypoint_mode_key(key),
+            },
             _ => (),
         }
 
@@ -338,8 +794,85 @@ impl State {
         self.window().request_redraw();
     }
 
+    /// Steps the time 5 minutes, or to the adjacent 30 minute row while Ctrl
+    /// is held.
+    fn step_time(&self, forward: bool) -> bool {
+        let mut time_idx = self.time_idx.write().unwrap();
+        if self.modifiers.control_key() {
+            time_idx.step_row(forward)
+        } else {
+            time_idx.step(forward)
+        }
+    }
+
+    fn handle_waypoint_key(&mut self, key: NamedKey) {
+        let shift = self.modifiers.shift_key();
+        // Arrow keys step the time unless it's locked.
+        let nudge = shift || self.ui_state.lock_time;
+        let mut trip = self.trip.write().unwrap();
+        let num_waypoints = trip.waypoints.len();
+        if num_waypoints == 0 {
+            return;
+        }
+
+        let selected = &mut self.ui_state.selected_waypoint;
+        match (key, *selected) {
+            (NamedKey::Tab, None) if shift => *selected = Some(num_waypoints - 1),
+            (NamedKey::Tab, None) => *selected = Some(0),
+            (NamedKey::Tab, Some(idx)) if shift => {
+                *selected = Some((idx + num_waypoints - 1) % num_waypoints)
+            },
+            (NamedKey::Tab, Some(idx)) => *selected = Some((idx + 1) % num_waypoints),
+            (NamedKey::Delete | NamedKey::Backspace, Some(idx)) => {
+                trip.remove_waypoint(idx);
+                *selected = None;
+            },
+            (
+                NamedKey::ArrowUp
+                | NamedKey::ArrowDown
+                | NamedKey::ArrowLeft
+                | NamedKey::ArrowRight,
+                Some(idx),
+            ) if nudge => {
+                let (dx, dy) = match key {
+                    NamedKey::ArrowUp => (0.0, 1.0),
+                    NamedKey::ArrowDown => (0.0, -1.0),
+                    NamedKey::ArrowLeft => (-1.0, 0.0),
+                    _ => (1.0, 0.0),
+                };
+                let offset =
+                    WAYPOINT_NUDGE_PIXELS * self.galileo_state.read().unwrap().logical_resolution();
+                let point = trip.waypoints[idx].point;
+                trip.move_waypoint(
+                    idx,
+                    Point2d::new(point.x + dx * offset, point.y + dy * offset),
+                );
+            },
+            _ => return,
+        }
+
+        self.galileo_state.read().unwrap().redraw();
+    }
+
+    fn extend_route(&mut self) {
+        if self.trip.write().unwrap().extend_route() {
+            self.galileo_state.read().unwrap().redraw();
+        }
+    }
+
+    fn handle_waypoint_mode_key(&mut self, key: &str) {
+        let waypoint_mode = match key {
+            "1" => WaypointClickAction::Move,
+            "2" => WaypointClickAction::Pause,
+            "3" => WaypointClickAction::Remove,
+            _ => return,
+        };
+        *self.ui_state.waypoint_mode.write().unwrap() = waypoint_mode;
+    }
+
     pub fn render(&mut self) -> Result<(), SurfaceError> {
         self.ui_state.pointer_position = self.galileo_state.read().unwrap().pointer_position();
+        self.ui_state.sync_selected_waypoint();
 
         let texture = self.surface.get_current_texture()?;
 
@@ -383,3 +916,27 @@ impl State {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use galileo_types::geo::impls::GeoPoint2d;
+    use galileo_types::geo::NewGeoPoint;
+
+    use super::*;
+    use crate::noaa::StationType;
+
+    #[test]
+    fn no_stations_message_only_without_stations() {
+        let station = Station {
+            id: "a".to_string(),
+            name: "a".to_string(),
+            loc: GeoPoint2d::latlon(40.70, -74.02),
+            type_: StationType::Harmonic,
+            api_proxy: None,
+        };
+
+        let message = no_stations_message("Somewhere", &[]).unwrap();
+        assert!(message.contains("Somewhere"), "{message}");
+        assert_eq!(no_stations_message("Somewhere", &[station]), None);
+    }
+}
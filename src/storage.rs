@@ -0,0 +1,57 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::PathBuf;
+
+use crate::prelude::*;
+
+/// State is kept in the user's data directory, e.g. `~/.local/share/kayaknav`
+/// on Linux, rather than anywhere shared with other users.
+#[cfg(not(target_arch = "wasm32"))]
+fn state_dir() -> Option<PathBuf> {
+    Some(dirs::data_dir()?.join("kayaknav"))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn state_path(key: &str) -> Option<PathBuf> {
+    Some(state_dir()?.join(format!("{key}.json")))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load(key: &str) -> Option<String> {
+    fs::read_to_string(state_path(key)?).ok()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn store(key: &str, value: &str) -> Result<()> {
+    let dir = state_dir()
+        .ok_or_else(|| anyhow!("No data directory to store {key:?} in"))
+        .log()?;
+    fs::create_dir_all(&dir).log()?;
+    fs::write(dir.join(format!("{key}.json")), value).log()?;
+    Ok(())
+}
+
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+fn storage_key(key: &str) -> String {
+    format!("kayaknav.{key}")
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn load(key: &str) -> Option<String> {
+    local_storage()?.get_item(&storage_key(key)).ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn store(key: &str, value: &str) -> Result<()> {
+    local_storage()
+        .log()?
+        .set_item(&storage_key(key), value)
+        .map_err(|err| anyhow!("Error writing {key:?} to localStorage: {err:?}"))
+        .log()
+}
@@ -0,0 +1,247 @@
+use crate::saturating::Saturating;
+
+/// Minutes between prediction rows, the resolution predictions are fetched at.
+const ROW_MINUTES: usize = 30;
+
+/// Minutes between steps, the resolution trips are integrated at.
+const STEP_MINUTES: usize = 5;
+
+const _: () = assert!(ROW_MINUTES % STEP_MINUTES == 0);
+
+/// Number of 5 minute steps, the resolution trips are integrated at, in each
+/// 30 minute prediction row.
+pub const STEPS_PER_ROW: usize = ROW_MINUTES / STEP_MINUTES;
+
+/// The 5 minute step at the start of the 30 minute prediction `row`.
+pub fn row_to_step(row: usize) -> usize {
+    row * STEPS_PER_ROW
+}
+
+/// Milliseconds between 30 minute prediction rows.
+pub const ROW_MILLIS: i64 = ROW_MINUTES as i64 * 60 * 1000;
+
+/// Milliseconds between 5 minute steps.
+pub const STEP_MILLIS: i64 = STEP_MINUTES as i64 * 60 * 1000;
+
+/// The selected time, as an index into the 5 minute grid that the time
+/// scrubber steps through. Every `STEPS_PER_ROW`th step is a 30 minute
+/// prediction row, as shown in the station table.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct TimeIdx {
+    idx: Saturating<usize>,
+    /// The last row with predictions.
+    data_last_row: usize,
+    /// The last row of the planning horizon, if times past it are hidden.
+    horizon_row: Option<usize>,
+}
+
+impl TimeIdx {
+    /// Starts at the first row and allows times up to `last_row`.
+    pub fn new(last_row: usize) -> Self {
+        Self {
+            idx: Saturating::new(0, 0, row_to_step(last_row)),
+            data_last_row: last_row,
+            horizon_row: None,
+        }
+    }
+
+    /// Index into the 5 minute grid.
+    pub fn val(&self) -> usize {
+        self.idx.val()
+    }
+
+    /// Index of the 30 minute row at or before the selected time.
+    pub fn row(&self) -> usize {
+        self.idx.val() / STEPS_PER_ROW
+    }
+
+    /// The selected time in fractional 30 minute rows, for interpolating
+    /// between them.
+    pub fn row_pos(&self) -> f64 {
+        self.idx.val() as f64 / STEPS_PER_ROW as f64
+    }
+
+    /// The last selectable row, which may be before the last row with
+    /// predictions if a planning horizon is set.
+    pub fn last_row(&self) -> usize {
+        self.idx.upper_bound() / STEPS_PER_ROW
+    }
+
+    pub fn upper_bound(&self) -> usize {
+        self.idx.upper_bound()
+    }
+
+    /// Hides times after `horizon_row`, or shows every time with predictions
+    /// if it's `None`, moving the selected time back if it's past the
+    /// horizon. Returns whether the selected time changed.
+    pub fn set_horizon(&mut self, horizon_row: Option<usize>) -> bool {
+        if self.horizon_row == horizon_row {
+            return false;
+        }
+        self.horizon_row = horizon_row;
+        let val = self.idx.val();
+        self.update_bounds();
+        self.idx.val() != val
+    }
+
+    fn update_bounds(&mut self) {
+        let last_row = self.horizon_row.map_or(self.data_last_row, |horizon_row| {
+            horizon_row.min(self.data_last_row)
+        });
+        self.idx = Saturating::new(self.idx.val(), 0, row_to_step(last_row));
+    }
+
+    pub fn set(&mut self, idx: usize) -> bool {
+        self.idx.set(idx)
+    }
+
+    pub fn set_row(&mut self, row: usize) -> bool {
+        self.idx.set(row.saturating_mul(STEPS_PER_ROW))
+    }
+
+    /// Steps 5 minutes forward or back.
+    pub fn step(&mut self, forward: bool) -> bool {
+        if forward {
+            self.idx.inc()
+        } else {
+            self.idx.dec()
+        }
+    }
+
+    /// Steps to the next or previous 30 minute row.
+    pub fn step_row(&mut self, forward: bool) -> bool {
+        if forward {
+            self.set_row(self.row() + 1)
+        } else {
+            self.set_row(self.val().div_ceil(STEPS_PER_ROW).saturating_sub(1))
+        }
+    }
+
+    pub fn with_val_mut<T>(&mut self, f: impl FnOnce(&mut usize) -> T) -> T {
+        self.idx.with_val_mut(f)
+    }
+}
+
+/// Time, in milliseconds, of the 5 minute `idx` given the times of the 30
+/// minute rows.
+pub fn time_at(time_vec: &[i64], idx: usize) -> Option<i64> {
+    let row_time = time_vec.get(idx / STEPS_PER_ROW)?;
+    Some(row_time + (idx % STEPS_PER_ROW) as i64 * STEP_MILLIS)
+}
+
+/// Index of the 5 minute step nearest `time`, preferring the earlier one on a
+/// tie, given the times of the 30 minute rows, which must be sorted ascending.
+/// Times outside the rows are clamped to them. Returns None if `time_vec` is
+/// empty.
+pub fn nearest_step(time_vec: &[i64], time: i64) -> Option<usize> {
+    let last_row = time_vec.len().checked_sub(1)?;
+    // The row at or before `time`, found by binary search.
+    let row = time_vec.partition_point(|t| *t <= time).saturating_sub(1);
+    let offset = time - time_vec[row];
+    let step = row_to_step(row) as i64 + (offset + STEP_MILLIS / 2 - 1).div_euclid(STEP_MILLIS);
+    Some((step.max(0) as usize).min(row_to_step(last_row)))
+}
+
+/// Like `nearest_step`, but returns None rather than clamping if `time` is
+/// more than half a step outside the rows.
+pub fn nearest_step_in_range(time_vec: &[i64], time: i64) -> Option<usize> {
+    let (first, last) = (time_vec.first()?, time_vec.last()?);
+    let half_step = STEP_MILLIS / 2;
+    if time < first - half_step || time > last + half_step {
+        return None;
+    }
+    nearest_step(time_vec, time)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE_MILLIS: i64 = 60 * 1000;
+
+    #[test]
+    fn six_steps_per_row() {
+        assert_eq!(STEPS_PER_ROW, 6);
+        assert_eq!(ROW_MILLIS, 6 * STEP_MILLIS);
+        for row in [0, 1, 7, 1000] {
+            assert_eq!(row_to_step(row), 6 * row);
+        }
+    }
+
+    /// Three 30 minute rows, starting 1000 minutes after the epoch.
+    fn time_vec() -> Vec<i64> {
+        (0..3)
+            .map(|row| (1000 + 30 * row) * MINUTE_MILLIS)
+            .collect()
+    }
+
+    #[test]
+    fn nearest_step_exact() {
+        assert_eq!(nearest_step(&time_vec(), 1000 * MINUTE_MILLIS), Some(0));
+        assert_eq!(nearest_step(&time_vec(), 1035 * MINUTE_MILLIS), Some(7));
+        assert_eq!(nearest_step(&time_vec(), 1060 * MINUTE_MILLIS), Some(12));
+    }
+
+    #[test]
+    fn nearest_step_between_steps() {
+        assert_eq!(nearest_step(&time_vec(), 1012 * MINUTE_MILLIS), Some(2));
+        assert_eq!(nearest_step(&time_vec(), 1013 * MINUTE_MILLIS), Some(3));
+        // Halfway between steps 2 and 3.
+        assert_eq!(
+            nearest_step(&time_vec(), 1010 * MINUTE_MILLIS + STEP_MILLIS / 2),
+            Some(2)
+        );
+    }
+
+    #[test]
+    fn nearest_step_before_range() {
+        assert_eq!(nearest_step(&time_vec(), 0), Some(0));
+        assert_eq!(nearest_step(&time_vec(), 999 * MINUTE_MILLIS), Some(0));
+    }
+
+    #[test]
+    fn nearest_step_after_range() {
+        assert_eq!(nearest_step(&time_vec(), 1061 * MINUTE_MILLIS), Some(12));
+        assert_eq!(nearest_step(&time_vec(), i64::MAX / 2), Some(12));
+    }
+
+    #[test]
+    fn nearest_step_counts_from_the_row_before() {
+        // The second row is an hour after the first, so the steps after it
+        // follow its time rather than the first row's.
+        let time_vec = [
+            1000 * MINUTE_MILLIS,
+            1060 * MINUTE_MILLIS,
+            1090 * MINUTE_MILLIS,
+        ];
+        assert_eq!(nearest_step(&time_vec, 1060 * MINUTE_MILLIS), Some(6));
+        assert_eq!(nearest_step(&time_vec, 1065 * MINUTE_MILLIS), Some(7));
+        assert_eq!(nearest_step(&time_vec, 1090 * MINUTE_MILLIS), Some(12));
+    }
+
+    #[test]
+    fn nearest_step_empty() {
+        assert_eq!(nearest_step(&[], 1000 * MINUTE_MILLIS), None);
+    }
+
+    #[test]
+    fn nearest_step_in_range_rejects_times_outside() {
+        assert_eq!(
+            nearest_step_in_range(&time_vec(), 999 * MINUTE_MILLIS),
+            Some(0)
+        );
+        assert_eq!(
+            nearest_step_in_range(&time_vec(), 1061 * MINUTE_MILLIS),
+            Some(12)
+        );
+        assert_eq!(
+            nearest_step_in_range(&time_vec(), 997 * MINUTE_MILLIS),
+            None
+        );
+        assert_eq!(
+            nearest_step_in_range(&time_vec(), 1063 * MINUTE_MILLIS),
+            None
+        );
+        assert_eq!(nearest_step_in_range(&[], 1000 * MINUTE_MILLIS), None);
+    }
+}